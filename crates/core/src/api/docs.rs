@@ -47,6 +47,8 @@ use utoipa_swagger_ui::SwaggerUi;
                 search::SidebarQuery,
                 search::SpellcheckQuery,
                 crate::searcher::WebsitesResult,
+                crate::searcher::HostFacet,
+                crate::searcher::ReturnField,
                 crate::search_prettifier::HighlightedSpellCorrection,
                 crate::search_prettifier::DisplayedWebpage,
                 crate::search_prettifier::DisplayedEntity,
@@ -64,6 +66,8 @@ use utoipa_swagger_ui::SwaggerUi;
                 crate::entity_index::entity::EntitySnippet,
                 crate::entity_index::entity::EntitySnippetFragment,
 
+                crate::inverted_index::PhraseMatch,
+
                 crate::bangs::UrlWrapper,
 
                 crate::widgets::Widget,
@@ -117,6 +117,10 @@ pub async fn router(config: &ApiConfig, counters: Counters) -> Result<Router> {
         )
         .await?,
     );
+    cluster
+        .start_health_checks(config.health_checks.clone())
+        .await;
+
     let remote_webgraph = RemoteWebgraph::new(cluster.clone());
 
     let dist_searcher = DistributedSearcher::new(Arc::clone(&cluster));
@@ -25,7 +25,7 @@ use axum_macros::debug_handler;
 
 use crate::{
     bangs::BangHit,
-    searcher::{self, SearchQuery, SearchResult, WebsitesResult},
+    searcher::{self, ReturnField, SearchQuery, SearchResult, WebsitesResult},
     webpage::region::Region,
 };
 
@@ -53,6 +53,22 @@ pub struct ApiSearchQuery {
 
     #[serde(default = "defaults::SearchQuery::count_results")]
     pub count_results: bool,
+
+    pub explain_url: Option<String>,
+    pub host_facets: Option<usize>,
+    pub min_score: Option<f32>,
+
+    #[serde(default = "defaults::SearchQuery::normalize_scores")]
+    pub normalize_scores: bool,
+
+    pub fields: Option<Vec<ReturnField>>,
+    pub collapse_field: Option<ReturnField>,
+
+    #[serde(default = "defaults::SearchQuery::case_sensitive_phrases")]
+    pub case_sensitive_phrases: bool,
+
+    #[serde(default)]
+    pub restrict_to_sites: Vec<String>,
 }
 
 impl TryFrom<ApiSearchQuery> for SearchQuery {
@@ -77,6 +93,14 @@ impl TryFrom<ApiSearchQuery> for SearchQuery {
             return_ranking_signals: api.return_ranking_signals,
             safe_search: api.safe_search.unwrap_or(default.safe_search),
             count_results: api.count_results,
+            explain_url: api.explain_url,
+            host_facets: api.host_facets,
+            min_score: api.min_score,
+            normalize_scores: api.normalize_scores,
+            fields: api.fields,
+            collapse_field: api.collapse_field,
+            case_sensitive_phrases: api.case_sensitive_phrases,
+            restrict_to_sites: api.restrict_to_sites,
         })
     }
 }
@@ -39,13 +39,11 @@ impl RemoteWebgraph {
 
     async fn host(&self, level: WebgraphGranularity) -> Option<SocketAddr> {
         self.cluster
-            .members()
-            .await
-            .iter()
-            .find_map(|member| match member.service {
-                Service::Webgraph { host, granularity } if granularity == level => Some(host),
+            .find_service(|service| match service {
+                Service::Webgraph { host, granularity } if *granularity == level => Some(*host),
                 _ => None,
             })
+            .await
     }
 }
 
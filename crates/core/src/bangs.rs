@@ -109,9 +109,13 @@ impl Bangs {
         }
     }
 
-    pub fn get(&self, terms: &[Box<Term>]) -> Option<BangHit> {
+    pub fn get(&self, terms: &[Term]) -> Option<BangHit> {
         for possible_bang in terms.iter().filter_map(|term| {
-            if let Term::PossibleBang(possible_bang) = term.as_ref() {
+            if let Term::PossibleBang {
+                bang: possible_bang,
+                ..
+            } = term
+            {
                 Some(possible_bang)
             } else {
                 None
@@ -122,13 +126,13 @@ impl Bangs {
                     terms
                         .iter()
                         .filter(|term| {
-                            if let Term::PossibleBang(bang) = term.as_ref() {
+                            if let Term::PossibleBang { bang, .. } = term {
                                 bang != possible_bang
                             } else {
                                 true
                             }
                         })
-                        .map(|term| term.as_ref().to_string()),
+                        .map(|term| term.to_string()),
                     " ".to_string(),
                 )
                 .collect::<String>();
@@ -154,7 +158,7 @@ impl Bangs {
 #[cfg(test)]
 mod tests {
 
-    use crate::query::parser::parse;
+    use crate::query::parser::parse_terms;
 
     use super::*;
 
@@ -172,11 +176,11 @@ mod tests {
         }]"#,
         );
 
-        assert_eq!(bangs.get(&parse("no bangs")), None);
-        assert_eq!(bangs.get(&parse("!no bangs")), None);
+        assert_eq!(bangs.get(&parse_terms("no bangs")), None);
+        assert_eq!(bangs.get(&parse_terms("!no bangs")), None);
 
         assert_eq!(
-            bangs.get(&parse("!ty bangs")),
+            bangs.get(&parse_terms("!ty bangs")),
             Some(BangHit {
                 bang: Bang {
                     category: Some("Multimedia".to_string()),
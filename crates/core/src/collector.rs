@@ -45,6 +45,7 @@ pub struct MaxDocsConsidered {
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Hashes {
     pub site: Prehashed,
+    pub domain: Prehashed,
     pub title: Prehashed,
     pub url: Prehashed,
     pub url_without_tld: Prehashed,
@@ -176,6 +177,7 @@ impl TopSegmentCollector {
         self.bucket_collector.insert(SegmentDoc {
             hashes: Hashes {
                 site: self.get_hash(&doc, &FastField::SiteHash1, &FastField::SiteHash2),
+                domain: self.get_hash(&doc, &FastField::DomainHash1, &FastField::DomainHash2),
                 title: self.get_hash(&doc, &FastField::TitleHash1, &FastField::TitleHash2),
                 url: self.get_hash(&doc, &FastField::UrlHash1, &FastField::UrlHash2),
                 url_without_tld: self.get_hash(
@@ -471,6 +473,193 @@ where
     }
 }
 
+/// Number of matching documents for a site along with one of the matching
+/// documents that can be used to look up the name of the site.
+#[derive(Debug, Clone)]
+pub struct SiteCount {
+    pub count: u64,
+    pub example: DocAddress,
+}
+
+/// Counts the number of matching documents per site (based on the site hash fast fields)
+/// and keeps the `top_n` sites with the most matches.
+pub struct HostFacetCollector {
+    top_n: usize,
+    fastfield_reader: fastfield_reader::FastFieldReader,
+}
+
+impl HostFacetCollector {
+    pub fn new(top_n: usize, fastfield_reader: fastfield_reader::FastFieldReader) -> Self {
+        Self {
+            top_n,
+            fastfield_reader,
+        }
+    }
+}
+
+impl Collector for HostFacetCollector {
+    type Fruit = Vec<SiteCount>;
+
+    type Child = HostFacetSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: u32,
+        segment_reader: &SegmentReader,
+    ) -> tantivy::Result<Self::Child> {
+        Ok(HostFacetSegmentCollector {
+            fastfield_segment_reader: self
+                .fastfield_reader
+                .get_segment(&segment_reader.segment_id()),
+            segment_ord: segment_local_id,
+            counts: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut counts: HashMap<u128, SiteCount> = HashMap::new();
+
+        for fruit in segment_fruits {
+            for (site, site_count) in fruit {
+                counts
+                    .entry(site)
+                    .and_modify(|c| c.count += site_count.count)
+                    .or_insert(site_count);
+            }
+        }
+
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|(a_site, a), (b_site, b)| b.count.cmp(&a.count).then(a_site.cmp(b_site)));
+
+        Ok(counts
+            .into_iter()
+            .take(self.top_n)
+            .map(|(_, count)| count)
+            .collect())
+    }
+}
+
+pub struct HostFacetSegmentCollector {
+    fastfield_segment_reader: Arc<fastfield_reader::SegmentReader>,
+    segment_ord: SegmentOrdinal,
+    counts: HashMap<u128, SiteCount>,
+}
+
+impl SegmentCollector for HostFacetSegmentCollector {
+    type Fruit = HashMap<u128, SiteCount>;
+
+    fn collect(&mut self, doc: DocId, _: tantivy::Score) {
+        let field_reader = self.fastfield_segment_reader.get_field_reader(&doc);
+        let site = combine_u64s([
+            field_reader.get(&FastField::SiteHash1).into(),
+            field_reader.get(&FastField::SiteHash2).into(),
+        ]);
+
+        let segment = self.segment_ord;
+        self.counts
+            .entry(site)
+            .and_modify(|c| c.count += 1)
+            .or_insert_with(|| SiteCount {
+                count: 1,
+                example: DocAddress {
+                    segment,
+                    doc_id: doc,
+                },
+            });
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
+/// Counts the number of matching documents in each group, where the group of a document
+/// is the hash stored in the two `hash_fields` (e.g. the site hash).
+pub struct GroupCountCollector {
+    hash_fields: [FastField; 2],
+    fastfield_reader: fastfield_reader::FastFieldReader,
+}
+
+impl GroupCountCollector {
+    pub fn new(
+        hash_fields: [FastField; 2],
+        fastfield_reader: fastfield_reader::FastFieldReader,
+    ) -> Self {
+        Self {
+            hash_fields,
+            fastfield_reader,
+        }
+    }
+}
+
+impl Collector for GroupCountCollector {
+    type Fruit = HashMap<Prehashed, u64>;
+
+    type Child = GroupCountSegmentCollector;
+
+    fn for_segment(&self, _: u32, segment_reader: &SegmentReader) -> tantivy::Result<Self::Child> {
+        Ok(GroupCountSegmentCollector {
+            fastfield_segment_reader: self
+                .fastfield_reader
+                .get_segment(&segment_reader.segment_id()),
+            hash_fields: self.hash_fields,
+            counts: HashMap::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
+    ) -> tantivy::Result<Self::Fruit> {
+        let mut counts = HashMap::new();
+
+        for fruit in segment_fruits {
+            for (group, count) in fruit {
+                *counts.entry(group).or_default() += count;
+            }
+        }
+
+        Ok(counts)
+    }
+}
+
+pub struct GroupCountSegmentCollector {
+    fastfield_segment_reader: Arc<fastfield_reader::SegmentReader>,
+    hash_fields: [FastField; 2],
+    counts: HashMap<Prehashed, u64>,
+}
+
+impl SegmentCollector for GroupCountSegmentCollector {
+    type Fruit = HashMap<Prehashed, u64>;
+
+    fn collect(&mut self, doc: DocId, _: tantivy::Score) {
+        let field_reader = self.fastfield_segment_reader.get_field_reader(&doc);
+        let [field1, field2] = &self.hash_fields;
+        let group: Prehashed = combine_u64s([
+            field_reader.get(field1).into(),
+            field_reader.get(field2).into(),
+        ])
+        .into();
+
+        *self.counts.entry(group).or_default() += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        self.counts
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -504,6 +693,7 @@ mod tests {
                 (
                     Hashes {
                         site: 1.into(),
+                        domain: 1.into(),
                         title: 1.into(),
                         url: 1.into(),
                         url_without_tld: 1.into(),
@@ -515,6 +705,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -526,6 +717,7 @@ mod tests {
                 (
                     Hashes {
                         site: 3.into(),
+                        domain: 3.into(),
                         title: 3.into(),
                         url: 3.into(),
                         url_without_tld: 3.into(),
@@ -537,6 +729,7 @@ mod tests {
                 (
                     Hashes {
                         site: 4.into(),
+                        domain: 4.into(),
                         title: 4.into(),
                         url: 4.into(),
                         url_without_tld: 4.into(),
@@ -548,6 +741,7 @@ mod tests {
                 (
                     Hashes {
                         site: 5.into(),
+                        domain: 5.into(),
                         title: 5.into(),
                         url: 5.into(),
                         url_without_tld: 5.into(),
@@ -569,6 +763,7 @@ mod tests {
                 (
                     Hashes {
                         site: 3.into(),
+                        domain: 3.into(),
                         title: 3.into(),
                         url: 3.into(),
                         url_without_tld: 3.into(),
@@ -580,6 +775,7 @@ mod tests {
                 (
                     Hashes {
                         site: 4.into(),
+                        domain: 4.into(),
                         title: 4.into(),
                         url: 4.into(),
                         url_without_tld: 4.into(),
@@ -591,6 +787,7 @@ mod tests {
                 (
                     Hashes {
                         site: 5.into(),
+                        domain: 5.into(),
                         title: 5.into(),
                         url: 5.into(),
                         url_without_tld: 5.into(),
@@ -612,6 +809,7 @@ mod tests {
                 (
                     Hashes {
                         site: 1.into(),
+                        domain: 1.into(),
                         title: 1.into(),
                         url: 1.into(),
                         url_without_tld: 1.into(),
@@ -623,6 +821,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -634,6 +833,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -652,6 +852,7 @@ mod tests {
                 (
                     Hashes {
                         site: 1.into(),
+                        domain: 1.into(),
                         title: 1.into(),
                         url: 1.into(),
                         url_without_tld: 1.into(),
@@ -663,6 +864,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -674,6 +876,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -695,6 +898,7 @@ mod tests {
                 (
                     Hashes {
                         site: 1.into(),
+                        domain: 1.into(),
                         title: 1.into(),
                         url: 1.into(),
                         url_without_tld: 1.into(),
@@ -706,6 +910,7 @@ mod tests {
                 (
                     Hashes {
                         site: 2.into(),
+                        domain: 2.into(),
                         title: 2.into(),
                         url: 2.into(),
                         url_without_tld: 2.into(),
@@ -717,6 +922,7 @@ mod tests {
                 (
                     Hashes {
                         site: 3.into(),
+                        domain: 3.into(),
                         title: 3.into(),
                         url: 3.into(),
                         url_without_tld: 3.into(),
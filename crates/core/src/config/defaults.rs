@@ -14,6 +14,10 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::collections::HashMap;
+
+use crate::schema::TextField;
+
 pub struct Collector;
 
 impl Collector {
@@ -126,6 +130,18 @@ impl WebgraphServer {
     }
 }
 
+pub struct Indexing;
+
+impl Indexing {
+    pub fn allowed_content_types() -> Vec<String> {
+        vec!["text/html".to_string()]
+    }
+
+    pub fn max_token_len() -> usize {
+        crate::tokenizer::DEFAULT_MAX_TOKEN_LEN
+    }
+}
+
 pub struct SearchQuery;
 
 impl SearchQuery {
@@ -144,6 +160,26 @@ impl SearchQuery {
     pub fn count_results() -> bool {
         false
     }
+
+    pub fn normalize_scores() -> bool {
+        false
+    }
+
+    pub fn case_sensitive_phrases() -> bool {
+        false
+    }
+}
+
+pub struct Query;
+
+impl Query {
+    pub fn negations_match_all() -> bool {
+        true
+    }
+
+    pub fn compound_boosts() -> HashMap<String, f32> {
+        HashMap::from([(TextField::Title.name().to_string(), 2.0)])
+    }
 }
 
 pub struct Correction;
@@ -169,3 +205,39 @@ impl Widgets {
         true
     }
 }
+
+pub struct HealthCheck;
+
+impl HealthCheck {
+    pub fn interval_ms() -> u64 {
+        5_000
+    }
+
+    pub fn timeout_ms() -> u64 {
+        1_000
+    }
+
+    pub fn failure_threshold() -> usize {
+        3
+    }
+}
+
+pub struct ResultCache;
+
+impl ResultCache {
+    pub fn capacity() -> usize {
+        1_000
+    }
+
+    pub fn ttl_secs() -> u64 {
+        60
+    }
+}
+
+pub struct S3;
+
+impl S3 {
+    pub fn force_path_style() -> bool {
+        true
+    }
+}
@@ -20,6 +20,7 @@ use super::Result;
 use crate::feed::scheduler::SplitId;
 use crate::searcher::ShardId;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::net::SocketAddr;
@@ -38,6 +39,57 @@ pub struct IndexingLocalConfig {
     pub page_centrality_store_path: Option<String>,
     pub safety_classifier_path: Option<String>,
     pub minimum_clean_words: Option<usize>,
+    #[serde(default)]
+    pub duplicate_records: DuplicateRecordStrategy,
+    pub max_html_size: Option<usize>,
+    /// Add the terms of the indexed pages to a spelling dictionary
+    /// stored in `<output_path>/term_dict`.
+    #[serde(default)]
+    pub build_term_dict: bool,
+    /// Index the character n-grams of the urls so `inurl:` matches substrings
+    /// within url tokens, like part of a slug. This makes the index noticeably larger.
+    #[serde(default)]
+    pub url_char_ngrams: bool,
+    /// Index the clean body with its case preserved so phrases can be matched
+    /// case sensitively. This makes the index noticeably larger.
+    #[serde(default)]
+    pub cased_body: bool,
+    /// Display and match titles without a trailing site name, like "Result | Example.com".
+    /// The suffix is only removed if it matches the domain of the page.
+    #[serde(default)]
+    pub clean_titles: bool,
+    /// Number of warc batches to index in parallel. Defaults to the number of cpus.
+    pub num_threads: Option<usize>,
+    /// Content types of the records that should be indexed. Records are always
+    /// parsed as html, so other types should only be added if they can be parsed as such.
+    /// Records without a known content type are indexed regardless.
+    #[serde(default = "defaults::Indexing::allowed_content_types")]
+    pub allowed_content_types: Vec<String>,
+    /// Longest token in bytes that is indexed. The limit is saved with the index
+    /// and used by the search servers for the index.
+    #[serde(default = "defaults::Indexing::max_token_len")]
+    pub max_token_len: usize,
+}
+
+/// Which record to keep when the same url occurs multiple times in a warc file.
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateRecordStrategy {
+    #[default]
+    KeepFirst,
+    KeepLast,
+}
+
+/// How the files of an existing index are accessed.
+#[derive(Serialize, Deserialize, Default, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexOpenMode {
+    /// Memory-map the files and let the OS decide what to keep in memory.
+    #[default]
+    Mmap,
+    /// Read all files into memory when the index is opened. Changes to the index
+    /// are not persisted to disk in this mode, so it should only be used for serving.
+    Ram,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -72,21 +124,7 @@ impl WarcSource {
                 warc_paths = config.names.clone();
             }
             WarcSource::S3(config) => {
-                let bucket = s3::Bucket::new(
-                    &config.bucket,
-                    s3::Region::Custom {
-                        region: "".to_string(),
-                        endpoint: config.endpoint.clone(),
-                    },
-                    s3::creds::Credentials {
-                        access_key: Some(config.access_key.clone()),
-                        secret_key: Some(config.secret_key.clone()),
-                        security_token: None,
-                        session_token: None,
-                        expiration: None,
-                    },
-                )?
-                .with_path_style();
+                let bucket = config.bucket()?;
 
                 let mut folder = config.folder.clone();
 
@@ -127,6 +165,18 @@ pub struct LocalConfig {
 pub struct HttpConfig {
     pub base_url: String,
     pub warc_paths_file: String,
+
+    /// User-agent sent when downloading warc files. Defaults to the user-agent of reqwest.
+    #[serde(default)]
+    pub user_agent: Option<String>,
+
+    /// Sent as `Authorization: Bearer <token>` when downloading warc files.
+    #[serde(default)]
+    pub bearer_token: Option<String>,
+
+    /// Additional headers sent when downloading warc files.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -136,6 +186,40 @@ pub struct S3Config {
     pub access_key: String,
     pub secret_key: String,
     pub endpoint: String,
+
+    /// Region to sign requests for. Most s3 compatible stores accept an empty region.
+    #[serde(default)]
+    pub region: String,
+
+    /// Address the bucket as `<endpoint>/<bucket>` instead of `<bucket>.<endpoint>`.
+    /// Needed by most self-hosted stores like MinIO and Ceph.
+    #[serde(default = "defaults::S3::force_path_style")]
+    pub force_path_style: bool,
+}
+
+impl S3Config {
+    pub fn bucket(&self) -> Result<s3::Bucket> {
+        let bucket = s3::Bucket::new(
+            &self.bucket,
+            s3::Region::Custom {
+                region: self.region.clone(),
+                endpoint: self.endpoint.clone(),
+            },
+            s3::creds::Credentials {
+                access_key: Some(self.access_key.clone()),
+                secret_key: Some(self.secret_key.clone()),
+                security_token: None,
+                session_token: None,
+                expiration: None,
+            },
+        )?;
+
+        if self.force_path_style {
+            Ok(bucket.with_path_style())
+        } else {
+            Ok(bucket)
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -168,6 +252,28 @@ impl Default for CollectorConfig {
     }
 }
 
+/// Cache of the results of identical queries in the local searcher.
+/// The cache is cleared whenever the index is committed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResultCacheConfig {
+    /// Maximum number of cached results. The oldest results are evicted first.
+    #[serde(default = "defaults::ResultCache::capacity")]
+    pub capacity: usize,
+
+    /// How long a result stays in the cache.
+    #[serde(default = "defaults::ResultCache::ttl_secs")]
+    pub ttl_secs: u64,
+}
+
+impl Default for ResultCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: defaults::ResultCache::capacity(),
+            ttl_secs: defaults::ResultCache::ttl_secs(),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ApiThresholds {
     #[serde(default = "defaults::Api::stackoverflow")]
@@ -220,6 +326,42 @@ pub struct ApiConfig {
 
     #[serde(default)]
     pub correction_config: CorrectionConfig,
+
+    #[serde(default)]
+    pub health_checks: HealthCheckConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueryConfig {
+    /// Names of the fields searched by terms without a field operator (like `intitle:`).
+    /// All searchable fields are used if not set.
+    pub default_fields: Option<Vec<String>>,
+
+    /// Names of the fields searched by phrases. Restricting phrases to content fields
+    /// avoids matches in noisy fields like the url. The default fields are used if not set.
+    #[serde(default)]
+    pub phrase_fields: Option<Vec<String>>,
+
+    /// Whether queries with only negated terms (like `-spam`) match all documents
+    /// except the excluded ones. Otherwise they match nothing.
+    #[serde(default = "defaults::Query::negations_match_all")]
+    pub negations_match_all: bool,
+
+    /// Boost of compound matches (like `newyork` for the query `new york`) by field name.
+    /// Compound matches in fields that are not listed are not boosted.
+    #[serde(default = "defaults::Query::compound_boosts")]
+    pub compound_boosts: HashMap<String, f32>,
+}
+
+impl Default for QueryConfig {
+    fn default() -> Self {
+        Self {
+            default_fields: None,
+            phrase_fields: None,
+            negations_match_all: defaults::Query::negations_match_all(),
+            compound_boosts: defaults::Query::compound_boosts(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -274,11 +416,21 @@ pub struct SearchServerConfig {
     pub lambda_model_path: Option<String>,
     pub host: SocketAddr,
 
+    #[serde(default)]
+    pub index_open_mode: IndexOpenMode,
+
     #[serde(default)]
     pub collector: CollectorConfig,
 
     #[serde(default)]
     pub snippet: SnippetConfig,
+
+    #[serde(default)]
+    pub query: QueryConfig,
+
+    /// Results are not cached if not set.
+    #[serde(default)]
+    pub result_cache: Option<ResultCacheConfig>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -426,6 +578,9 @@ pub struct LiveIndexConfig {
     pub safety_classifier_path: Option<String>,
     pub host_centrality_threshold: Option<f64>,
     pub minimum_clean_words: Option<usize>,
+    /// Longest token in bytes that is indexed and searched for.
+    #[serde(default = "defaults::Indexing::max_token_len")]
+    pub max_token_len: usize,
 
     // search
     pub cluster_id: String,
@@ -482,6 +637,17 @@ pub struct CorrectionConfig {
     /// corrected
     #[serde(default = "defaults::Correction::correction_threshold")]
     pub correction_threshold: f64,
+
+    /// Number of summed term frequencies to keep cached in memory
+    /// for each language. Caching is disabled if not set.
+    #[serde(default)]
+    pub freq_cache_size: Option<usize>,
+
+    /// Merge the term dictionary of each language into a single
+    /// dictionary when it is opened with more than this many dictionaries.
+    /// The dictionaries are never merged if not set.
+    #[serde(default)]
+    pub compact_term_dict_above: Option<usize>,
 }
 
 impl Default for CorrectionConfig {
@@ -490,6 +656,85 @@ impl Default for CorrectionConfig {
             misspelled_prob: defaults::Correction::misspelled_prob(),
             lm_prob_weight: defaults::Correction::lm_prob_weight(),
             correction_threshold: defaults::Correction::correction_threshold(),
+            freq_cache_size: None,
+            compact_term_dict_above: None,
         }
     }
 }
+
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub struct HealthCheckConfig {
+    #[serde(default = "defaults::HealthCheck::interval_ms")]
+    pub interval_ms: u64,
+
+    #[serde(default = "defaults::HealthCheck::timeout_ms")]
+    pub timeout_ms: u64,
+
+    /// Number of consecutive failed checks before a cluster member
+    /// is considered unhealthy
+    #[serde(default = "defaults::HealthCheck::failure_threshold")]
+    pub failure_threshold: usize,
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self {
+            interval_ms: defaults::HealthCheck::interval_ms(),
+            timeout_ms: defaults::HealthCheck::timeout_ms(),
+            failure_threshold: defaults::HealthCheck::failure_threshold(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s3_config(json: &str) -> S3Config {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn s3_bucket() {
+        let config = s3_config(
+            r#"{
+                "bucket": "warcs",
+                "folder": "crawl",
+                "access_key": "access",
+                "secret_key": "secret",
+                "endpoint": "http://localhost:9000"
+            }"#,
+        );
+
+        assert_eq!(config.region, "");
+        assert!(config.force_path_style);
+
+        let bucket = config.bucket().unwrap();
+        assert!(bucket.is_path_style());
+        assert!(matches!(
+            bucket.region(),
+            s3::Region::Custom { region, endpoint }
+                if region.is_empty() && endpoint == "http://localhost:9000"
+        ));
+
+        let config = s3_config(
+            r#"{
+                "bucket": "warcs",
+                "folder": "crawl",
+                "access_key": "access",
+                "secret_key": "secret",
+                "endpoint": "https://s3.eu-central-1.amazonaws.com",
+                "region": "eu-central-1",
+                "force_path_style": false
+            }"#,
+        );
+
+        let bucket = config.bucket().unwrap();
+        assert!(!bucket.is_path_style());
+        assert!(matches!(
+            bucket.region(),
+            s3::Region::Custom { region, endpoint }
+                if region == "eu-central-1" && endpoint == "https://s3.eu-central-1.amazonaws.com"
+        ));
+    }
+}
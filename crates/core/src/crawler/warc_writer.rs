@@ -58,24 +58,9 @@ async fn commit(writer: warc::DeduplicatedWarcWriter, s3: config::S3Config) {
     );
     let data = writer.finish().unwrap();
 
-    match s3::Bucket::new(
-        &s3.bucket,
-        s3::Region::Custom {
-            region: "".to_string(),
-            endpoint: s3.endpoint.clone(),
-        },
-        s3::creds::Credentials {
-            access_key: Some(s3.access_key.clone()),
-            secret_key: Some(s3.secret_key.clone()),
-            security_token: None,
-            session_token: None,
-            expiration: None,
-        },
-    ) {
+    match s3.bucket() {
         Ok(bucket) => {
-            let bucket = bucket
-                .with_path_style()
-                .with_request_timeout(Duration::from_secs(30 * 60));
+            let bucket = bucket.with_request_timeout(Duration::from_secs(30 * 60));
 
             if let Err(err) = bucket
                 .put_object_with_content_type(
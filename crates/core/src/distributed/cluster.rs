@@ -17,12 +17,21 @@ use chitchat::{
     spawn_chitchat, transport::UdpTransport, ChitchatConfig, ChitchatHandle, FailureDetectorConfig,
     NodeId,
 };
-use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::RwLock;
 use tokio_stream::StreamExt;
 use tracing::error;
 
-use crate::distributed::member::{Member, Service};
+use crate::{
+    config::HealthCheckConfig,
+    distributed::member::{Member, Service, ServiceKind},
+};
 
 const CLUSTER_ID: &str = "stract-cluster";
 const GOSSIP_INTERVAL: Duration = Duration::from_secs(1);
@@ -30,8 +39,105 @@ const SERVICE_KEY: &str = "service";
 
 type Result<T> = std::result::Result<T, anyhow::Error>;
 
+pub trait HealthCheck: Send + Sync + 'static {
+    fn is_healthy(&self, member: &Member) -> impl Future<Output = bool> + Send;
+}
+
+/// Considers a member healthy if a tcp connection can be established to its host.
+pub struct TcpHealthCheck {
+    timeout: Duration,
+}
+
+impl TcpHealthCheck {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout }
+    }
+}
+
+impl HealthCheck for TcpHealthCheck {
+    async fn is_healthy(&self, member: &Member) -> bool {
+        matches!(
+            tokio::time::timeout(
+                self.timeout,
+                tokio::net::TcpStream::connect(member.service.host())
+            )
+            .await,
+            Ok(Ok(_))
+        )
+    }
+}
+
+#[derive(Default)]
+struct MemberHealth {
+    consecutive_failures: HashMap<String, usize>,
+    failure_threshold: Option<usize>,
+}
+
+impl MemberHealth {
+    fn record(&mut self, member: &Member, healthy: bool) {
+        if healthy {
+            self.consecutive_failures.remove(&member.id);
+        } else {
+            *self
+                .consecutive_failures
+                .entry(member.id.clone())
+                .or_default() += 1;
+        }
+    }
+
+    fn retain(&mut self, members: &[Member]) {
+        let ids: HashSet<_> = members.iter().map(|member| &member.id).collect();
+        self.consecutive_failures.retain(|id, _| ids.contains(id));
+    }
+
+    fn is_healthy(&self, member: &Member) -> bool {
+        match self.failure_threshold {
+            Some(threshold) => {
+                self.consecutive_failures
+                    .get(&member.id)
+                    .copied()
+                    .unwrap_or_default()
+                    < threshold
+            }
+            None => true,
+        }
+    }
+}
+
+async fn check_members<H: HealthCheck>(
+    members: &[Member],
+    checker: &H,
+    health: &RwLock<MemberHealth>,
+) {
+    let mut results = Vec::with_capacity(members.len());
+
+    for member in members {
+        results.push(checker.is_healthy(member).await);
+    }
+
+    let mut health = health.write().await;
+    health.retain(members);
+
+    for (member, healthy) in members.iter().zip(results) {
+        if !healthy {
+            tracing::warn!("member {} failed health check", member.id);
+        }
+
+        health.record(member, healthy);
+    }
+}
+
+fn services_of_kind(members: Vec<Member>, kind: ServiceKind) -> Vec<Service> {
+    members
+        .into_iter()
+        .map(|member| member.service)
+        .filter(|service| service.kind() == kind)
+        .collect()
+}
+
 pub struct Cluster {
     alive_nodes: Arc<RwLock<HashSet<Member>>>,
+    health: Arc<RwLock<MemberHealth>>,
     // dropping the handle leaves the cluster
     _chitchat_handle: ChitchatHandle,
 }
@@ -128,10 +234,47 @@ impl Cluster {
 
         Ok(Self {
             alive_nodes,
+            health: Arc::new(RwLock::new(MemberHealth::default())),
             _chitchat_handle: chitchat_handle,
         })
     }
 
+    /// Periodically check all members using a tcp health check.
+    /// Members that fail `config.failure_threshold` consecutive checks are
+    /// excluded from [`Cluster::healthy_members`] until they respond again.
+    pub async fn start_health_checks(&self, config: HealthCheckConfig) {
+        let checker = TcpHealthCheck::new(Duration::from_millis(config.timeout_ms));
+        self.start_health_checks_with(config, checker).await;
+    }
+
+    pub async fn start_health_checks_with<H: HealthCheck>(
+        &self,
+        config: HealthCheckConfig,
+        checker: H,
+    ) {
+        self.health.write().await.failure_threshold = Some(config.failure_threshold);
+
+        let alive_nodes = Arc::downgrade(&self.alive_nodes);
+        let health = Arc::downgrade(&self.health);
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(config.interval_ms));
+
+            loop {
+                interval.tick().await;
+
+                // stop checking once the cluster has been dropped
+                let (Some(alive_nodes), Some(health)) = (alive_nodes.upgrade(), health.upgrade())
+                else {
+                    break;
+                };
+
+                let members: Vec<_> = alive_nodes.read().await.iter().cloned().collect();
+                check_members(&members, &checker, &health).await;
+            }
+        });
+    }
+
     pub async fn members(&self) -> Vec<Member> {
         let lock = self.alive_nodes.read().await;
         let mut res = Vec::with_capacity(lock.len());
@@ -142,4 +285,132 @@ impl Cluster {
 
         res
     }
+
+    /// Members known through gossip, excluding those that have failed too many
+    /// consecutive health checks. Equivalent to [`Cluster::members`] if health checks
+    /// have not been started.
+    pub async fn healthy_members(&self) -> Vec<Member> {
+        let members = self.members().await;
+        let health = self.health.read().await;
+
+        members
+            .into_iter()
+            .filter(|member| health.is_healthy(member))
+            .collect()
+    }
+
+    /// All healthy services of the given kind.
+    pub async fn services_of_kind(&self, kind: ServiceKind) -> Vec<Service> {
+        services_of_kind(self.healthy_members().await, kind)
+    }
+
+    /// The first healthy service for which `f` returns `Some`.
+    pub async fn find_service<T, F>(&self, f: F) -> Option<T>
+    where
+        F: Fn(&Service) -> Option<T>,
+    {
+        self.healthy_members()
+            .await
+            .iter()
+            .find_map(|member| f(&member.service))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    struct MockHealthCheck {
+        responding: AtomicBool,
+    }
+
+    impl HealthCheck for Arc<MockHealthCheck> {
+        async fn is_healthy(&self, _member: &Member) -> bool {
+            self.responding.load(Ordering::SeqCst)
+        }
+    }
+
+    #[test]
+    fn filter_services_of_kind() {
+        let members = vec![
+            Member {
+                id: "api".to_string(),
+                service: Service::Api {
+                    host: "127.0.0.1:1000".parse().unwrap(),
+                },
+            },
+            Member {
+                id: "entity".to_string(),
+                service: Service::EntitySearcher {
+                    host: "127.0.0.1:1001".parse().unwrap(),
+                },
+            },
+            Member {
+                id: "api2".to_string(),
+                service: Service::Api {
+                    host: "127.0.0.1:1002".parse().unwrap(),
+                },
+            },
+        ];
+
+        let mut hosts: Vec<_> = services_of_kind(members.clone(), ServiceKind::Api)
+            .into_iter()
+            .map(|service| service.host())
+            .collect();
+        hosts.sort();
+
+        assert_eq!(
+            hosts,
+            vec![
+                "127.0.0.1:1000".parse::<SocketAddr>().unwrap(),
+                "127.0.0.1:1002".parse().unwrap()
+            ]
+        );
+
+        assert_eq!(
+            services_of_kind(members.clone(), ServiceKind::EntitySearcher),
+            vec![Service::EntitySearcher {
+                host: "127.0.0.1:1001".parse().unwrap()
+            }]
+        );
+
+        assert!(services_of_kind(members, ServiceKind::Webgraph).is_empty());
+    }
+
+    #[tokio::test]
+    async fn unresponsive_member_excluded_after_threshold() {
+        let member = Member {
+            id: "searcher".to_string(),
+            service: Service::Api {
+                host: "127.0.0.1:1234".parse().unwrap(),
+            },
+        };
+        let members = vec![member.clone()];
+
+        let checker = Arc::new(MockHealthCheck {
+            responding: AtomicBool::new(true),
+        });
+        let health = RwLock::new(MemberHealth {
+            failure_threshold: Some(2),
+            ..Default::default()
+        });
+
+        check_members(&members, &checker, &health).await;
+        assert!(health.read().await.is_healthy(&member));
+
+        checker.responding.store(false, Ordering::SeqCst);
+
+        check_members(&members, &checker, &health).await;
+        assert!(health.read().await.is_healthy(&member));
+
+        check_members(&members, &checker, &health).await;
+        assert!(!health.read().await.is_healthy(&member));
+
+        checker.responding.store(true, Ordering::SeqCst);
+
+        check_members(&members, &checker, &health).await;
+        assert!(health.read().await.is_healthy(&member));
+    }
 }
@@ -42,6 +42,37 @@ pub enum Service {
     },
 }
 
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum ServiceKind {
+    Searcher,
+    EntitySearcher,
+    LiveIndex,
+    Api,
+    Webgraph,
+}
+
+impl Service {
+    pub fn kind(&self) -> ServiceKind {
+        match self {
+            Service::Searcher { .. } => ServiceKind::Searcher,
+            Service::EntitySearcher { .. } => ServiceKind::EntitySearcher,
+            Service::LiveIndex { .. } => ServiceKind::LiveIndex,
+            Service::Api { .. } => ServiceKind::Api,
+            Service::Webgraph { .. } => ServiceKind::Webgraph,
+        }
+    }
+
+    pub fn host(&self) -> SocketAddr {
+        match self {
+            Service::Searcher { host, .. }
+            | Service::EntitySearcher { host }
+            | Service::LiveIndex { host, .. }
+            | Service::Api { host }
+            | Service::Webgraph { host, .. } => *host,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
 pub struct Member {
     pub id: String,
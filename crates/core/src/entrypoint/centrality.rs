@@ -111,14 +111,18 @@ impl Centrality {
         store_csv(top_harmonics, base_output.as_ref().join("harmonic.csv"));
     }
 
-    pub fn build_similarity<P: AsRef<Path>>(webgraph_path: P, base_output: P) {
+    pub fn build_similarity<P: AsRef<Path>>(
+        webgraph_path: P,
+        base_output: P,
+        max_inbound: Option<usize>,
+    ) {
         tracing::info!(
             "Building inbound similarity for {}",
             webgraph_path.as_ref().to_str().unwrap()
         );
         let graph = WebgraphBuilder::new(webgraph_path).single_threaded().open();
 
-        let sim = InboundSimilarity::build(&graph);
+        let sim = InboundSimilarity::build_with_max_inbound(&graph, max_inbound);
 
         sim.save(base_output.as_ref().join("inbound_similarity"))
             .unwrap();
@@ -139,7 +139,7 @@ fn calculate_centrality() {
 
     if !out_path.exists() {
         Centrality::build_harmonic(&webgraph_path, &out_path);
-        Centrality::build_similarity(&webgraph_path, &out_path);
+        Centrality::build_similarity(&webgraph_path, &out_path, None);
     }
 
     let webgraph_page = Path::new(DATA_PATH).join("webgraph_page");
@@ -174,6 +174,14 @@ fn create_inverted_index() -> Result<()> {
         settings: JobSettings {
             host_centrality_threshold: None,
             minimum_clean_words: None,
+            duplicate_records: Default::default(),
+            max_html_size: None,
+            build_term_dict: false,
+            url_char_ngrams: false,
+            cased_body: false,
+            clean_titles: false,
+            allowed_content_types: crate::config::defaults::Indexing::allowed_content_types(),
+            max_token_len: crate::config::defaults::Indexing::max_token_len(),
         },
     };
 
@@ -15,27 +15,38 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use anyhow::anyhow;
 use chrono::Utc;
-use rayon::prelude::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::thread;
 
 use itertools::Itertools;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tokio::pin;
-use tracing::{debug, info, trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
-use crate::config::{self, WarcSource};
-use crate::entrypoint::download_all_warc_files;
+use crate::config::{self, defaults, DuplicateRecordStrategy, WarcSource};
+use crate::executor::Executor;
 use crate::index::Index;
 use crate::kv::rocksdb_store::RocksDbStore;
 use crate::kv::Kv;
 use crate::mapreduce::{Map, Reduce, Worker};
 use crate::ranking::SignalAggregator;
-use crate::warc::PayloadType;
+use crate::warc::{PayloadType, WarcFile};
+use crate::web_spell::{self, TermDict};
 use crate::webgraph::{Node, NodeID, Webgraph, WebgraphBuilder};
-use crate::webpage::{safety_classifier, Html, Webpage};
+use crate::webpage::{safety_classifier, Html, Webpage, DEFAULT_MAX_HTML_SIZE};
 use crate::{human_website_annotations, Result};
 
+/// Folder in the output path where each job stores its spelling dictionary
+/// until they are merged.
+const TERM_DICTS_FOLDER: &str = "term_dicts";
+
+/// File in the index folder of a job that lists the warc files it has completely indexed.
+const CHECKPOINT_FILE: &str = "checkpoint.json";
+
+/// File in the index folder of a job that describes the commit in progress.
+const COMMIT_JOURNAL_FILE: &str = "commit_journal.json";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Job {
     pub source_config: config::WarcSource,
@@ -44,10 +55,25 @@ pub struct Job {
     pub settings: JobSettings,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobSettings {
     pub host_centrality_threshold: Option<f64>,
     pub minimum_clean_words: Option<usize>,
+    #[serde(default)]
+    pub duplicate_records: DuplicateRecordStrategy,
+    pub max_html_size: Option<usize>,
+    #[serde(default)]
+    pub build_term_dict: bool,
+    #[serde(default)]
+    pub url_char_ngrams: bool,
+    #[serde(default)]
+    pub cased_body: bool,
+    #[serde(default)]
+    pub clean_titles: bool,
+    #[serde(default = "defaults::Indexing::allowed_content_types")]
+    pub allowed_content_types: Vec<String>,
+    #[serde(default = "defaults::Indexing::max_token_len")]
+    pub max_token_len: usize,
 }
 
 pub struct IndexingWorker {
@@ -96,7 +122,13 @@ impl IndexingWorker {
     }
 
     pub fn prepare_webpage(&self, body: &str, url: &str, fetch_time_ms: u64) -> Result<Webpage> {
-        let mut html = match Html::parse_without_text(body, url) {
+        let max_html_size = self
+            .job_settings
+            .as_ref()
+            .and_then(|s| s.max_html_size)
+            .unwrap_or(DEFAULT_MAX_HTML_SIZE);
+
+        let mut html = match Html::parse_without_text_with_max_size(body, url, max_html_size) {
             Ok(html) => html,
             Err(err) => {
                 debug!("error parsing html: {:?}", err);
@@ -126,8 +158,10 @@ impl IndexingWorker {
             .get(&host_node_id)
             .unwrap_or(u64::MAX as f64);
 
-        if let Some(host_centrality_threshold) =
-            self.job_settings.and_then(|s| s.host_centrality_threshold)
+        if let Some(host_centrality_threshold) = self
+            .job_settings
+            .as_ref()
+            .and_then(|s| s.host_centrality_threshold)
         {
             if host_centrality < host_centrality_threshold {
                 debug!("skipping due to low host_centrality value");
@@ -141,7 +175,11 @@ impl IndexingWorker {
             return Err(anyhow!("empty all text"));
         }
 
-        if let Some(minimum_clean_words) = self.job_settings.and_then(|s| s.minimum_clean_words) {
+        if let Some(minimum_clean_words) = self
+            .job_settings
+            .as_ref()
+            .and_then(|s| s.minimum_clean_words)
+        {
             match html.clean_text() {
                 Some(clean_text) => {
                     if clean_text.split_whitespace().count() < minimum_clean_words {
@@ -265,20 +303,62 @@ pub fn process_job(job: &Job, worker: &IndexingWorker) -> Index {
 
     info!("processing {}", name);
 
-    let mut index = Index::open(Path::new(&job.base_path).join(name)).unwrap();
+    let index_path = Path::new(&job.base_path).join(name);
+    let mut index = Index::open(&index_path).unwrap();
     index.prepare_writer().unwrap();
+    index
+        .inverted_index
+        .set_url_char_ngrams(job.settings.url_char_ngrams)
+        .unwrap();
+    index
+        .inverted_index
+        .set_cased_body(job.settings.cased_body)
+        .unwrap();
+    index
+        .inverted_index
+        .set_clean_titles(job.settings.clean_titles)
+        .unwrap();
+    index
+        .inverted_index
+        .set_max_token_len(job.settings.max_token_len)
+        .unwrap();
+
+    let mut checkpoint = recover(&index, &index_path).unwrap();
+
+    let mut term_dict = if job.settings.build_term_dict {
+        Some(TermDict::open(job_term_dict_path(&job.base_path, name)).unwrap())
+    } else {
+        None
+    };
+
+    let allowed_payload_types: Vec<PayloadType> = job
+        .settings
+        .allowed_content_types
+        .iter()
+        .filter_map(|content_type| PayloadType::from_str(content_type).ok())
+        .collect();
+
+    for warc_path in &job.warc_paths {
+        if checkpoint.warc_paths.contains(warc_path) {
+            debug!("skipping {} as it has already been indexed", warc_path);
+            continue;
+        }
 
-    let warc_files = download_all_warc_files(&job.warc_paths, &job.source_config);
-    pin!(warc_files);
+        let file = match WarcFile::download(&job.source_config, warc_path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!("error while downloading {}: {:?}", warc_path, err);
+                continue;
+            }
+        };
 
-    for file in warc_files.by_ref() {
-        for record in
-            file.records()
-                .flatten()
-                .filter(|record| match &record.response.payload_type {
-                    Some(payload_type) => matches!(payload_type, PayloadType::Html),
-                    None => true,
-                })
+        for record in file
+            .deduplicated_records(job.settings.duplicate_records)
+            .flatten()
+            .filter(|record| match &record.response.payload_type {
+                Some(payload_type) => allowed_payload_types.contains(payload_type),
+                None => true,
+            })
         {
             if let Ok(webpage) = worker.prepare_webpage(
                 &record.response.body,
@@ -300,6 +380,12 @@ pub fn process_job(job: &Job, worker: &IndexingWorker) -> Index {
                 trace!("title = {:?}", webpage.html.title());
                 trace!("text = {:?}", webpage.html.clean_text());
 
+                if let (Some(term_dict), Some(text)) = (&mut term_dict, webpage.html.clean_text()) {
+                    for term in web_spell::tokenize(text) {
+                        term_dict.insert(&term);
+                    }
+                }
+
                 if let Err(err) = index.insert(webpage) {
                     warn!("{:?}", err);
                     panic!();
@@ -307,7 +393,18 @@ pub fn process_job(job: &Job, worker: &IndexingWorker) -> Index {
             }
         }
 
-        index.commit().unwrap();
+        commit_warc_file(
+            &mut index,
+            term_dict.as_mut(),
+            &mut checkpoint,
+            &index_path,
+            warc_path,
+        )
+        .unwrap();
+    }
+
+    if let Some(term_dict) = &mut term_dict {
+        term_dict.merge_dicts().unwrap();
     }
 
     if !has_host_centrality {
@@ -329,6 +426,117 @@ pub fn process_job(job: &Job, worker: &IndexingWorker) -> Index {
     index
 }
 
+/// Written to the index folder of a job before a warc file is committed and
+/// removed once the index, the term dictionary and the checkpoint all include it.
+/// If the journal still exists when the job is restarted, the process died
+/// in the middle of the commit and [`recover`] must complete or roll it back.
+#[derive(Debug, Serialize, Deserialize)]
+struct CommitJournal {
+    warc_path: String,
+}
+
+/// The warc files that have been completely indexed by a job,
+/// so a restarted job can skip them.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Checkpoint {
+    warc_paths: Vec<String>,
+}
+
+/// Write `value` as json to a temporary file and move it into place,
+/// so a crash never leaves a partially written file at `path`.
+fn write_json_atomic<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec(value)?)?;
+    std::fs::rename(tmp_path, path)?;
+
+    Ok(())
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(serde_json::from_slice(&std::fs::read(path)?)?))
+}
+
+/// Commit the documents of `warc_path` to the index and the term dictionary
+/// and add the file to the checkpoint. The index commit is tagged with the warc path,
+/// so [`recover`] can tell whether it happened if the process dies before the journal is removed.
+fn commit_warc_file(
+    index: &mut Index,
+    term_dict: Option<&mut TermDict>,
+    checkpoint: &mut Checkpoint,
+    index_path: &Path,
+    warc_path: &str,
+) -> Result<()> {
+    let journal_path = index_path.join(COMMIT_JOURNAL_FILE);
+
+    write_json_atomic(
+        &journal_path,
+        &CommitJournal {
+            warc_path: warc_path.to_string(),
+        },
+    )?;
+
+    index.commit_with_payload(warc_path)?;
+
+    if let Some(term_dict) = term_dict {
+        term_dict.commit()?;
+    }
+
+    checkpoint.warc_paths.push(warc_path.to_string());
+    write_json_atomic(&index_path.join(CHECKPOINT_FILE), checkpoint)?;
+
+    std::fs::remove_file(journal_path)?;
+
+    Ok(())
+}
+
+/// Bring the index folder of a job back to a consistent state after a crash
+/// and return the checkpoint of the warc files that are already indexed.
+///
+/// If the index contains the commit described by a leftover journal, the commit is
+/// completed by adding the warc file to the checkpoint. The terms of the file
+/// that had not been written to the term dictionary are lost in that case.
+/// Otherwise the documents were never committed, so the commit is rolled back
+/// by removing the journal and the file is indexed again.
+fn recover(index: &Index, index_path: &Path) -> Result<Checkpoint> {
+    let journal_path = index_path.join(COMMIT_JOURNAL_FILE);
+    let checkpoint_path = index_path.join(CHECKPOINT_FILE);
+
+    let mut checkpoint: Checkpoint = read_json(&checkpoint_path)?.unwrap_or_default();
+
+    if let Some(journal) = read_json::<CommitJournal>(&journal_path)? {
+        if index.inverted_index.commit_payload()?.as_deref() == Some(journal.warc_path.as_str()) {
+            warn!(
+                "completing interrupted commit of {} in {}",
+                journal.warc_path,
+                index_path.display()
+            );
+
+            if !checkpoint.warc_paths.contains(&journal.warc_path) {
+                checkpoint.warc_paths.push(journal.warc_path);
+                write_json_atomic(&checkpoint_path, &checkpoint)?;
+            }
+        } else {
+            warn!(
+                "rolling back interrupted commit of {} in {}",
+                journal.warc_path,
+                index_path.display()
+            );
+        }
+
+        std::fs::remove_file(journal_path)?;
+    }
+
+    Ok(checkpoint)
+}
+
+fn job_term_dict_path(base_path: &str, name: &str) -> PathBuf {
+    Path::new(base_path).join(TERM_DICTS_FOLDER).join(name)
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct IndexPointer(String);
 
@@ -375,7 +583,12 @@ impl Indexer {
             config.safety_classifier_path.clone(),
         );
 
-        let indexes = warc_paths
+        let executor = match config.num_threads {
+            Some(num_threads) => Executor::with_threads(num_threads, "indexer")?,
+            None => Executor::multi_thread("indexer")?,
+        };
+
+        let jobs = warc_paths
             .into_iter()
             .skip(config.skip_warc_files.unwrap_or(0))
             .take(config.limit_warc_files.unwrap_or(usize::MAX))
@@ -383,7 +596,7 @@ impl Indexer {
             .into_iter()
             .map(|paths| paths.collect_vec())
             .collect_vec()
-            .into_par_iter()
+            .into_iter()
             .map(|warc_paths| Job {
                 source_config: job_config.clone(),
                 warc_paths,
@@ -391,15 +604,80 @@ impl Indexer {
                 settings: JobSettings {
                     host_centrality_threshold: config.host_centrality_threshold,
                     minimum_clean_words: config.minimum_clean_words,
+                    duplicate_records: config.duplicate_records,
+                    max_html_size: config.max_html_size,
+                    build_term_dict: config.build_term_dict,
+                    url_char_ngrams: config.url_char_ngrams,
+                    cased_body: config.cased_body,
+                    clean_titles: config.clean_titles,
+                    allowed_content_types: config.allowed_content_types.clone(),
+                    max_token_len: config.max_token_len,
                 },
-            })
-            .map(|job| {
+            });
+
+        let indexes = executor.map(
+            |job| {
                 let pointer: IndexPointer = job.map(&worker);
                 pointer
-            })
-            .collect();
+            },
+            jobs,
+        )?;
 
         Self::merge(indexes)?;
+
+        if config.build_term_dict {
+            Self::merge_term_dicts(Path::new(&config.output_path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Merge the spelling dictionaries built by the individual jobs
+    /// into a single dictionary in `<output_path>/term_dict`.
+    pub fn merge_term_dicts(output_path: &Path) -> Result<()> {
+        let dicts_path = output_path.join(TERM_DICTS_FOLDER);
+
+        if !dicts_path.exists() {
+            return Ok(());
+        }
+
+        let mut term_dict = TermDict::open(output_path.join("term_dict"))?;
+
+        for entry in std::fs::read_dir(&dicts_path)? {
+            term_dict.merge(TermDict::open(entry?.path())?)?;
+        }
+
+        term_dict.merge_dicts()?;
+        std::fs::remove_dir_all(dicts_path)?;
+
+        Ok(())
+    }
+
+    /// Merge the segments of the committed index at `path` until at most
+    /// `max_num_segments` segments remain. Fewer segments give faster queries.
+    pub fn optimize<P: AsRef<Path>>(path: P, max_num_segments: u64) -> Result<()> {
+        if max_num_segments == 0 {
+            return Err(anyhow::anyhow!("max_num_segments must be at least 1"));
+        }
+
+        let mut index = Index::open(path)?;
+        let num_segments = index.inverted_index.num_segments();
+
+        info!(
+            "optimizing index with {} segments into at most {} segments",
+            num_segments, max_num_segments
+        );
+
+        index
+            .inverted_index
+            .merge_into_max_segments(max_num_segments)?;
+
+        info!(
+            "optimized index from {} to {} segments",
+            num_segments,
+            index.inverted_index.num_segments()
+        );
+
         Ok(())
     }
 
@@ -458,3 +736,314 @@ impl Indexer {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::config::LocalConfig;
+    use crate::warc::{Metadata, Request, Response, WarcRecord, WarcWriter};
+
+    use super::*;
+
+    fn record(url: &str, title: &str) -> WarcRecord {
+        WarcRecord {
+            request: Request {
+                url: url.to_string(),
+            },
+            response: Response {
+                body: format!(
+                    "<html><head><title>{title}</title></head><body>this is the page body</body></html>"
+                ),
+                payload_type: Some(PayloadType::Html),
+            },
+            metadata: Metadata { fetch_time_ms: 0 },
+        }
+    }
+
+    fn settings() -> JobSettings {
+        JobSettings {
+            host_centrality_threshold: None,
+            minimum_clean_words: None,
+            duplicate_records: DuplicateRecordStrategy::default(),
+            max_html_size: None,
+            build_term_dict: false,
+            url_char_ngrams: false,
+            cased_body: false,
+            clean_titles: false,
+            allowed_content_types: defaults::Indexing::allowed_content_types(),
+            max_token_len: defaults::Indexing::max_token_len(),
+        }
+    }
+
+    fn index_records(base_path: &Path, records: &[WarcRecord], settings: JobSettings) -> Index {
+        process_job(&job(base_path, records, settings), &worker(base_path))
+    }
+
+    fn job(base_path: &Path, records: &[WarcRecord], settings: JobSettings) -> Job {
+        let warc_folder = base_path.join("warc");
+        std::fs::create_dir_all(&warc_folder).unwrap();
+
+        let mut writer = WarcWriter::new();
+        for record in records {
+            writer.write(record).unwrap();
+        }
+        std::fs::write(
+            warc_folder.join("records.warc.gz"),
+            writer.finish().unwrap(),
+        )
+        .unwrap();
+
+        Job {
+            source_config: WarcSource::Local(LocalConfig {
+                folder: warc_folder.to_str().unwrap().to_string(),
+                names: vec!["records.warc.gz".to_string()],
+            }),
+            warc_paths: vec!["records.warc.gz".to_string()],
+            base_path: base_path.join("index").to_str().unwrap().to_string(),
+            settings,
+        }
+    }
+
+    fn worker(base_path: &Path) -> IndexingWorker {
+        IndexingWorker::new(
+            base_path.join("centrality").to_str().unwrap().to_string(),
+            None,
+            None,
+            None,
+            None,
+        )
+    }
+
+    fn index_duplicates(duplicate_records: DuplicateRecordStrategy) -> Index {
+        index_records(
+            &crate::gen_temp_path(),
+            &[
+                record("https://a.com/", "First"),
+                record("https://a.com/", "Second"),
+            ],
+            JobSettings {
+                duplicate_records,
+                ..settings()
+            },
+        )
+    }
+
+    #[test]
+    fn only_allowed_content_types() {
+        let with_payload_type = |url: &str, payload_type: Option<PayloadType>| {
+            let mut record = record(url, "Title");
+            record.response.payload_type = payload_type;
+            record
+        };
+
+        let index = index_records(
+            &crate::gen_temp_path(),
+            &[
+                with_payload_type("https://a.com/", Some(PayloadType::Html)),
+                with_payload_type("https://b.com/", Some(PayloadType::Pdf)),
+                with_payload_type(
+                    "https://c.com/",
+                    Some(PayloadType::Other("application/json".to_string())),
+                ),
+                with_payload_type(
+                    "https://d.com/",
+                    Some(PayloadType::Other("image/png".to_string())),
+                ),
+                with_payload_type("https://e.com/", None),
+            ],
+            settings(),
+        );
+
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 2);
+        assert!(index.inverted_index.get_webpage("https://a.com/").is_some());
+        assert!(index.inverted_index.get_webpage("https://b.com/").is_none());
+        assert!(index.inverted_index.get_webpage("https://c.com/").is_none());
+        assert!(index.inverted_index.get_webpage("https://d.com/").is_none());
+        assert!(index.inverted_index.get_webpage("https://e.com/").is_some());
+
+        let index = index_records(
+            &crate::gen_temp_path(),
+            &[
+                with_payload_type("https://a.com/", Some(PayloadType::Html)),
+                with_payload_type("https://b.com/", Some(PayloadType::Pdf)),
+            ],
+            JobSettings {
+                allowed_content_types: vec!["application/pdf".to_string()],
+                ..settings()
+            },
+        );
+
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 1);
+        assert!(index.inverted_index.get_webpage("https://b.com/").is_some());
+    }
+
+    #[test]
+    fn duplicate_records_indexed_once() {
+        let index = index_duplicates(DuplicateRecordStrategy::KeepFirst);
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 1);
+        assert_eq!(
+            index
+                .inverted_index
+                .get_webpage("https://a.com/")
+                .unwrap()
+                .title,
+            "First"
+        );
+
+        let index = index_duplicates(DuplicateRecordStrategy::KeepLast);
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 1);
+        assert_eq!(
+            index
+                .inverted_index
+                .get_webpage("https://a.com/")
+                .unwrap()
+                .title,
+            "Second"
+        );
+    }
+
+    #[test]
+    fn clean_titles() {
+        let records = [record("https://a.com/", "First | A.com")];
+
+        let index = index_records(&crate::gen_temp_path(), &records, settings());
+        assert_eq!(
+            index
+                .inverted_index
+                .get_webpage("https://a.com/")
+                .unwrap()
+                .title,
+            "First | A.com"
+        );
+        assert!(!index.inverted_index.has_clean_titles());
+
+        let index = index_records(
+            &crate::gen_temp_path(),
+            &records,
+            JobSettings {
+                clean_titles: true,
+                ..settings()
+            },
+        );
+        assert_eq!(
+            index
+                .inverted_index
+                .get_webpage("https://a.com/")
+                .unwrap()
+                .title,
+            "First"
+        );
+        assert!(index.inverted_index.has_clean_titles());
+    }
+
+    #[test]
+    fn recover_interrupted_commit() {
+        let base_path = crate::gen_temp_path();
+        let records = [
+            record("https://a.com/", "First"),
+            record("https://b.com/", "Second"),
+        ];
+        let job = job(&base_path, &records, settings());
+        let index_path = Path::new(&job.base_path).join("records.warc.gz");
+        let journal = CommitJournal {
+            warc_path: "records.warc.gz".to_string(),
+        };
+
+        // crash after the index commit but before the checkpoint was written
+        let index = process_job(&job, &worker(&base_path));
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 2);
+        drop(index);
+
+        std::fs::remove_file(index_path.join(CHECKPOINT_FILE)).unwrap();
+        write_json_atomic(&index_path.join(COMMIT_JOURNAL_FILE), &journal).unwrap();
+
+        // the commit is completed, so the file is not indexed twice
+        let index = process_job(&job, &worker(&base_path));
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 2);
+        assert!(!index_path.join(COMMIT_JOURNAL_FILE).exists());
+
+        let checkpoint: Checkpoint = read_json(&index_path.join(CHECKPOINT_FILE))
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.warc_paths, vec!["records.warc.gz".to_string()]);
+        drop(index);
+
+        // crash before the index commit
+        let base_path = crate::gen_temp_path();
+        let job = self::job(&base_path, &records, settings());
+        let index_path = Path::new(&job.base_path).join("records.warc.gz");
+
+        std::fs::create_dir_all(&index_path).unwrap();
+        write_json_atomic(&index_path.join(COMMIT_JOURNAL_FILE), &journal).unwrap();
+
+        // the commit is rolled back, so the file is indexed again
+        let index = process_job(&job, &worker(&base_path));
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 2);
+        assert!(!index_path.join(COMMIT_JOURNAL_FILE).exists());
+        assert!(index.inverted_index.get_webpage("https://a.com/").is_some());
+
+        let checkpoint: Checkpoint = read_json(&index_path.join(CHECKPOINT_FILE))
+            .unwrap()
+            .unwrap();
+        assert_eq!(checkpoint.warc_paths, vec!["records.warc.gz".to_string()]);
+    }
+
+    #[test]
+    fn optimize() {
+        let path = crate::gen_temp_path();
+        let mut index = Index::open(&path).unwrap();
+        index.prepare_writer().unwrap();
+
+        for i in 0..4 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!("<html><head><title>Page {i}</title></head></html>"),
+                        &format!("https://www.{i}.com/"),
+                    )
+                    .unwrap(),
+                    ..Default::default()
+                })
+                .unwrap();
+            index.commit().unwrap();
+        }
+
+        assert_eq!(index.inverted_index.num_segments(), 4);
+        drop(index);
+
+        assert!(Indexer::optimize(&path, 0).is_err());
+
+        Indexer::optimize(&path, 1).unwrap();
+
+        let index = Index::open(&path).unwrap();
+        assert_eq!(index.inverted_index.num_segments(), 1);
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 4);
+    }
+
+    #[test]
+    fn build_term_dict() {
+        let base_path = crate::gen_temp_path();
+
+        let index = index_records(
+            &base_path,
+            &[
+                record("https://a.com/", "First"),
+                record("https://b.com/", "Second"),
+            ],
+            JobSettings {
+                build_term_dict: true,
+                ..settings()
+            },
+        );
+        assert_eq!(index.inverted_index.tv_searcher().num_docs(), 2);
+        drop(index);
+
+        let output_path = base_path.join("index");
+        Indexer::merge_term_dicts(&output_path).unwrap();
+        assert!(!output_path.join(TERM_DICTS_FOLDER).exists());
+
+        let term_dict = TermDict::open(output_path.join("term_dict")).unwrap();
+        assert_eq!(term_dict.freq("page"), Some(2));
+        assert_eq!(term_dict.freq("body"), Some(2));
+        assert_eq!(term_dict.freq("missing"), None);
+    }
+}
@@ -59,7 +59,7 @@ impl SearchService {
         let centrality_store = config
             .host_centrality_store_path
             .map(|p| InboundSimilarity::open(Path::new(&p).join("inbound_similarity")).unwrap());
-        let search_index = Index::open(config.index_path)?;
+        let search_index = Index::open_with_mode(config.index_path, config.index_open_mode)?;
 
         let mut local_searcher = LocalSearcher::new(search_index);
 
@@ -77,6 +77,11 @@ impl SearchService {
 
         local_searcher.set_collector_config(config.collector);
         local_searcher.set_snippet_config(config.snippet);
+        local_searcher.set_query_config(config.query);
+
+        if let Some(result_cache) = config.result_cache {
+            local_searcher.set_result_cache(result_cache);
+        }
 
         let cluster_handle = Cluster::join(
             Member {
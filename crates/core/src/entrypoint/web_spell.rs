@@ -26,6 +26,7 @@ use whatlang::Lang;
 use crate::{
     config::{self, WebSpellConfig},
     entrypoint::download_all_warc_files,
+    executor::Executor,
     mapreduce::Worker,
     web_spell::{FirstTrainer, FirstTrainerResult, SecondTrainer},
     webpage::Html,
@@ -167,6 +168,8 @@ pub fn run(config: WebSpellConfig) -> Result<()> {
         }
     }
 
+    let executor = Executor::multi_thread("term-dict-prune")?;
+
     for (lang, results) in combined {
         info!("creating second trainer for {}", lang);
         let second_trainer = SecondTrainer::new(
@@ -174,6 +177,7 @@ pub fn run(config: WebSpellConfig) -> Result<()> {
             Path::new(&config.output_path)
                 .join("checker")
                 .join(lang.code()),
+            &executor,
         )?;
         debug!("second trainer created");
 
@@ -35,7 +35,13 @@ impl Executor {
         Self::with_threads(num_cpus::get(), prefix)
     }
 
+    /// Thread pool with exactly `num_threads` workers named `<prefix><num>`.
     pub fn with_threads(num_threads: usize, prefix: &'static str) -> Result<Executor> {
+        // rayon would otherwise silently pick a thread count for us
+        if num_threads == 0 {
+            return Err(Error::NoExecutorThreads.into());
+        }
+
         let pool = ThreadPoolBuilder::new()
             .num_threads(num_threads)
             .stack_size(80_000_000)
@@ -102,6 +108,8 @@ impl Executor {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+    use std::sync::Barrier;
 
     use super::Executor;
 
@@ -153,4 +161,30 @@ mod tests {
             assert_eq!(*r, i * 2);
         }
     }
+
+    #[test]
+    fn test_num_threads() {
+        let num_threads = 4;
+        let barrier = Barrier::new(num_threads);
+
+        // every job waits for all the others, so they can only finish
+        // if they run on different threads at the same time
+        let names: HashSet<String> = Executor::with_threads(num_threads, "search-test")
+            .unwrap()
+            .map(
+                |_| {
+                    barrier.wait();
+                    std::thread::current().name().unwrap().to_string()
+                },
+                0..num_threads,
+            )
+            .unwrap()
+            .into_iter()
+            .collect();
+
+        assert_eq!(names.len(), num_threads);
+        assert!(names.iter().all(|name| name.starts_with("search-test")));
+
+        assert!(Executor::with_threads(0, "search-test").is_err());
+    }
 }
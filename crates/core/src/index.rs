@@ -23,6 +23,7 @@ use std::time::SystemTime;
 use tantivy::tokenizer::TokenizerManager;
 
 use crate::collector::MainCollector;
+use crate::config::IndexOpenMode;
 use crate::inverted_index::{self, InvertedIndex};
 use crate::query::Query;
 use crate::search_ctx::Ctx;
@@ -34,6 +35,35 @@ use crate::Result;
 const INVERTED_INDEX_SUBFOLDER_NAME: &str = "inverted_index";
 const REGION_COUNT_FILE_NAME: &str = "region_count.json";
 
+/// Size of an [`Index`]. See [`Index::stats`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexStats {
+    /// Number of committed documents that have not been deleted.
+    pub num_docs: u64,
+    pub num_segments: usize,
+    /// See [`InvertedIndex::num_terms`].
+    pub num_terms: u64,
+    /// Total size of the files in the index folder.
+    pub size_bytes: u64,
+}
+
+fn dir_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            size += dir_size(&entry.path())?;
+        } else {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
 pub struct Index {
     pub inverted_index: InvertedIndex,
     pub region_count: Mutex<RegionCount>,
@@ -42,12 +72,16 @@ pub struct Index {
 
 impl Index {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_mode(path, IndexOpenMode::default())
+    }
+
+    pub fn open_with_mode<P: AsRef<Path>>(path: P, mode: IndexOpenMode) -> Result<Self> {
         if !path.as_ref().exists() {
             fs::create_dir_all(path.as_ref())?;
         }
 
         let inverted_index =
-            InvertedIndex::open(path.as_ref().join(INVERTED_INDEX_SUBFOLDER_NAME))?;
+            InvertedIndex::open_with_mode(path.as_ref().join(INVERTED_INDEX_SUBFOLDER_NAME), mode)?;
 
         let region_count = RegionCount::open(path.as_ref().join(REGION_COUNT_FILE_NAME));
 
@@ -92,13 +126,24 @@ impl Index {
 
     pub fn commit(&mut self) -> Result<()> {
         self.inverted_index.commit()?;
+        self.commit_region_count();
 
-        let mut reg = self.region_count.lock().unwrap_or_else(|e| e.into_inner());
-        reg.commit();
+        Ok(())
+    }
+
+    /// See [`InvertedIndex::commit_with_payload`].
+    pub fn commit_with_payload(&mut self, payload: &str) -> Result<()> {
+        self.inverted_index.commit_with_payload(payload)?;
+        self.commit_region_count();
 
         Ok(())
     }
 
+    fn commit_region_count(&self) {
+        let mut reg = self.region_count.lock().unwrap_or_else(|e| e.into_inner());
+        reg.commit();
+    }
+
     pub fn top_nodes(
         &self,
         query: &Query,
@@ -149,6 +194,17 @@ impl Index {
     pub(crate) fn prepare_writer(&mut self) -> Result<()> {
         self.inverted_index.prepare_writer()
     }
+
+    /// Statistics of the committed documents. Everything except the size on disk
+    /// is read from the segment metadata, so this is cheap even for large indexes.
+    pub fn stats(&self) -> Result<IndexStats> {
+        Ok(IndexStats {
+            num_docs: self.inverted_index.tv_searcher().num_docs(),
+            num_segments: self.inverted_index.num_segments(),
+            num_terms: self.inverted_index.num_terms()?,
+            size_bytes: dir_size(Path::new(&self.path))?,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -177,7 +233,7 @@ mod tests {
                 </body>
             </html>
             "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 1)
                     ),
                     "https://www.first.com",
                 )
@@ -198,7 +254,7 @@ mod tests {
                 </body>
             </html>
             "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 2)
                     ),
                     "https://www.second.com",
                 )
@@ -219,7 +275,7 @@ mod tests {
                 </body>
             </html>
             "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 3)
                     ),
                     "https://www.third.com",
                 )
@@ -249,4 +305,107 @@ mod tests {
                 .unwrap())
             .all(|&v| v.value > 0.0));
     }
+
+    #[test]
+    fn open_modes() {
+        let path = crate::gen_temp_path();
+
+        {
+            let mut index = Index::open(&path).expect("Unable to open index");
+            index.prepare_writer().unwrap();
+
+            for i in 0..3 {
+                index
+                    .insert(
+                        Webpage::new(
+                            &format!(
+                                r#"
+            <html>
+                <head>
+                    <title>Test website {i}</title>
+                </head>
+                <body>
+                    {CONTENT} {}
+                </body>
+            </html>
+            "#,
+                                crate::rand_words_seeded(100, 10 + i)
+                            ),
+                            &format!("https://www.{i}.com"),
+                        )
+                        .unwrap(),
+                    )
+                    .expect("failed to insert webpage");
+            }
+
+            index.commit().unwrap();
+        }
+
+        let results: Vec<_> = [IndexOpenMode::Mmap, IndexOpenMode::Ram]
+            .into_iter()
+            .map(|mode| {
+                let index = Index::open_with_mode(&path, mode).expect("Unable to open index");
+                let searcher = LocalSearcher::from(index);
+
+                searcher
+                    .search(&SearchQuery {
+                        query: "test".to_string(),
+                        ..Default::default()
+                    })
+                    .unwrap()
+                    .webpages
+                    .into_iter()
+                    .map(|webpage| (webpage.url, webpage.score))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        assert_eq!(results[0].len(), 3);
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn stats() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        let stats = index.stats().unwrap();
+        assert_eq!(stats.num_docs, 0);
+        assert_eq!(stats.num_terms, 0);
+
+        for i in 0..5 {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Test website {i}</title>
+                </head>
+                <body>
+                    {CONTENT} {}
+                </body>
+            </html>
+            "#,
+                            crate::rand_words_seeded(100, 20 + i)
+                        ),
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+
+        // uncommitted documents are not counted
+        assert_eq!(index.stats().unwrap().num_docs, 0);
+
+        index.commit().unwrap();
+
+        let stats = index.stats().unwrap();
+        assert_eq!(stats.num_docs, 5);
+        assert_eq!(stats.num_segments, index.inverted_index.num_segments());
+        assert!(stats.num_segments >= 1);
+        assert!(stats.num_terms > 0);
+        assert!(stats.size_bytes > 0);
+    }
 }
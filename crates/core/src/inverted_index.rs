@@ -29,35 +29,45 @@ use chrono::NaiveDateTime;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use tantivy::collector::Count;
-use tantivy::directory::MmapDirectory;
+use tantivy::directory::{Directory, MmapDirectory, RamDirectory};
 use tantivy::merge_policy::NoMergePolicy;
+use tantivy::postings::Postings;
 use tantivy::schema::{Schema, Value};
 use tantivy::tokenizer::TokenizerManager;
-use tantivy::{IndexReader, IndexWriter, SegmentMeta, TantivyDocument};
+use tantivy::{DocSet, IndexReader, IndexWriter, SegmentMeta, TantivyDocument};
+use tracing::debug;
 use url::Url;
+use utoipa::ToSchema;
 
-use crate::collector::{Hashes, MainCollector};
-use crate::config::SnippetConfig;
+use crate::collector::{GroupCountCollector, Hashes, HostFacetCollector, MainCollector};
+use crate::config::{IndexOpenMode, QueryConfig, SnippetConfig};
 use crate::fastfield_reader::FastFieldReader;
+use crate::minhash::MinHash;
+use crate::prehashed::Prehashed;
+use crate::query::parser::{analyze_with_max_token_len, Term};
 use crate::query::shortcircuit::ShortCircuitQuery;
 use crate::query::Query;
 use crate::ranking::initial::Score;
 use crate::ranking::pipeline::RankingWebsite;
 use crate::ranking::SignalAggregator;
+use crate::schema::{schema_fingerprint, schema_version};
 use crate::schema::{FastField, Field, TextField};
 use crate::search_ctx::Ctx;
+use crate::searcher::HostFacet;
+use crate::simhash;
 use crate::snippet::TextSnippet;
 use crate::snippet::{self, TextSnippetFragment};
 use crate::tokenizer::{
-    BigramTokenizer, Identity, JsonField, SiteOperatorUrlTokenizer, TrigramTokenizer,
+    BigramTokenizer, Cased, CharNgramTokenizer, Identity, JsonField, SiteOperatorUrlTokenizer,
+    TrigramTokenizer, DEFAULT_MAX_TOKEN_LEN,
 };
 use crate::webgraph::NodeID;
 use crate::webpage::region::Region;
 use crate::webpage::url_ext::UrlExt;
 use crate::webpage::{schema_org, Webpage};
-use crate::Result;
 use crate::{schema::create_schema, tokenizer::Tokenizer};
-use std::collections::HashSet;
+use crate::{Error, Result};
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
@@ -134,12 +144,23 @@ pub fn merge_tantivy_segments<P: AsRef<Path>>(
         best_candidate.segments.push(segment);
     }
 
-    for merge in merge_segments
+    // start all merges before waiting so the disjoint groups are merged in parallel
+    let merges: Vec<_> = merge_segments
         .into_iter()
         .filter(|merge| !merge.segments.is_empty())
-    {
-        let segment_ids: Vec<_> = merge.segments.iter().map(|segment| segment.id()).collect();
-        writer.merge(&segment_ids[..]).wait()?;
+        .map(|merge| {
+            let segment_ids: Vec<_> = merge.segments.iter().map(|segment| segment.id()).collect();
+            let future = writer.merge(&segment_ids[..]);
+
+            (merge, future)
+        })
+        .collect();
+
+    let num_merges = merges.len();
+
+    for (i, (merge, future)) in merges.into_iter().enumerate() {
+        future.wait()?;
+        debug!("merged segment group {}/{}", i + 1, num_merges);
 
         for segment in merge.segments {
             for file in segment.list_files() {
@@ -151,6 +172,58 @@ pub fn merge_tantivy_segments<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Make sure `tantivy_index` was created with the current schema. The schema
+/// is compared to the one tantivy stored in the index metadata.
+fn check_schema_version(tantivy_index: &tantivy::Index) -> Result<()> {
+    let expected = schema_version();
+    let found = schema_fingerprint(&tantivy_index.schema());
+
+    if found != expected {
+        return Err(Error::SchemaMismatch { expected, found }.into());
+    }
+
+    Ok(())
+}
+
+/// File in the index directory that stores the [`IndexSettings`] of the index.
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Settings that change what gets indexed. They are saved with the index on every
+/// commit and read back when it is opened, so an index is always searched with
+/// the settings it was built with. Indexes without saved settings use the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct IndexSettings {
+    max_token_len: usize,
+    cased_body: bool,
+    url_char_ngrams: bool,
+    clean_titles: bool,
+}
+
+impl Default for IndexSettings {
+    fn default() -> Self {
+        Self {
+            max_token_len: DEFAULT_MAX_TOKEN_LEN,
+            cased_body: false,
+            url_char_ngrams: false,
+            clean_titles: false,
+        }
+    }
+}
+
+fn load_settings(tantivy_index: &tantivy::Index) -> Result<IndexSettings> {
+    match tantivy_index
+        .directory()
+        .atomic_read(Path::new(SETTINGS_FILE))
+    {
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(tantivy::directory::error::OpenReadError::FileDoesNotExist(_)) => {
+            Ok(IndexSettings::default())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
 struct SegmentMergeCandidate {
     num_docs: u32,
     segments: Vec<SegmentMeta>,
@@ -163,16 +236,66 @@ pub struct InvertedIndex {
     reader: IndexReader,
     schema: Arc<Schema>,
     snippet_config: SnippetConfig,
+    query_config: QueryConfig,
     fastfield_reader: FastFieldReader,
+    settings: IndexSettings,
+}
+
+/// Register the tokenizers that split text into words, which drop tokens
+/// longer than `max_token_len`.
+fn register_word_tokenizers(tantivy_index: &tantivy::Index, max_token_len: usize) {
+    for tokenizer in [
+        Tokenizer::default(),
+        Tokenizer::Cased(Cased::default()),
+        Tokenizer::new_stemmed(),
+        Tokenizer::Bigram(BigramTokenizer::default()),
+        Tokenizer::Trigram(TrigramTokenizer::default()),
+    ] {
+        let tokenizer = tokenizer.with_max_token_len(max_token_len);
+        tantivy_index
+            .tokenizers()
+            .register(tokenizer.as_str(), tokenizer);
+    }
+}
+
+/// Copy the files of the index at `path` into memory.
+fn ram_directory<P: AsRef<Path>>(path: P) -> Result<RamDirectory> {
+    let directory = RamDirectory::create();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+
+        // locks are held by the process that created them
+        if !entry.file_type()?.is_file() || name.to_string_lossy().ends_with(".lock") {
+            continue;
+        }
+
+        directory.atomic_write(Path::new(&name), &fs::read(entry.path())?)?;
+    }
+
+    Ok(directory)
 }
 
 impl InvertedIndex {
     pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with_mode(path, IndexOpenMode::default())
+    }
+
+    /// Open the index using `mode` to access its files. New indexes are always
+    /// created on disk.
+    pub fn open_with_mode<P: AsRef<Path>>(path: P, mode: IndexOpenMode) -> Result<Self> {
         let schema = create_schema();
 
         let tantivy_index = if path.as_ref().exists() {
-            let mmap_directory = MmapDirectory::open(&path)?;
-            tantivy::Index::open(mmap_directory)?
+            let tantivy_index = match mode {
+                IndexOpenMode::Mmap => tantivy::Index::open(MmapDirectory::open(&path)?)?,
+                IndexOpenMode::Ram => tantivy::Index::open(ram_directory(&path)?)?,
+            };
+
+            check_schema_version(&tantivy_index)?;
+
+            tantivy_index
         } else {
             let index_settings = tantivy::IndexSettings {
                 sort_by_field: Some(tantivy::IndexSortByField {
@@ -187,37 +310,26 @@ impl InvertedIndex {
             tantivy::Index::create(mmap_directory, schema.clone(), index_settings)?
         };
 
-        let tokenizer = Tokenizer::default();
-        tantivy_index
-            .tokenizers()
-            .register(tokenizer.as_str(), tokenizer);
+        let settings = load_settings(&tantivy_index)?;
 
-        let tokenizer = Tokenizer::new_stemmed();
-        tantivy_index
-            .tokenizers()
-            .register(tokenizer.as_str(), tokenizer);
+        register_word_tokenizers(&tantivy_index, settings.max_token_len);
 
         let tokenizer = Tokenizer::Identity(Identity::default());
         tantivy_index
             .tokenizers()
             .register(tokenizer.as_str(), tokenizer);
 
-        let tokenizer = Tokenizer::Bigram(BigramTokenizer::default());
-        tantivy_index
-            .tokenizers()
-            .register(tokenizer.as_str(), tokenizer);
-
-        let tokenizer = Tokenizer::Trigram(TrigramTokenizer::default());
+        let tokenizer = Tokenizer::SiteOperator(SiteOperatorUrlTokenizer);
         tantivy_index
             .tokenizers()
             .register(tokenizer.as_str(), tokenizer);
 
-        let tokenizer = Tokenizer::SiteOperator(SiteOperatorUrlTokenizer);
+        let tokenizer = Tokenizer::Json(JsonField);
         tantivy_index
             .tokenizers()
             .register(tokenizer.as_str(), tokenizer);
 
-        let tokenizer = Tokenizer::Json(JsonField);
+        let tokenizer = Tokenizer::CharNgram(CharNgramTokenizer);
         tantivy_index
             .tokenizers()
             .register(tokenizer.as_str(), tokenizer);
@@ -233,7 +345,9 @@ impl InvertedIndex {
             path: path.as_ref().to_str().unwrap().to_string(),
             tantivy_index,
             snippet_config: SnippetConfig::default(),
+            query_config: QueryConfig::default(),
             fastfield_reader,
+            settings,
         })
     }
 
@@ -262,6 +376,106 @@ impl InvertedIndex {
         self.snippet_config = config;
     }
 
+    pub fn set_query_config(&mut self, config: QueryConfig) {
+        self.query_config = config;
+    }
+
+    pub fn query_config(&self) -> &QueryConfig {
+        &self.query_config
+    }
+
+    /// Index the character n-grams of the urls of inserted pages so `inurl:`
+    /// can match substrings within url tokens. This makes the index larger.
+    pub fn set_url_char_ngrams(&mut self, url_char_ngrams: bool) -> Result<()> {
+        self.check_setting(
+            "url_char_ngrams",
+            self.settings.url_char_ngrams,
+            url_char_ngrams,
+        )?;
+        self.settings.url_char_ngrams = url_char_ngrams;
+
+        Ok(())
+    }
+
+    /// Whether the index was built with the character n-grams of the urls.
+    pub fn has_url_char_ngrams(&self) -> bool {
+        self.settings.url_char_ngrams
+    }
+
+    /// Index the clean body of inserted pages with its case preserved so phrases
+    /// can be matched case sensitively. Titles are not cased, so case sensitive
+    /// phrases only match the body. This makes the index larger.
+    pub fn set_cased_body(&mut self, cased_body: bool) -> Result<()> {
+        self.check_setting("cased_body", self.settings.cased_body, cased_body)?;
+        self.settings.cased_body = cased_body;
+
+        Ok(())
+    }
+
+    /// Index the [clean titles](crate::webpage::Html::clean_title) of inserted pages,
+    /// so they are displayed and matched without a trailing site name.
+    pub fn set_clean_titles(&mut self, clean_titles: bool) -> Result<()> {
+        self.check_setting("clean_titles", self.settings.clean_titles, clean_titles)?;
+        self.settings.clean_titles = clean_titles;
+
+        Ok(())
+    }
+
+    /// Whether the index was built with clean titles.
+    pub fn has_clean_titles(&self) -> bool {
+        self.settings.clean_titles
+    }
+
+    /// Whether the index was built with a cased body. Case sensitive
+    /// phrases are matched as normal phrases otherwise.
+    pub fn has_cased_body(&self) -> bool {
+        self.settings.cased_body
+    }
+
+    /// Settings are saved with the index, so they can only be changed
+    /// while the index has no committed documents.
+    fn check_setting<T: PartialEq + std::fmt::Display>(
+        &self,
+        setting: &'static str,
+        built: T,
+        requested: T,
+    ) -> Result<()> {
+        if built != requested && self.tv_searcher().num_docs() > 0 {
+            return Err(Error::IndexSettingMismatch {
+                setting,
+                built: built.to_string(),
+                requested: requested.to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn save_settings(&self) -> Result<()> {
+        self.tantivy_index.directory().atomic_write(
+            Path::new(SETTINGS_FILE),
+            &serde_json::to_vec(&self.settings)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Longest token in bytes that is indexed and searched for. The limit is saved
+    /// with the index, so searches use the limit the index was built with.
+    pub fn set_max_token_len(&mut self, max_token_len: usize) -> Result<()> {
+        self.check_setting("max_token_len", self.settings.max_token_len, max_token_len)?;
+
+        self.settings.max_token_len = max_token_len;
+        register_word_tokenizers(&self.tantivy_index, max_token_len);
+
+        Ok(())
+    }
+
+    pub fn max_token_len(&self) -> usize {
+        self.settings.max_token_len
+    }
+
     pub fn set_auto_merge_policy(&mut self) {
         let merge_policy = tantivy::merge_policy::LogMergePolicy::default();
         self.writer
@@ -284,11 +498,42 @@ impl InvertedIndex {
         Ok(s)
     }
 
-    pub fn insert(&self, webpage: Webpage) -> Result<()> {
+    pub fn insert(&self, mut webpage: Webpage) -> Result<()> {
+        webpage.html.set_use_clean_title(self.settings.clean_titles);
+
+        let url = self
+            .settings
+            .url_char_ngrams
+            .then(|| webpage.html.url().to_string());
+        let cased_body = self
+            .settings
+            .cased_body
+            .then(|| webpage.html.clean_text().cloned().unwrap_or_default());
+
+        let mut doc = webpage.into_tantivy(&self.schema, self.settings.max_token_len)?;
+
+        if let Some(url) = url {
+            doc.add_text(
+                self.schema
+                    .get_field(Field::Text(TextField::UrlCharNgrams).name())
+                    .expect("Failed to get url-char-ngrams field"),
+                url,
+            );
+        }
+
+        if let Some(cased_body) = cased_body {
+            doc.add_text(
+                self.schema
+                    .get_field(Field::Text(TextField::CleanBodyCased).name())
+                    .expect("Failed to get cased clean body field"),
+                cased_body,
+            );
+        }
+
         self.writer
             .as_ref()
             .expect("writer has not been prepared")
-            .add_document(webpage.into_tantivy(&self.schema)?)?;
+            .add_document(doc)?;
         Ok(())
     }
 
@@ -298,6 +543,31 @@ impl InvertedIndex {
             .as_mut()
             .expect("writer has not been prepared")
             .commit()?;
+        self.save_settings()?;
+        self.reload()
+    }
+
+    /// Commit and store `payload` in the index metadata together with the commit.
+    /// Readers can use [`InvertedIndex::commit_payload`] to check whether the commit happened.
+    pub fn commit_with_payload(&mut self, payload: &str) -> Result<()> {
+        self.prepare_writer()?;
+        let mut prepared = self
+            .writer
+            .as_mut()
+            .expect("writer has not been prepared")
+            .prepare_commit()?;
+        prepared.set_payload(payload);
+        prepared.commit()?;
+        self.save_settings()?;
+        self.reload()
+    }
+
+    /// Payload of the last commit made with [`InvertedIndex::commit_with_payload`].
+    pub fn commit_payload(&self) -> Result<Option<String>> {
+        Ok(self.tantivy_index.load_metas()?.payload)
+    }
+
+    fn reload(&mut self) -> Result<()> {
         self.reader.reload()?;
         self.fastfield_reader = FastFieldReader::new(&self.reader.searcher());
 
@@ -356,6 +626,187 @@ impl InvertedIndex {
         })
     }
 
+    /// Number of documents matching the query for each of the `top_n` hosts with most matches.
+    pub fn host_facets(&self, query: &Query, ctx: &Ctx, top_n: usize) -> Result<Vec<HostFacet>> {
+        let collector = HostFacetCollector::new(top_n, ctx.fastfield_reader.clone());
+        let counts = ctx.tv_searcher.search(query, &collector)?;
+
+        let mut facets = Vec::with_capacity(counts.len());
+
+        for site_count in counts {
+            let webpage = self.retrieve_doc(site_count.example, &ctx.tv_searcher)?;
+
+            if let Some(host) = Url::parse(&webpage.url)
+                .ok()
+                .and_then(|url| url.normalized_host().map(|host| host.to_string()))
+            {
+                facets.push(HostFacet {
+                    host,
+                    count: site_count.count,
+                });
+            }
+        }
+
+        Ok(facets)
+    }
+
+    /// Number of documents matching the query in each group, where the group of a document
+    /// is the hash stored in the two `hash_fields`.
+    pub fn group_counts(
+        &self,
+        query: &Query,
+        ctx: &Ctx,
+        hash_fields: [FastField; 2],
+    ) -> Result<HashMap<Prehashed, u64>> {
+        let collector = GroupCountCollector::new(hash_fields, ctx.fastfield_reader.clone());
+
+        Ok(ctx.tv_searcher.search(query, &collector)?)
+    }
+
+    /// Scan all documents in the index and group near-duplicates based on the simhash of
+    /// their clean text. Only the hashes are kept in memory during the scan. The urls
+    /// for each cluster are retrieved as the returned iterator is consumed.
+    pub fn near_duplicate_clusters(&self) -> impl Iterator<Item = Result<Vec<String>>> + '_ {
+        let tv_searcher = self.tv_searcher();
+        let mut hashes = Vec::new();
+
+        for (segment_ord, segment_reader) in tv_searcher.segment_readers().iter().enumerate() {
+            let fastfield_reader = self
+                .fastfield_reader
+                .get_segment(&segment_reader.segment_id());
+
+            for doc_id in segment_reader.doc_ids_alive() {
+                let hash = fastfield_reader
+                    .get_field_reader(&doc_id)
+                    .get(&FastField::SimHash);
+
+                // documents without any text have a simhash of 0
+                if hash != 0 {
+                    hashes.push((
+                        hash,
+                        DocAddress {
+                            segment: segment_ord as u32,
+                            doc_id,
+                        },
+                    ));
+                }
+            }
+        }
+
+        let mut clusters = simhash::near_duplicate_clusters(hashes);
+
+        for cluster in &mut clusters {
+            cluster.sort_by_key(|address| (address.segment, address.doc_id));
+        }
+        clusters.sort_by_key(|cluster| {
+            cluster
+                .first()
+                .map(|address| (address.segment, address.doc_id))
+        });
+
+        clusters.into_iter().map(move |cluster| {
+            cluster
+                .into_iter()
+                .map(|address| Ok(self.retrieve_doc(address, &tv_searcher)?.url))
+                .collect()
+        })
+    }
+
+    fn min_hash(&self, address: DocAddress, tv_searcher: &tantivy::Searcher) -> Option<MinHash> {
+        let segment_reader = tv_searcher.segment_reader(address.segment);
+        let fastfield_reader = self
+            .fastfield_reader
+            .get_segment(&segment_reader.segment_id());
+        let field_reader = fastfield_reader.get_field_reader(&address.doc_id);
+
+        MinHash::from_packed([
+            field_reader.get(&FastField::MinHash1),
+            field_reader.get(&FastField::MinHash2),
+            field_reader.get(&FastField::MinHash3),
+            field_reader.get(&FastField::MinHash4),
+        ])
+    }
+
+    /// Find the `limit` documents whose clean text is most similar to the document with `url`,
+    /// based on the minhash signatures stored in the fast fields. Only the documents that share
+    /// at least one lsh band with the target are scored, so documents with a low similarity
+    /// might be missed. Returns the documents together with their estimated jaccard similarity,
+    /// most similar first.
+    pub fn similar_documents(
+        &self,
+        url: &str,
+        limit: usize,
+    ) -> Result<Vec<(RetrievedWebpage, f64)>> {
+        let tv_searcher = self.tv_searcher();
+
+        let target = match self.doc_address(url, &tv_searcher) {
+            Some(address) => address,
+            None => return Ok(Vec::new()),
+        };
+
+        let target_hash = match self.min_hash(target, &tv_searcher) {
+            Some(hash) => hash,
+            None => return Ok(Vec::new()),
+        };
+
+        let bands_field = tv_searcher
+            .schema()
+            .get_field(Field::Text(TextField::MinHashBands).name())
+            .unwrap();
+
+        let candidates_query = tantivy::query::BooleanQuery::new(
+            target_hash
+                .bands()
+                .iter()
+                .map(|band| {
+                    let term = tantivy::Term::from_field_text(bands_field, band);
+                    let query: Box<dyn tantivy::query::Query> =
+                        Box::new(tantivy::query::TermQuery::new(
+                            term,
+                            tantivy::schema::IndexRecordOption::Basic,
+                        ));
+
+                    (tantivy::query::Occur::Should, query)
+                })
+                .collect(),
+        );
+
+        let candidates =
+            tv_searcher.search(&candidates_query, &tantivy::collector::DocSetCollector)?;
+
+        let mut scored = Vec::new();
+
+        for address in candidates {
+            let address: DocAddress = address.into();
+
+            if address == target {
+                continue;
+            }
+
+            if let Some(hash) = self.min_hash(address, &tv_searcher) {
+                let similarity = target_hash.similarity(&hash);
+
+                if similarity > 0.0 {
+                    scored.push((address, similarity));
+                }
+            }
+        }
+
+        scored.sort_by(|(a_address, a), (b_address, b)| {
+            b.total_cmp(a).then_with(|| {
+                (a_address.segment, a_address.doc_id).cmp(&(b_address.segment, b_address.doc_id))
+            })
+        });
+        scored.truncate(limit);
+
+        scored
+            .into_iter()
+            .map(|(address, similarity)| {
+                Ok((self.retrieve_doc(address, &tv_searcher)?, similarity))
+            })
+            .collect()
+    }
+
     pub fn local_search_ctx(&self) -> Ctx {
         let tv_searcher = self.tv_searcher();
         Ctx {
@@ -435,12 +886,34 @@ impl InvertedIndex {
         query: &Query,
     ) -> Result<Vec<RetrievedWebpage>> {
         let tv_searcher = self.reader.searcher();
+        let phrases: Vec<_> = query
+            .terms()
+            .iter()
+            .filter_map(|term| match term {
+                Term::Phrase(phrase) if !phrase.trim().is_empty() => Some(phrase.as_str()),
+                _ => None,
+            })
+            .collect();
+
         let mut webpages: Vec<RetrievedWebpage> = websites
             .iter()
-            .map(|website| self.retrieve_doc(website.address, &tv_searcher))
-            .filter_map(|res| res.ok())
+            .filter_map(|website| {
+                let mut page = self.retrieve_doc(website.address, &tv_searcher).ok()?;
+
+                if !phrases.is_empty() {
+                    page.phrase_matches = self
+                        .phrase_matches(website.address, &phrases, &tv_searcher)
+                        .unwrap_or_default();
+                }
+
+                Some(page)
+            })
             .collect();
 
+        if !query.generate_snippets() {
+            return Ok(webpages);
+        }
+
         for (url, page) in webpages.iter_mut().filter_map(|page| {
             let url = Url::parse(&page.url).ok()?;
             Some((url, page))
@@ -499,6 +972,23 @@ impl InvertedIndex {
                         &page.region,
                         self.snippet_config.clone(),
                     );
+
+                    // the query only matched outside the body (e.g. in the title),
+                    // so the description is likely more informative than the body prefix.
+                    if !page.snippet.has_highlights() {
+                        if let Some(description) = page
+                            .description()
+                            .filter(|desc| !desc.trim().is_empty())
+                            .cloned()
+                        {
+                            page.snippet = snippet::generate(
+                                query,
+                                &description,
+                                &page.region,
+                                self.snippet_config.clone(),
+                            );
+                        }
+                    }
                 }
             }
         }
@@ -526,6 +1016,57 @@ impl InvertedIndex {
         Ok(())
     }
 
+    /// Token positions where each of the `phrases` occurs in the positioned fields
+    /// of the document. Phrases that do not occur in a field are left out.
+    fn phrase_matches(
+        &self,
+        address: DocAddress,
+        phrases: &[&str],
+        tv_searcher: &tantivy::Searcher,
+    ) -> Result<Vec<PhraseMatch>> {
+        let segment_reader = tv_searcher.segment_reader(address.segment);
+        let mut matches = Vec::new();
+
+        for text_field in PHRASE_MATCH_FIELDS {
+            let field = self.schema.get_field(text_field.name())?;
+            let inverted_index = segment_reader.inverted_index(field)?;
+
+            for phrase in phrases {
+                let mut term_positions = Vec::new();
+
+                for token in
+                    analyze_with_max_token_len(text_field, phrase, self.settings.max_token_len)
+                {
+                    let term = tantivy::Term::from_field_text(field, &token);
+                    let mut positions = Vec::new();
+
+                    if let Some(mut postings) = inverted_index.read_postings(
+                        &term,
+                        tantivy::schema::IndexRecordOption::WithFreqsAndPositions,
+                    )? {
+                        if postings.seek(address.doc_id) == address.doc_id {
+                            postings.positions(&mut positions);
+                        }
+                    }
+
+                    term_positions.push(positions);
+                }
+
+                let positions = phrase_positions(&term_positions);
+
+                if !positions.is_empty() {
+                    matches.push(PhraseMatch {
+                        phrase: phrase.to_string(),
+                        field: text_field.name().to_string(),
+                        positions,
+                    });
+                }
+            }
+        }
+
+        Ok(matches)
+    }
+
     fn retrieve_doc(
         &self,
         doc_address: DocAddress,
@@ -536,6 +1077,11 @@ impl InvertedIndex {
     }
 
     pub fn merge(mut self, mut other: InvertedIndex) -> Self {
+        assert_eq!(
+            self.settings, other.settings,
+            "cannot merge indexes built with different settings"
+        );
+
         self.prepare_writer().expect("failed to prepare writer");
         other.prepare_writer().expect("failed to prepare writer");
 
@@ -626,9 +1172,27 @@ impl InvertedIndex {
         self.tantivy_index.searchable_segments().unwrap().len()
     }
 
-    pub(crate) fn get_webpage(&self, url: &str) -> Option<RetrievedWebpage> {
+    /// Number of terms in the term dictionaries of all indexed fields. Terms that
+    /// occur in multiple segments or fields are counted once for each of them.
+    pub fn num_terms(&self) -> Result<u64> {
+        let tv_searcher = self.tv_searcher();
+        let mut num_terms = 0;
+
+        for segment_reader in tv_searcher.segment_readers() {
+            for (field, entry) in self.schema.fields() {
+                if !entry.is_indexed() {
+                    continue;
+                }
+
+                num_terms += segment_reader.inverted_index(field)?.terms().num_terms() as u64;
+            }
+        }
+
+        Ok(num_terms)
+    }
+
+    fn doc_address(&self, url: &str, tv_searcher: &tantivy::Searcher) -> Option<DocAddress> {
         let url = Url::parse(url).ok()?;
-        let tv_searcher = self.reader.searcher();
         let field = tv_searcher
             .schema()
             .get_field(Field::Text(TextField::UrlNoTokenizer).name())
@@ -642,8 +1206,14 @@ impl InvertedIndex {
             .search(&query, &tantivy::collector::TopDocs::with_limit(1))
             .unwrap();
 
-        res.pop()
-            .map(|(_, doc)| self.retrieve_doc(doc.into(), &tv_searcher).unwrap())
+        res.pop().map(|(_, doc)| doc.into())
+    }
+
+    pub(crate) fn get_webpage(&self, url: &str) -> Option<RetrievedWebpage> {
+        let tv_searcher = self.reader.searcher();
+        let address = self.doc_address(url, &tv_searcher)?;
+
+        Some(self.retrieve_doc(address, &tv_searcher).unwrap())
     }
 
     pub(crate) fn get_homepage(&self, url: &Url) -> Option<RetrievedWebpage> {
@@ -668,6 +1238,40 @@ impl InvertedIndex {
     }
 }
 
+/// Positioned fields that are checked for phrase matches.
+const PHRASE_MATCH_FIELDS: [TextField; 2] = [TextField::Title, TextField::CleanBody];
+
+/// Start of every run of consecutive positions where the `i`th
+/// list contains the position of the `i`th token of a phrase.
+fn phrase_positions(term_positions: &[Vec<u32>]) -> Vec<u32> {
+    let (first, rest) = match term_positions.split_first() {
+        Some(split) => split,
+        None => return Vec::new(),
+    };
+
+    first
+        .iter()
+        .copied()
+        .filter(|start| {
+            rest.iter()
+                .zip(1..)
+                .all(|(positions, offset)| positions.binary_search(&(start + offset)).is_ok())
+        })
+        .collect()
+}
+
+/// The token positions where a phrase from the query matched a field of a result.
+/// Positions count the tokens produced by the tokenizer of the field, starting from 0.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PhraseMatch {
+    pub phrase: String,
+    /// Name of the matched field, e.g. `title` or `body`.
+    pub field: String,
+    /// Position of the first token of each match.
+    pub positions: Vec<u32>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SearchResult {
     pub num_docs: Option<usize>,
@@ -689,6 +1293,9 @@ pub struct RetrievedWebpage {
     pub likely_has_ads: bool,
     pub likely_has_paywall: bool,
     pub recipe_first_ingredient_tag_id: Option<String>,
+    /// Only set for queries with phrases.
+    #[serde(default)]
+    pub phrase_matches: Vec<PhraseMatch>,
 }
 impl RetrievedWebpage {
     pub fn description(&self) -> Option<&String> {
@@ -818,6 +1425,8 @@ mod tests {
         webpage::Html,
     };
 
+    use crate::query::parser::analyze;
+
     use super::*;
 
     const CONTENT: &str = "this is the best example website ever this is the best example website ever this is the best example website ever this is the best example website ever this is the best example website ever this is the best example website ever";
@@ -1124,8 +1733,8 @@ mod tests {
     fn limited_top_docs() {
         let mut index = InvertedIndex::temporary().expect("Unable to open index");
 
-        for _ in 0..100 {
-            let dedup_s = crate::rand_words(100);
+        for i in 0..100 {
+            let dedup_s = crate::rand_words_seeded(100, i);
 
             index
                 .insert(
@@ -1238,7 +1847,7 @@ mod tests {
                 </body>
             </html>
             "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 1)
                     ),
                     "https://www.example.com",
                 )
@@ -1262,7 +1871,7 @@ mod tests {
                 </body>
             </html>
             "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 2)
                     ),
                     "https://www.example.com",
                 )
@@ -1573,7 +2182,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                    CONTENT = crate::rand_words(100)
+                    CONTENT = crate::rand_words_seeded(100, 3)
                 ),
                 "https://www.a.com",
             )
@@ -1594,7 +2203,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                    CONTENT = crate::rand_words(100)
+                    CONTENT = crate::rand_words_seeded(100, 4)
                 ),
                 "https://www.b.com",
             )
@@ -1650,4 +2259,244 @@ mod tests {
 
         assert_eq!(result.documents.len(), 1);
     }
+
+    #[test]
+    fn schema_version_mismatch() {
+        let path = crate::gen_temp_path();
+
+        let index = InvertedIndex::open(&path).unwrap();
+        drop(index);
+
+        assert!(InvertedIndex::open(&path).is_ok());
+
+        // an index created with an older schema that is missing fields
+        let path = crate::gen_temp_path();
+        let mut builder = Schema::builder();
+        builder.add_text_field(
+            Field::Text(TextField::Title).name(),
+            tantivy::schema::TEXT | tantivy::schema::STORED,
+        );
+        let legacy_schema = builder.build();
+        let legacy_version = schema_fingerprint(&legacy_schema);
+
+        fs::create_dir_all(&path).unwrap();
+        tantivy::Index::create_in_dir(&path, legacy_schema).unwrap();
+
+        for mode in [IndexOpenMode::Mmap, IndexOpenMode::Ram] {
+            let files_before = fs::read_dir(&path).unwrap().count();
+
+            let err = InvertedIndex::open_with_mode(&path, mode).err().unwrap();
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::SchemaMismatch { found, .. }) if *found == legacy_version
+            ));
+
+            // opening the index does not write to it
+            assert_eq!(fs::read_dir(&path).unwrap().count(), files_before);
+        }
+    }
+
+    #[test]
+    fn settings_saved_with_index() {
+        let path = crate::gen_temp_path();
+
+        let mut index = InvertedIndex::open(&path).unwrap();
+        index.prepare_writer().unwrap();
+        index.set_max_token_len(DEFAULT_MAX_TOKEN_LEN * 2).unwrap();
+        index.set_cased_body(true).unwrap();
+        index.set_url_char_ngrams(true).unwrap();
+        index.set_clean_titles(true).unwrap();
+        index
+            .insert(
+                Webpage::new(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Website</title>
+                            </head>
+                            <body>
+                                {CONTENT}
+                            </body>
+                        </html>
+                    "#
+                    ),
+                    "https://www.first.com",
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        index.commit().unwrap();
+        index.stop();
+
+        for mode in [IndexOpenMode::Mmap, IndexOpenMode::Ram] {
+            let mut index = InvertedIndex::open_with_mode(&path, mode).unwrap();
+            assert_eq!(index.max_token_len(), DEFAULT_MAX_TOKEN_LEN * 2);
+
+            // the index already has documents built with the saved limit
+            let err = index
+                .set_max_token_len(DEFAULT_MAX_TOKEN_LEN)
+                .err()
+                .unwrap();
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::IndexSettingMismatch {
+                    setting: "max_token_len",
+                    ..
+                })
+            ));
+            assert!(index.set_max_token_len(DEFAULT_MAX_TOKEN_LEN * 2).is_ok());
+
+            assert!(index.has_cased_body());
+            assert!(index.set_cased_body(false).is_err());
+
+            assert!(index.has_url_char_ngrams());
+            assert!(index.set_url_char_ngrams(false).is_err());
+
+            assert!(index.has_clean_titles());
+            assert!(index.set_clean_titles(false).is_err());
+        }
+    }
+
+    #[test]
+    fn near_duplicate_clusters() {
+        let mut index = InvertedIndex::temporary().expect("Unable to open index");
+
+        for (url, body) in [
+            ("https://www.first.com", CONTENT),
+            ("https://www.mirror.com", CONTENT),
+            (
+                "https://www.unique.com",
+                "a completely different page about gardening with tomatoes, potatoes and plenty of other vegetables that grow well in the summer",
+            ),
+        ] {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
+                        <html>
+                            <head>
+                                <title>Website</title>
+                            </head>
+                            <body>
+                                {body}
+                            </body>
+                        </html>
+                    "#
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+
+        let clusters: Vec<Vec<String>> = index
+            .near_duplicate_clusters()
+            .collect::<Result<_>>()
+            .unwrap();
+
+        assert_eq!(clusters.len(), 1);
+
+        let mut cluster = clusters[0].clone();
+        cluster.sort();
+
+        assert_eq!(
+            cluster,
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.mirror.com/".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn phrase_matches() {
+        let mut index = InvertedIndex::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Quick brown fox</title>
+                            </head>
+                            <body>
+                                the quick brown fox jumps over the lazy dog and the quick brown fox sleeps
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.example.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let ctx = index.local_search_ctx();
+
+        let query = Query::parse(
+            &ctx,
+            &SearchQuery {
+                query: "\"quick brown fox\"".to_string(),
+                ..Default::default()
+            },
+            &index,
+        )
+        .expect("Failed to parse query");
+
+        let ranker = Ranker::new(
+            SignalAggregator::new(Some(&query)),
+            ctx.fastfield_reader.clone(),
+            Default::default(),
+        );
+        let result =
+            search(&index, &query, &ctx, ranker.collector(ctx.clone())).expect("Search failed");
+
+        assert_eq!(result.documents.len(), 1);
+        let webpage = &result.documents[0];
+
+        assert_eq!(
+            webpage.phrase_matches,
+            vec![
+                PhraseMatch {
+                    phrase: "quick brown fox".to_string(),
+                    field: TextField::Title.name().to_string(),
+                    positions: vec![0],
+                },
+                PhraseMatch {
+                    phrase: "quick brown fox".to_string(),
+                    field: TextField::CleanBody.name().to_string(),
+                    positions: vec![1, 11],
+                },
+            ]
+        );
+
+        let body_tokens = analyze(TextField::CleanBody, &webpage.body);
+        for position in &webpage.phrase_matches[1].positions {
+            let position = *position as usize;
+            assert_eq!(
+                body_tokens[position..position + 3],
+                ["quick", "brown", "fox"]
+            );
+        }
+
+        let query = Query::parse(
+            &ctx,
+            &SearchQuery {
+                query: "quick fox".to_string(),
+                ..Default::default()
+            },
+            &index,
+        )
+        .expect("Failed to parse query");
+
+        let result =
+            search(&index, &query, &ctx, ranker.collector(ctx.clone())).expect("Search failed");
+
+        assert_eq!(result.documents.len(), 1);
+        assert!(result.documents[0].phrase_matches.is_empty());
+    }
 }
@@ -57,6 +57,7 @@ mod leaky_queue;
 mod live_index;
 mod llm_utils;
 mod metrics;
+mod minhash;
 mod models;
 pub mod naive_bayes;
 pub mod prehashed;
@@ -93,6 +94,12 @@ pub enum Error {
     #[error("Failed to download warc files after all retries")]
     DownloadFailed,
 
+    #[error("Byte range of {length} bytes at offset {offset} is out of bounds")]
+    InvalidByteRange { offset: u64, length: u64 },
+
+    #[error("Server ignored the range request and returned the entire file")]
+    RangeIgnored,
+
     #[error("Query cannot be completely empty")]
     EmptyQuery,
 
@@ -110,6 +117,29 @@ pub enum Error {
 
     #[error("Unknown webpage robots meta tag")]
     UnknownRobotsMetaTag,
+
+    #[error("HTML input of {size} bytes exceeds the maximum of {max} bytes")]
+    HtmlTooLarge { size: usize, max: usize },
+
+    #[error(
+        "Index schema mismatch (expected version {expected}, found {found}). Reindex required"
+    )]
+    SchemaMismatch { expected: String, found: String },
+
+    #[error("Executor needs at least one thread")]
+    NoExecutorThreads,
+
+    #[error(
+        "No fields to search for the term '{0}'. Check that the configured query fields exist"
+    )]
+    NoQueryFields(String),
+
+    #[error("Index was built with {setting} = {built} and cannot be changed to {requested}")]
+    IndexSettingMismatch {
+        setting: &'static str,
+        built: String,
+        requested: String,
+    },
 }
 
 pub type Result<T, E = anyhow::Error> = std::result::Result<T, E>;
@@ -142,14 +172,13 @@ pub fn gen_temp_path() -> PathBuf {
 }
 
 #[cfg(test)]
-fn rand_words(num_words: usize) -> String {
-    use rand::{distributions::Alphanumeric, Rng};
+fn rand_words_with<R: rand::Rng>(rng: &mut R, num_words: usize) -> String {
+    use rand::distributions::Alphanumeric;
     let mut res = String::new();
 
     for _ in 0..num_words {
         res.push_str(
-            rand::thread_rng()
-                .sample_iter(&Alphanumeric)
+            rng.sample_iter(&Alphanumeric)
                 .take(30)
                 .map(char::from)
                 .collect::<String>()
@@ -161,6 +190,21 @@ fn rand_words(num_words: usize) -> String {
     res.trim().to_string()
 }
 
+/// Random words that are different on every call. Useful for fuzzing,
+/// but tests should prefer [`rand_words_seeded`] so failures can be reproduced.
+#[cfg(test)]
+#[allow(unused)]
+fn rand_words(num_words: usize) -> String {
+    rand_words_with(&mut rand::thread_rng(), num_words)
+}
+
+/// Random words that are the same for the same `seed`.
+#[cfg(test)]
+fn rand_words_seeded(num_words: usize, seed: u64) -> String {
+    use rand::SeedableRng;
+    rand_words_with(&mut rand::rngs::StdRng::seed_from_u64(seed), num_words)
+}
+
 fn ceil_char_boundary(str: &str, index: usize) -> usize {
     let mut res = index;
 
@@ -193,6 +237,13 @@ pub fn combine_u64s(nums: [u64; 2]) -> u128 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn seeded_rand_words() {
+        assert_eq!(rand_words_seeded(10, 42), rand_words_seeded(10, 42));
+        assert_ne!(rand_words_seeded(10, 42), rand_words_seeded(10, 43));
+        assert_eq!(rand_words_seeded(10, 42).split(' ').count(), 10);
+    }
+
     #[test]
     fn split_combine_u128() {
         for num in 0..10000_u128 {
@@ -312,6 +312,8 @@ impl From<&LiveIndexConfig> for CrawlerConfig {
                 access_key: String::new(),
                 secret_key: String::new(),
                 endpoint: String::new(),
+                region: String::new(),
+                force_path_style: true,
             },
             router_hosts: Vec::new(),
         }
@@ -326,6 +328,10 @@ pub struct IndexManager {
 impl IndexManager {
     pub fn new(config: LiveIndexConfig) -> Result<Self> {
         let index = Index::new(&config.index_path)?;
+        index
+            .write()
+            .inverted_index
+            .set_max_token_len(config.max_token_len)?;
         let indexer = Arc::new(Indexer {
             search_index: index.clone_inner_index(),
             worker: IndexingWorker::new(
@@ -173,6 +173,9 @@ enum CentralityMode {
     Host {
         webgraph_path: String,
         output_path: String,
+        /// Maximum number of inbound neighbors to keep for each host in the inbound similarity.
+        #[clap(long)]
+        max_inbound: Option<usize>,
     },
     /// Calculate metrics for the page webgraph.
     Page {
@@ -208,6 +211,14 @@ enum IndexingOptions {
         paths: Vec<String>,
     },
 
+    /// Merge the segments of a search index to speed up queries.
+    Optimize {
+        path: String,
+
+        #[clap(long, default_value_t = 1)]
+        max_num_segments: u64,
+    },
+
     /// Create the entity index. Used in the sidebar of the search UI.
     Entity {
         wikipedia_dump_path: String,
@@ -260,15 +271,24 @@ fn main() -> Result<()> {
                     .collect::<Vec<_>>();
                 entrypoint::indexer::Indexer::merge(pointers)?;
             }
+            IndexingOptions::Optimize {
+                path,
+                max_num_segments,
+            } => entrypoint::indexer::Indexer::optimize(path, max_num_segments)?,
         },
         Commands::Centrality { mode } => {
             match mode {
                 CentralityMode::Host {
                     webgraph_path,
                     output_path,
+                    max_inbound,
                 } => {
                     entrypoint::Centrality::build_harmonic(&webgraph_path, &output_path);
-                    entrypoint::Centrality::build_similarity(&webgraph_path, &output_path);
+                    entrypoint::Centrality::build_similarity(
+                        &webgraph_path,
+                        &output_path,
+                        max_inbound,
+                    );
                 }
                 CentralityMode::Page {
                     webgraph_path,
@@ -0,0 +1,195 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! MinHash signatures to estimate the jaccard similarity between the word shingles of two texts.
+//!
+//! Each minimum is truncated to 16 bits (b-bit minhash) so the entire
+//! signature fits in [`NUM_PACKED`] `u64`s that can be stored as fast fields.
+
+use crate::tokenizer::Tokenizer;
+
+const SHINGLE_SIZE: usize = 2;
+const HASHES_PER_PACKED: usize = (u64::BITS / u16::BITS) as usize;
+
+/// Number of `u64`s the signature is packed into.
+pub const NUM_PACKED: usize = 4;
+pub const NUM_HASHES: usize = NUM_PACKED * HASHES_PER_PACKED;
+
+const ROWS_PER_BAND: usize = 2;
+/// Number of bands the signature is split into for locality sensitive hashing.
+pub const NUM_BANDS: usize = NUM_HASHES / ROWS_PER_BAND;
+
+// the signatures are stored in the index, so the shingle hash must be stable
+// across builds and platforms (unlike `DefaultHasher`)
+fn hash_shingle(shingle: &[String]) -> u64 {
+    let digest = md5::compute(shingle.join(" "));
+    u128::from_le_bytes(*digest) as u64
+}
+
+// splitmix64 is used to derive a hash for each permutation from a single shingle hash
+fn permute(hash: u64, seed: usize) -> u64 {
+    let mut z = hash.wrapping_add((seed as u64 + 1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MinHash([u16; NUM_HASHES]);
+
+impl MinHash {
+    /// Signature of the word shingles in `text`. Returns `None` if
+    /// the text has no words.
+    pub fn new(text: &str) -> Option<Self> {
+        let mut tokenizer = Tokenizer::default();
+        let mut stream = tantivy::tokenizer::Tokenizer::token_stream(&mut tokenizer, text);
+
+        let mut words = Vec::new();
+        while let Some(token) = stream.next() {
+            words.push(token.text.to_lowercase());
+        }
+
+        if words.is_empty() {
+            return None;
+        }
+
+        let mut mins = [u64::MAX; NUM_HASHES];
+
+        for shingle in words.windows(SHINGLE_SIZE.min(words.len())) {
+            let hash = hash_shingle(shingle);
+
+            for (seed, min) in mins.iter_mut().enumerate() {
+                *min = (*min).min(permute(hash, seed));
+            }
+        }
+
+        Some(Self(
+            mins.map(|min| (min >> (u64::BITS - u16::BITS)) as u16),
+        ))
+    }
+
+    /// Estimated jaccard similarity between the shingles of the two texts.
+    /// The estimate is a multiple of `1 / NUM_HASHES`.
+    pub fn similarity(&self, other: &Self) -> f64 {
+        let equal = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .filter(|(a, b)| a == b)
+            .count();
+
+        equal as f64 / NUM_HASHES as f64
+    }
+
+    /// Locality sensitive hashing bands of the signature, formatted as terms that can
+    /// be indexed. Two texts with a jaccard similarity of `s` share at least one band
+    /// with probability `1 - (1 - s^2)^8`, so similar texts can be found by looking
+    /// up the documents with one of the bands.
+    pub fn bands(&self) -> [String; NUM_BANDS] {
+        std::array::from_fn(|band| {
+            let rows = &self.0[band * ROWS_PER_BAND..(band + 1) * ROWS_PER_BAND];
+            let rows: String = rows.iter().map(|row| format!("{row:04x}")).collect();
+
+            format!("{band}:{rows}")
+        })
+    }
+
+    pub fn to_packed(&self) -> [u64; NUM_PACKED] {
+        let mut packed = [0; NUM_PACKED];
+
+        for (i, hash) in self.0.iter().enumerate() {
+            packed[i / HASHES_PER_PACKED] |=
+                (*hash as u64) << ((i % HASHES_PER_PACKED) as u32 * u16::BITS);
+        }
+
+        packed
+    }
+
+    /// Inverse of [`MinHash::to_packed`]. Documents without any text
+    /// are stored as all zeros and returns `None`.
+    pub fn from_packed(packed: [u64; NUM_PACKED]) -> Option<Self> {
+        if packed.iter().all(|p| *p == 0) {
+            return None;
+        }
+
+        let mut hashes = [0; NUM_HASHES];
+
+        for (i, hash) in hashes.iter_mut().enumerate() {
+            *hash = (packed[i / HASHES_PER_PACKED] >> ((i % HASHES_PER_PACKED) as u32 * u16::BITS))
+                as u16;
+        }
+
+        Some(Self(hashes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity() {
+        let a = MinHash::new("the quick brown fox jumps over the lazy dog").unwrap();
+        let b = MinHash::new("the quick brown fox jumps over the lazy dog").unwrap();
+        let c =
+            MinHash::new("rust is a systems programming language with a borrow checker").unwrap();
+
+        assert_eq!(a.similarity(&b), 1.0);
+        assert!(a.similarity(&c) < 0.2);
+        assert!(MinHash::new("").is_none());
+    }
+
+    #[test]
+    fn bands() {
+        let a = MinHash::new("the quick brown fox jumps over the lazy dog").unwrap();
+        let b = MinHash::new("the quick brown fox jumps over the lazy dog").unwrap();
+        let c =
+            MinHash::new("rust is a systems programming language with a borrow checker").unwrap();
+
+        assert_eq!(a.bands(), b.bands());
+        assert!(a.bands().iter().all(|band| !c.bands().contains(band)));
+
+        // bands at different positions never collide
+        assert!(a.bands()[0].starts_with("0:"));
+        assert_eq!(a.bands()[0].len(), "0:".len() + 4 * ROWS_PER_BAND);
+    }
+
+    #[test]
+    fn packing() {
+        let hash = MinHash::new("the quick brown fox jumps over the lazy dog").unwrap();
+
+        assert_eq!(MinHash::from_packed(hash.to_packed()), Some(hash));
+        assert_eq!(MinHash::from_packed([0; NUM_PACKED]), None);
+    }
+
+    #[test]
+    fn stable_signature() {
+        assert_eq!(
+            hash_shingle(&["hello".to_string(), "world".to_string()]),
+            15055004554908907102
+        );
+
+        assert_eq!(
+            MinHash::new("hello world").unwrap().to_packed(),
+            [
+                1587905019870033440,
+                4090667799334661973,
+                4631822555602159850,
+                10399808391436094675
+            ]
+        );
+    }
+}
@@ -20,13 +20,14 @@ use crate::{
     ranking::SignalCoefficient,
     schema::{Field, TextField},
     search_ctx::Ctx,
-    searcher::SearchQuery,
+    searcher::{ReturnField, SearchQuery},
+    tokenizer,
     webpage::{region::Region, safety_classifier},
     Result,
 };
 use optics::{HostRankings, Optic};
 use std::collections::HashMap;
-use tantivy::query::{BooleanQuery, Occur, QueryClone, TermQuery};
+use tantivy::query::{AllQuery, BooleanQuery, Occur, QueryClone, TermQuery};
 
 mod const_query;
 pub mod intersection;
@@ -44,25 +45,39 @@ const MAX_SIMILAR_TERMS: usize = 10;
 
 #[derive(Clone, Debug)]
 pub struct Query {
-    #[allow(clippy::vec_box)]
-    terms: Vec<Box<Term>>,
+    terms: Vec<Term>,
     simple_terms_text: Vec<String>,
     tantivy_query: Box<BooleanQuery>,
+    exact_phrases: Vec<BooleanQuery>,
     host_rankings: HostRankings,
     offset: usize,
     region: Option<Region>,
     optics: Vec<Optic>,
     top_n: usize,
     count_results: bool,
+    generate_snippets: bool,
 }
 
 impl Query {
     pub fn parse(ctx: &Ctx, query: &SearchQuery, index: &InvertedIndex) -> Result<Query> {
-        let parsed_terms = parser::parse(&query.query);
+        let parsed_terms = if query.case_sensitive_phrases {
+            parser::parse_terms_case_sensitive_phrases(&query.query)
+        } else {
+            parser::parse_terms(&query.query)
+        };
+        let max_token_len = index.max_token_len();
         let mut term_count = HashMap::new();
         let mut terms = Vec::new();
 
         for term in parsed_terms {
+            // such terms are dropped from the index by the tokenizers, so they would
+            // otherwise exclude every result
+            if let Term::Simple(simple) = &term {
+                if tokenizer::has_only_long_tokens(simple.as_str(), max_token_len) {
+                    continue;
+                }
+            }
+
             let count = term_count.entry(term.clone()).or_insert(0);
 
             if *count < MAX_SIMILAR_TERMS {
@@ -76,7 +91,7 @@ impl Query {
             .clone()
             .into_iter()
             .map(|term| CompoundAwareTerm {
-                term: *term,
+                term,
                 adjacent_terms: Vec::new(),
             })
             .collect();
@@ -104,13 +119,99 @@ impl Query {
 
         let schema = index.schema();
 
-        let fields: Vec<tantivy::schema::Field> = schema.fields().map(|(field, _)| field).collect();
+        // `inurl:` substrings only match whole url tokens if the index has no url n-grams
+        let fields: Vec<tantivy::schema::Field> = schema
+            .fields()
+            .map(|(field, _)| field)
+            .filter(|field| {
+                index.has_url_char_ngrams()
+                    || Field::get(field.field_id() as usize)
+                        != Some(Field::Text(TextField::UrlCharNgrams))
+            })
+            .collect();
+
+        let fields_by_name = |names: &[String]| -> Vec<tantivy::schema::Field> {
+            fields
+                .iter()
+                .filter(|field| {
+                    names
+                        .iter()
+                        .any(|name| name == schema.get_field_name(**field))
+                })
+                .copied()
+                .collect()
+        };
+
+        let default_fields = match &index.query_config().default_fields {
+            Some(names) => fields_by_name(names),
+            None => fields.clone(),
+        };
+
+        let phrase_fields = match &index.query_config().phrase_fields {
+            Some(names) => fields_by_name(names),
+            None => default_fields.clone(),
+        };
+
+        let compound_boosts: HashMap<_, _> = index
+            .query_config()
+            .compound_boosts
+            .iter()
+            .filter_map(|(name, boost)| schema.get_field(name).ok().map(|field| (field, *boost)))
+            .collect();
+
+        // indexes without a cased body match case sensitive phrases as normal phrases
+        let case_sensitive_phrases = query.case_sensitive_phrases && index.has_cased_body();
 
         let mut queries: Vec<(Occur, Box<dyn tantivy::query::Query + 'static>)> = compound_terms
             .iter()
-            .map(|term| term.as_tantivy_query(&fields))
+            .map(|term| {
+                if case_sensitive_phrases {
+                    if let Some(query) = term
+                        .term
+                        .as_case_sensitive_tantivy_query(&fields, max_token_len)
+                    {
+                        return Ok(query);
+                    }
+                }
+
+                let is_phrase = match &term.term {
+                    Term::Phrase(_) => true,
+                    Term::Not(subterm) => matches!(**subterm, Term::Phrase(_)),
+                    _ => false,
+                };
+
+                if is_phrase {
+                    term.as_tantivy_query(&fields, &phrase_fields, &compound_boosts, max_token_len)
+                } else {
+                    term.as_tantivy_query(&fields, &default_fields, &compound_boosts, max_token_len)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        // phrases in fields without positions fall back to matching their tokens anywhere
+        let exact_phrases = terms
+            .iter()
+            .filter(|term| {
+                !case_sensitive_phrases
+                    || term
+                        .as_case_sensitive_tantivy_query(&fields, max_token_len)
+                        .is_none()
+            })
+            .filter_map(|term| term.as_exact_phrase_query(&phrase_fields, max_token_len))
             .collect();
 
+        // the clauses added below are filters and should not decide whether the
+        // query only consists of negations
+        let only_negations =
+            !queries.is_empty() && queries.iter().all(|(occur, _)| *occur == Occur::MustNot);
+
+        if !query.restrict_to_sites.is_empty() {
+            queries.push((
+                Occur::Must,
+                Term::into_tantivy_any_site(&query.restrict_to_sites, &fields, max_token_len),
+            ));
+        }
+
         if query.safe_search {
             let field = Field::Text(TextField::SafetyClassification);
             let field = schema.get_field(field.name()).unwrap();
@@ -127,12 +228,18 @@ impl Query {
             ));
         }
 
+        // a boolean query without any positive clauses matches nothing
+        if index.query_config().negations_match_all && only_negations {
+            queries.push((Occur::Must, Box::new(AllQuery)));
+        }
+
         let mut tantivy_query = Box::new(BooleanQuery::new(queries));
 
         let simple_terms_text: Vec<String> = terms
             .clone()
             .into_iter()
-            .filter_map(|term| term.as_simple_text().map(|s| s.to_string()))
+            // phrases keep their case if they are case sensitive
+            .filter_map(|term| term.as_simple_text().map(|s| s.to_lowercase()))
             .flat_map(|term| {
                 // term might be a phrase, so we split it into words
                 term.split_ascii_whitespace()
@@ -164,11 +271,17 @@ impl Query {
             }),
             simple_terms_text,
             tantivy_query,
+            exact_phrases,
             optics,
             offset: query.num_results * query.page,
             region: query.selected_region,
             top_n: query.num_results,
             count_results: query.count_results,
+            generate_snippets: query
+                .fields
+                .as_ref()
+                .map(|fields| fields.contains(&ReturnField::Snippet))
+                .unwrap_or(true),
         })
     }
 
@@ -176,20 +289,40 @@ impl Query {
         self.count_results
     }
 
+    /// Whether snippets should be generated for the retrieved webpages.
+    /// This is not the case if the query selects fields without the snippet.
+    pub fn generate_snippets(&self) -> bool {
+        self.generate_snippets
+    }
+
     pub fn simple_terms(&self) -> &[String] {
         &self.simple_terms_text
     }
 
-    pub fn terms(&self) -> &[Box<Term>] {
+    pub fn terms(&self) -> &[Term] {
         &self.terms
     }
 
+    /// Queries that match the phrases of the query without the fallback for fields without
+    /// positions. Results that do not match all of them are flagged with
+    /// [`crate::ranking::Signal::PhraseFallback`].
+    pub fn exact_phrases(&self) -> &[BooleanQuery] {
+        &self.exact_phrases
+    }
+
     pub fn optics(&self) -> &[Optic] {
         &self.optics
     }
 
+    /// The tantivy query the terms and optics were lowered into.
+    pub fn tantivy_query(&self) -> &BooleanQuery {
+        &self.tantivy_query
+    }
+
+    /// A query is empty if it has no terms or only operators without
+    /// values (like `site:`).
     pub fn is_empty(&self) -> bool {
-        self.terms.is_empty()
+        self.terms.iter().all(Term::is_empty_operator)
     }
 
     pub fn num_results(&self) -> usize {
@@ -241,8 +374,9 @@ impl tantivy::query::Query for Query {
 #[cfg(test)]
 mod tests {
     use crate::{
+        config::QueryConfig,
         index::Index,
-        rand_words,
+        rand_words_seeded,
         searcher::{LocalSearcher, SearchQuery},
         webpage::Webpage,
     };
@@ -439,234 +573,344 @@ mod tests {
     }
 
     #[test]
-    fn site_query() {
+    fn only_not_query() {
         let mut index = Index::temporary().expect("Unable to open index");
 
-        index
-            .insert(
-                Webpage::new(
-                    r#"
+        for (i, (body, url)) in [
+            ("This page is full of spam", "https://www.first.com"),
+            ("This is a test website", "https://www.second.com"),
+            ("This is another test website", "https://www.third.com"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
                         <html>
                             <head>
                                 <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website
+                                {body} {}
                             </body>
                         </html>
                     "#,
-                    "https://www.first.com",
+                            rand_words_seeded(100, 200 + i as u64)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
                 )
-                .unwrap(),
-            )
-            .expect("failed to insert webpage");
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+        let mut searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "-spam".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.into_iter().map(|w| w.url).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.second.com/".to_string(),
+                "https://www.third.com/".to_string(),
+            ]
+        );
+
+        searcher.set_query_config(QueryConfig {
+            negations_match_all: false,
+            ..Default::default()
+        });
+        let result = searcher.search(&query).expect("Search failed");
+        assert!(result.webpages.is_empty());
+    }
+
+    #[test]
+    fn long_tokens_are_dropped() {
+        let long_token = "a".repeat(tokenizer::DEFAULT_MAX_TOKEN_LEN * 10);
+
+        let mut index = Index::temporary().expect("Unable to open index");
+
         index
             .insert(
                 Webpage::new(
-                    r#"
+                    &format!(
+                        r#"
                         <html>
                             <head>
-                                <title>Test test</title>
+                                <title>Test website</title>
                             </head>
                             <body>
-                                This test page does not contain the forbidden word
+                                test {long_token} {}
                             </body>
                         </html>
                     "#,
-                    "https://www.second.com",
+                        rand_words_seeded(100, 220)
+                    ),
+                    "https://www.first.com",
                 )
                 .unwrap(),
             )
             .expect("failed to insert webpage");
         index.commit().expect("failed to commit index");
+
+        let tv_searcher = index.inverted_index.tv_searcher();
+        let field = tv_searcher
+            .schema()
+            .get_field(Field::Text(TextField::CleanBody).name())
+            .unwrap();
+
+        for segment_reader in tv_searcher.segment_readers() {
+            let terms = segment_reader.inverted_index(field).unwrap();
+            assert!(terms.terms().get(&long_token).unwrap().is_none());
+            assert!(terms.terms().get("test").unwrap().is_some());
+        }
+
         let searcher = LocalSearcher::from(index);
 
-        let query = SearchQuery {
-            query: "test site:first.com".to_string(),
-            ..Default::default()
-        };
-        let result = searcher.search(&query).expect("Search failed");
-        assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+        let result = searcher
+            .search(&SearchQuery {
+                query: format!("test {long_token}"),
+                ..Default::default()
+            })
+            .expect("Search failed");
 
-        let query = SearchQuery {
-            query: "test site:www.first.com".to_string(),
-            ..Default::default()
-        };
-        let result = searcher.search(&query).expect("Search failed");
         assert_eq!(result.webpages.len(), 1);
         assert_eq!(result.webpages[0].url, "https://www.first.com/");
-
-        let query = SearchQuery {
-            query: "test -site:first.com".to_string(),
-            ..Default::default()
-        };
-        let result = searcher.search(&query).expect("Search failed");
-        assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.second.com/");
     }
 
     #[test]
-    fn title_query() {
+    fn configured_max_token_len() {
+        let long_token = "a".repeat(tokenizer::DEFAULT_MAX_TOKEN_LEN + 50);
+
         let mut index = Index::temporary().expect("Unable to open index");
+        index
+            .inverted_index
+            .set_max_token_len(tokenizer::DEFAULT_MAX_TOKEN_LEN * 2)
+            .unwrap();
 
         index
             .insert(
                 Webpage::new(
-                    r#"
+                    &format!(
+                        r#"
                         <html>
                             <head>
                                 <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website
+                                {long_token} {}
                             </body>
                         </html>
                     "#,
+                        rand_words_seeded(100, 222)
+                    ),
                     "https://www.first.com",
                 )
                 .unwrap(),
             )
             .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+
+        let searcher = LocalSearcher::from(index);
+
+        let result = searcher
+            .search(&SearchQuery {
+                query: long_token,
+                ..Default::default()
+            })
+            .expect("Search failed");
+
+        assert!(!result.empty_query);
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+    }
+
+    #[test]
+    fn only_long_tokens_is_empty_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
         index
             .insert(
                 Webpage::new(
-                    r#"
+                    &format!(
+                        r#"
                         <html>
                             <head>
-                                <title>Test test</title>
+                                <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website
+                                {}
                             </body>
                         </html>
                     "#,
-                    "https://www.second.com",
+                        rand_words_seeded(100, 221)
+                    ),
+                    "https://www.first.com",
                 )
                 .unwrap(),
             )
             .expect("failed to insert webpage");
         index.commit().expect("failed to commit index");
+
         let searcher = LocalSearcher::from(index);
 
-        let query = SearchQuery {
-            query: "intitle:website".to_string(),
-            ..Default::default()
-        };
-        let result = searcher.search(&query).expect("Search failed");
-        assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+        for safe_search in [false, true] {
+            let result = searcher
+                .search(&SearchQuery {
+                    query: "a".repeat(101),
+                    safe_search,
+                    ..Default::default()
+                })
+                .expect("Search failed");
+
+            assert!(result.empty_query);
+            assert!(result.webpages.is_empty());
+        }
     }
 
     #[test]
-    fn url_query() {
+    fn case_sensitive_phrases() {
         let mut index = Index::temporary().expect("Unable to open index");
-
-        index
-            .insert(
-                Webpage::new(
-                    r#"
+        index.inverted_index.set_cased_body(true).unwrap();
+
+        for (i, (body, url)) in [
+            ("Programming in Rust is fun", "https://www.first.com"),
+            ("There is rust on my old car", "https://www.second.com"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
                         <html>
                             <head>
                                 <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website
+                                {body} {}
                             </body>
                         </html>
                     "#,
-                    "https://www.first.com/forum",
+                            rand_words_seeded(100, 210 + i as u64)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
                 )
-                .unwrap(),
-            )
-            .expect("failed to insert webpage");
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let search = |query: &str, case_sensitive_phrases: bool| {
+            let mut urls: Vec<_> = searcher
+                .search(&SearchQuery {
+                    query: query.to_string(),
+                    case_sensitive_phrases,
+                    ..Default::default()
+                })
+                .expect("Search failed")
+                .webpages
+                .into_iter()
+                .map(|w| w.url)
+                .collect();
+            urls.sort();
+            urls
+        };
+
+        assert_eq!(
+            search("\"Rust\"", false),
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.second.com/".to_string(),
+            ]
+        );
+        assert_eq!(
+            search("\"Rust\"", true),
+            vec!["https://www.first.com/".to_string()]
+        );
+        assert_eq!(
+            search("\"rust\"", true),
+            vec!["https://www.second.com/".to_string()]
+        );
+        assert_eq!(
+            search("\"in Rust\"", true),
+            vec!["https://www.first.com/".to_string()]
+        );
+    }
+
+    #[test]
+    fn case_sensitive_phrases_without_cased_body() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
         index
             .insert(
                 Webpage::new(
-                    r#"
+                    &format!(
+                        r#"
                         <html>
                             <head>
-                                <title>Test test</title>
+                                <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website
+                                There is rust on my old car {}
                             </body>
                         </html>
                     "#,
-                    "https://www.second.com",
+                        rand_words_seeded(100, 212)
+                    ),
+                    "https://www.first.com",
                 )
                 .unwrap(),
             )
             .expect("failed to insert webpage");
         index.commit().expect("failed to commit index");
-        let searcher = LocalSearcher::from(index);
-
-        let query = SearchQuery {
-            query: "test inurl:forum".to_string(),
-            ..Default::default()
-        };
-        let result = searcher.search(&query).expect("Search failed");
-        assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.first.com/forum");
-    }
-
-    #[test]
-    fn empty_query() {
-        let index = empty_index();
-        let ctx = index.local_search_ctx();
-
-        let query = Query::parse(
-            &ctx,
-            &SearchQuery {
-                query: "".to_string(),
-                ..Default::default()
-            },
-            &index,
-        )
-        .expect("failed to parse query");
 
-        assert!(query.is_empty())
-    }
+        assert!(!index.inverted_index.has_cased_body());
 
-    #[test]
-    fn query_term_only_special_char() {
-        let index = empty_index();
-        let ctx = index.local_search_ctx();
+        let searcher = LocalSearcher::from(index);
 
-        let query = Query::parse(
-            &ctx,
-            &SearchQuery {
-                query: "&".to_string(),
+        let result = searcher
+            .search(&SearchQuery {
+                query: "\"Rust\"".to_string(),
+                case_sensitive_phrases: true,
                 ..Default::default()
-            },
-            &index,
-        )
-        .expect("Failed to parse query");
+            })
+            .expect("Search failed");
 
-        assert!(!query.is_empty());
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
     }
 
     #[test]
-    fn site_query_split_domain() {
+    fn site_query() {
         let mut index = Index::temporary().expect("Unable to open index");
 
         index
             .insert(
                 Webpage::new(
-                    &format!(
-                        r#"
+                    r#"
                         <html>
                             <head>
                                 <title>Test website</title>
                             </head>
                             <body>
-                                This is a test website {}
+                                This is a test website
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
-                    ),
-                    "https://www.the-first.com",
+                    "https://www.first.com",
                 )
                 .unwrap(),
             )
@@ -674,19 +918,16 @@ mod tests {
         index
             .insert(
                 Webpage::new(
-                    &format!(
-                        r#"
+                    r#"
                         <html>
                             <head>
                                 <title>Test test</title>
                             </head>
                             <body>
-                                This test page does not contain the forbidden word {}
+                                This test page does not contain the forbidden word
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
-                    ),
                     "https://www.second.com",
                 )
                 .unwrap(),
@@ -700,23 +941,670 @@ mod tests {
             ..Default::default()
         };
         let result = searcher.search(&query).expect("Search failed");
-        assert_eq!(result.webpages.len(), 0);
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
 
         let query = SearchQuery {
-            query: "test site:the-first.com".to_string(),
+            query: "test site:www.first.com".to_string(),
             ..Default::default()
         };
         let result = searcher.search(&query).expect("Search failed");
         assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.the-first.com/");
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
 
         let query = SearchQuery {
-            query: "test site:www.the-first.com".to_string(),
+            query: "test -site:first.com".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+    }
+
+    #[test]
+    fn restrict_to_sites() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for site in ["first", "second", "third"] {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is the {site} test website
+                            </body>
+                        </html>
+                    "#
+                        ),
+                        &format!("https://www.{site}.com"),
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let search = |query: &str| {
+            let mut urls: Vec<_> = searcher
+                .search(&SearchQuery {
+                    query: query.to_string(),
+                    restrict_to_sites: vec!["first.com".to_string(), "www.second.com".to_string()],
+                    ..Default::default()
+                })
+                .expect("Search failed")
+                .webpages
+                .into_iter()
+                .map(|webpage| webpage.url)
+                .collect();
+            urls.sort();
+            urls
+        };
+
+        assert_eq!(
+            search("test"),
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.second.com/".to_string()
+            ]
+        );
+        assert_eq!(
+            search("test site:first.com"),
+            vec!["https://www.first.com/".to_string()]
+        );
+        assert!(search("test site:third.com").is_empty());
+        assert_eq!(
+            search("test -site:first.com"),
+            vec!["https://www.second.com/".to_string()]
+        );
+    }
+
+    #[test]
+    fn title_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.first.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test test</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.second.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "intitle:website".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/");
+        let query = SearchQuery {
+            query: "test -intitle:website".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+    }
+
+    #[test]
+    fn url_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.first.com/forum",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test test</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.second.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "test inurl:forum".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/forum");
+        let query = SearchQuery {
+            query: "test -inurl:forum".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.second.com/");
+    }
+
+    #[test]
+    fn url_prefix_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (i, url) in [
+            "https://www.first.com/blog/first-post",
+            "https://www.first.com/blog/second-post",
+            "https://www.first.com/about/blog",
+            "https://www.second.com/blogging",
+            "https://www.third.com/Blog/Third-Post",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website {}
+                            </body>
+                        </html>
+                    "#,
+                            rand_words_seeded(100, 100 + i as u64)
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "test inurl:/blog/".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        let mut urls: Vec<_> = result.webpages.into_iter().map(|w| w.url).collect();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://www.first.com/blog/first-post".to_string(),
+                "https://www.first.com/blog/second-post".to_string(),
+            ]
+        );
+
+        let query = SearchQuery {
+            query: "test inurl:/blog".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 3);
+
+        let query = SearchQuery {
+            query: "test -inurl:/blog/".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 3);
+
+        let query = SearchQuery {
+            query: "test inurl:/Blog/Third".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(
+            result.webpages[0].url,
+            "https://www.third.com/Blog/Third-Post"
+        );
+    }
+
+    #[test]
+    fn url_substring_query() {
+        let index_with_ngrams = |url_char_ngrams: bool| {
+            let mut index = Index::temporary().expect("Unable to open index");
+            index
+                .inverted_index
+                .set_url_char_ngrams(url_char_ngrams)
+                .unwrap();
+
+            for url in [
+                "https://www.first.com/recipes/easy-sourdoughbread",
+                "https://www.second.com/recipes/bread",
+            ] {
+                index
+                    .insert(
+                        Webpage::new(
+                            r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                            url,
+                        )
+                        .unwrap(),
+                    )
+                    .expect("failed to insert webpage");
+            }
+
+            index.commit().expect("failed to commit index");
+            LocalSearcher::from(index)
+        };
+
+        let query = SearchQuery {
+            query: "test inurl:doughbr".to_string(),
+            ..Default::default()
+        };
+
+        let searcher = index_with_ngrams(true);
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(
+            result.webpages[0].url,
+            "https://www.first.com/recipes/easy-sourdoughbread"
+        );
+
+        let whole_token_query = SearchQuery {
+            query: "test inurl:bread".to_string(),
+            ..Default::default()
+        };
+
+        let result = searcher.search(&whole_token_query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 2);
+
+        // without n-grams only whole url tokens match
+        let searcher = index_with_ngrams(false);
+        let result = searcher.search(&query).expect("Search failed");
+        assert!(result.webpages.is_empty());
+
+        let result = searcher.search(&whole_token_query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(
+            result.webpages[0].url,
+            "https://www.second.com/recipes/bread"
+        );
+    }
+
+    #[test]
+    fn default_fields() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.first.com/unicorn",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let mut searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "unicorn".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+
+        searcher.set_query_config(QueryConfig {
+            default_fields: Some(vec![
+                TextField::Title.name().to_string(),
+                TextField::AllBody.name().to_string(),
+            ]),
+            ..Default::default()
+        });
+
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 0);
+
+        let query = SearchQuery {
+            query: "website".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+
+        let query = SearchQuery {
+            query: "inurl:unicorn".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+
+        searcher.set_query_config(QueryConfig {
+            default_fields: Some(vec!["not_a_field".to_string()]),
+            ..Default::default()
+        });
+
+        let query = SearchQuery {
+            query: "website".to_string(),
+            ..Default::default()
+        };
+        assert!(searcher.search(&query).is_err());
+    }
+
+    #[test]
+    fn phrase_fallback_without_positions() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.first.com/unicorn",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let mut searcher = LocalSearcher::from(index);
+
+        // the site field has no positions
+        searcher.set_query_config(QueryConfig {
+            default_fields: Some(vec![TextField::SiteNoTokenizer.name().to_string()]),
+            ..Default::default()
+        });
+
+        let query = SearchQuery {
+            query: "\"www.first.com\"".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.first.com/unicorn");
+
+        let query = SearchQuery {
+            query: "\"www.second.com\"".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 0);
+
+        // the fallback is flagged so ranking can tell it apart from a positional match
+        let phrase_fallback = |searcher: &LocalSearcher<Index>, query: &str| {
+            let result = searcher
+                .search(&SearchQuery {
+                    query: query.to_string(),
+                    return_ranking_signals: true,
+                    ..Default::default()
+                })
+                .expect("Search failed");
+            assert_eq!(result.webpages.len(), 1);
+
+            result.webpages[0]
+                .ranking_signals
+                .as_ref()
+                .unwrap()
+                .get(&crate::ranking::Signal::PhraseFallback)
+                .map(|score| score.value)
+        };
+
+        assert_eq!(phrase_fallback(&searcher, "\"www.first.com\""), Some(1.0));
+
+        searcher.set_query_config(QueryConfig {
+            default_fields: Some(vec![
+                TextField::Title.name().to_string(),
+                TextField::SiteNoTokenizer.name().to_string(),
+            ]),
+            ..Default::default()
+        });
+
+        assert_eq!(phrase_fallback(&searcher, "\"test website\""), Some(0.0));
+        assert_eq!(phrase_fallback(&searcher, "test website"), None);
+    }
+
+    #[test]
+    fn phrase_fields() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    r#"
+                        <html>
+                            <head>
+                                <title>Magic unicorn</title>
+                            </head>
+                            <body>
+                                This is a test website
+                            </body>
+                        </html>
+                    "#,
+                    "https://www.first.com/",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let mut searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "\"magic unicorn\"".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+
+        searcher.set_query_config(QueryConfig {
+            phrase_fields: Some(vec![TextField::CleanBody.name().to_string()]),
+            ..Default::default()
+        });
+
+        // the phrase only occurs in the title which is excluded
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 0);
+
+        let query = SearchQuery {
+            query: "\"test website\"".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+
+        // simple terms still use the default fields
+        let query = SearchQuery {
+            query: "unicorn".to_string(),
             ..Default::default()
         };
         let result = searcher.search(&query).expect("Search failed");
         assert_eq!(result.webpages.len(), 1);
-        assert_eq!(result.webpages[0].url, "https://www.the-first.com/");
+    }
+
+    #[test]
+    fn empty_query() {
+        let index = empty_index();
+        let ctx = index.local_search_ctx();
+
+        let query = Query::parse(
+            &ctx,
+            &SearchQuery {
+                query: "".to_string(),
+                ..Default::default()
+            },
+            &index,
+        )
+        .expect("failed to parse query");
+
+        assert!(query.is_empty())
+    }
+
+    #[test]
+    fn query_term_only_special_char() {
+        let index = empty_index();
+        let ctx = index.local_search_ctx();
+
+        let query = Query::parse(
+            &ctx,
+            &SearchQuery {
+                query: "&".to_string(),
+                ..Default::default()
+            },
+            &index,
+        )
+        .expect("Failed to parse query");
+
+        assert!(!query.is_empty());
+    }
+
+    #[test]
+    fn site_query_split_domain() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                This is a test website {}
+                            </body>
+                        </html>
+                    "#,
+                        rand_words_seeded(1000, 1)
+                    ),
+                    "https://www.the-first.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index
+            .insert(
+                Webpage::new(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Test test</title>
+                            </head>
+                            <body>
+                                This test page does not contain the forbidden word {}
+                            </body>
+                        </html>
+                    "#,
+                        rand_words_seeded(1000, 2)
+                    ),
+                    "https://www.second.com",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::from(index);
+
+        let query = SearchQuery {
+            query: "test site:first.com".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 0);
+
+        let query = SearchQuery {
+            query: "test site:the-first.com".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.the-first.com/");
+
+        let query = SearchQuery {
+            query: "test site:www.the-first.com".to_string(),
+            ..Default::default()
+        };
+        let result = searcher.search(&query).expect("Search failed");
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(result.webpages[0].url, "https://www.the-first.com/");
     }
 
     #[test]
@@ -737,7 +1625,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 3)
                     ),
                     "https://www.first.com",
                 )
@@ -758,7 +1646,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 4)
                     ),
                     "https://www.second.com",
                 )
@@ -802,7 +1690,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 5)
                     ),
                     "https://www.first.com",
                 )
@@ -824,7 +1712,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 6)
                     ),
                     "https://www.second.com",
                 )
@@ -852,6 +1740,72 @@ mod tests {
         assert_eq!(result.webpages.len(), 2);
     }
 
+    #[test]
+    fn compound_boosts() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (i, (title, body)) in [
+            ("Newyork guide", "A guide to the city"),
+            ("City guide", "A guide to newyork"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            index
+                .insert(
+                    Webpage::new(
+                        &format!(
+                            r#"
+                        <html>
+                            <head>
+                                <title>{title}</title>
+                            </head>
+                            <body>
+                                {body} {}
+                            </body>
+                        </html>
+                    "#,
+                            rand_words_seeded(100, 300 + i as u64)
+                        ),
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                )
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+
+        let top_doc = |index: &Index| {
+            let query = Query::parse(
+                &index.inverted_index.local_search_ctx(),
+                &SearchQuery {
+                    query: "new york".to_string(),
+                    ..Default::default()
+                },
+                &index.inverted_index,
+            )
+            .expect("failed to parse query");
+
+            let top_docs = index
+                .inverted_index
+                .tv_searcher()
+                .search(&query, &tantivy::collector::TopDocs::with_limit(2))
+                .unwrap();
+
+            assert_eq!(top_docs.len(), 2);
+            top_docs[0].1.doc_id
+        };
+
+        // the compound in the title is boosted by default
+        assert_eq!(top_doc(&index), 0);
+
+        index.inverted_index.set_query_config(QueryConfig {
+            compound_boosts: HashMap::from([(TextField::AllBody.name().to_string(), 100.0)]),
+            ..Default::default()
+        });
+        assert_eq!(top_doc(&index), 1);
+    }
+
     #[test]
     fn safe_search() {
         let mut index = Index::temporary().expect("Unable to open index");
@@ -867,7 +1821,7 @@ mod tests {
                     </body>
                 </html>
             "#,
-                rand_words(1000)
+                rand_words_seeded(1000, 7)
             ),
             "https://www.sfw.com",
         )
@@ -890,7 +1844,7 @@ mod tests {
                     </body>
                 </html>
             "#,
-                rand_words(1000)
+                rand_words_seeded(1000, 8)
             ),
             "https://www.nsfw.com",
         )
@@ -943,7 +1897,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 9)
                     ),
                     "https://www.first.com/example",
                 )
@@ -964,7 +1918,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 10)
                     ),
                     "https://www.second.com",
                 )
@@ -985,7 +1939,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 11)
                     ),
                     "https://www.third.io",
                 )
@@ -294,7 +294,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 1)
                     ),
                     "https://www.a.com",
                 )
@@ -317,7 +317,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 2)
                     ),
                     "https://www.b.com",
                 )
@@ -509,7 +509,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 3)
                     ),
                     "https://www.a.com/this/is/a/pattern",
                 )
@@ -532,7 +532,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 4)
                     ),
                     "https://www.b.com/this/is/b/pattern",
                 )
@@ -556,7 +556,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 5)
                     ),
                     "https://www.c.com/this/is/c/pattern",
                 )
@@ -657,7 +657,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 6)
                     ),
                     "https://www.a.com/this/is/a/pattern",
                 )
@@ -681,7 +681,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 7)
                     ),
                     "https://www.b.com/this/is/b/pattern",
                 )
@@ -706,7 +706,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 8)
                     ),
                     "https://www.c.com/this/is/c/pattern",
                 )
@@ -778,7 +778,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 9)
                     ),
                     "https://www.a.com/",
                 )
@@ -816,7 +816,7 @@ mod tests {
                         </body>
                     </html>
                 "##,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 10)
                     ),
                     "https://www.b.com/",
                 ).unwrap(),
@@ -945,7 +945,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 11)
                     ),
                     "https://chat.stackoverflow.com",
                 )
@@ -1011,7 +1011,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 12)
                     ),
                     "https://a.com",
                 )
@@ -1035,7 +1035,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 13)
                     ),
                     "https://b.com/",
                 )
@@ -1097,7 +1097,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(1000)
+                        crate::rand_words_seeded(1000, 14)
                     ),
                     "https://example.com",
                 )
@@ -1253,7 +1253,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(1000)
+                        crate::rand_words_seeded(1000, 15)
                     ),
                     "https://example.com",
                 )
@@ -1321,7 +1321,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(1000)
+                        crate::rand_words_seeded(1000, 16)
                     ),
                     "https://example.com",
                 )
@@ -1376,7 +1376,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(1000)
+                        crate::rand_words_seeded(1000, 17)
                     ),
                     "https://example.com",
                 )
@@ -1400,7 +1400,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(1000)
+                        crate::rand_words_seeded(1000, 18)
                     ),
                     "https://example.com",
                 )
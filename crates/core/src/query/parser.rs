@@ -14,18 +14,23 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display, ops::Bound};
 use tantivy::{
-    query::{BooleanQuery, Occur, PhraseQuery, TermQuery},
+    query::{BooleanQuery, BoostQuery, EmptyQuery, Occur, PhraseQuery, RangeQuery, TermQuery},
     tokenizer::Tokenizer,
 };
 
 use crate::{
     bangs::BANG_PREFIXES,
-    floor_char_boundary,
     schema::{Field, TextField},
+    tokenizer::DEFAULT_MAX_TOKEN_LEN,
+    Error,
 };
 
+/// Score multiplier for phrases that are matched as a conjunction of their tokens
+/// in fields without positions, as the tokens might not be adjacent.
+pub const PHRASE_FALLBACK_BOOST: f32 = 0.5;
+
 #[derive(Debug, Clone)]
 pub struct TermCompound {
     pub terms: Vec<SimpleTerm>,
@@ -37,17 +42,44 @@ pub struct CompoundAwareTerm {
     pub adjacent_terms: Vec<TermCompound>,
 }
 impl CompoundAwareTerm {
+    /// `default_fields` are searched by simple terms and phrases while
+    /// field operators (like `intitle:`) can use any of `fields`.
+    /// Compound matches in a field are boosted by its entry in `compound_boosts`.
+    ///
+    /// Returns [`Error::NoQueryFields`] if the term has no fields to search,
+    /// as the query would otherwise silently match nothing.
     pub fn as_tantivy_query(
         &self,
         fields: &[tantivy::schema::Field],
-    ) -> (Occur, Box<dyn tantivy::query::Query + 'static>) {
+        default_fields: &[tantivy::schema::Field],
+        compound_boosts: &HashMap<tantivy::schema::Field, f32>,
+        max_token_len: usize,
+    ) -> Result<(Occur, Box<dyn tantivy::query::Query + 'static>), Error> {
+        let searched_fields = if self.term.uses_default_fields() {
+            default_fields
+        } else {
+            fields
+        };
+
+        if searched_fields.is_empty() {
+            return Err(Error::NoQueryFields(self.term.to_string()));
+        }
+
         if !self.adjacent_terms.is_empty() {
             if let Term::Simple(simple_term) = &self.term {
-                return simple_into_tantivy(simple_term, &self.adjacent_terms, fields);
+                return Ok(simple_into_tantivy(
+                    simple_term,
+                    &self.adjacent_terms,
+                    default_fields,
+                    compound_boosts,
+                    max_token_len,
+                ));
             }
         }
 
-        self.term.as_tantivy_query(fields)
+        Ok(self
+            .term
+            .as_tantivy_query(fields, default_fields, max_token_len))
     }
 }
 
@@ -80,12 +112,19 @@ pub enum Term {
     Title(String),
     Body(String),
     Url(String),
-    PossibleBang(String),
+    /// Path prefix (starting with `/`) that the url of the page must start with.
+    UrlPrefix(String),
+    /// The prefix is kept so the term is displayed the way it was written.
+    PossibleBang {
+        prefix: char,
+        bang: String,
+    },
 }
 
 impl Display for Term {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Term::Simple(term) if needs_escape(&term.0) => write!(f, "\\{}", term.0),
             Term::Simple(term) => write!(f, "{}", term.0),
             Term::Phrase(phrase) => write!(f, "\"{}\"", phrase),
             Term::Not(term) => write!(f, "-{}", term),
@@ -93,7 +132,8 @@ impl Display for Term {
             Term::Title(title) => write!(f, "intitle:{}", title),
             Term::Body(body) => write!(f, "inbody:{}", body),
             Term::Url(url) => write!(f, "inurl:{}", url),
-            Term::PossibleBang(bang) => write!(f, "{}{}", BANG_PREFIXES[0], bang),
+            Term::UrlPrefix(prefix) => write!(f, "inurl:{}", prefix),
+            Term::PossibleBang { prefix, bang } => write!(f, "{}{}", prefix, bang),
         }
     }
 }
@@ -102,18 +142,17 @@ fn simple_into_tantivy(
     term: &SimpleTerm,
     adjacent_terms: &[TermCompound],
     fields: &[tantivy::schema::Field],
+    compound_boosts: &HashMap<tantivy::schema::Field, f32>,
+    max_token_len: usize,
 ) -> (Occur, Box<dyn tantivy::query::Query + 'static>) {
-    let mut queries = Term::into_tantivy_simple(term, fields);
+    let mut queries = Term::into_tantivy_simple(term, fields, max_token_len);
 
     let fields = fields
         .iter()
         .filter(|field| {
-            matches!(
-                Field::get(field.field_id() as usize),
-                Some(Field::Text(TextField::AllBody))
-                    | Some(Field::Text(TextField::Title))
-                    | Some(Field::Text(TextField::Url))
-            )
+            Field::get(field.field_id() as usize)
+                .map(|field| field.is_compound_eligible())
+                .unwrap_or(false)
         })
         .copied()
         .collect::<Vec<_>>();
@@ -126,14 +165,48 @@ fn simple_into_tantivy(
             .collect::<String>();
 
         for field in &fields {
-            queries.push((Occur::Should, Term::tantivy_text_query(field, &combined)))
+            let query = Term::tantivy_text_query(field, &combined, max_token_len);
+
+            let query = match compound_boosts.get(field) {
+                Some(boost) => Box::new(BoostQuery::new(query, *boost)),
+                None => query,
+            };
+
+            queries.push((Occur::Should, query))
         }
     }
 
     (Occur::Must, Box::new(BooleanQuery::new(queries)))
 }
 
+/// Prefixes of the field operators, like `site:`.
+const OPERATORS: [&str; 4] = ["site:", "intitle:", "inbody:", "inurl:"];
+
 impl Term {
+    /// Whether the term is an operator without a value, like `site:`, `-` or `""`.
+    /// Such terms are parsed as simple terms, but a query consisting only of them
+    /// has nothing meaningful to search for.
+    pub fn is_empty_operator(&self) -> bool {
+        match self {
+            Term::Simple(term) => term.0 == "-" || OPERATORS.contains(&term.0.as_str()),
+            Term::Phrase(phrase) => phrase.trim().is_empty(),
+            Term::Not(subterm) => subterm.is_empty_operator(),
+            _ => false,
+        }
+    }
+
+    /// Whether the term is searched in the default fields instead of
+    /// the field of an operator.
+    fn uses_default_fields(&self) -> bool {
+        match self {
+            Term::Simple(_) | Term::Phrase(_) | Term::PossibleBang { .. } => true,
+            Term::Not(subterm) => subterm.uses_default_fields(),
+            Term::Site(_) | Term::Title(_) | Term::Body(_) | Term::Url(_) | Term::UrlPrefix(_) => {
+                false
+            }
+        }
+    }
+
     pub fn as_simple_text(&self) -> Option<&str> {
         match self {
             Term::Simple(term) => Some(&term.0),
@@ -142,102 +215,165 @@ impl Term {
         }
     }
 
+    /// The clauses that match `phrase` with positions in `default_fields`.
+    fn exact_phrase_clauses(
+        phrase: &str,
+        default_fields: &[tantivy::schema::Field],
+        max_token_len: usize,
+    ) -> Vec<(Occur, Box<dyn tantivy::query::Query + 'static>)> {
+        let mut phrases = Vec::with_capacity(default_fields.len());
+
+        for (field, tv_field) in default_fields
+            .iter()
+            .filter_map(|tv_field| {
+                Field::get(tv_field.field_id() as usize).map(|mapped| (mapped, *tv_field))
+            })
+            .filter(|(field, _)| field.is_searchable())
+            .filter(|(field, _)| field.has_pos())
+        {
+            let mut processed_terms = Term::process_tantivy_term(phrase, tv_field, max_token_len);
+
+            if processed_terms.is_empty() {
+                continue;
+            }
+
+            if processed_terms.len() == 1 {
+                let options = field.as_text().unwrap().index_option();
+
+                phrases.push((
+                    Occur::Should,
+                    Box::new(TermQuery::new(processed_terms.pop().unwrap(), options))
+                        as Box<dyn tantivy::query::Query>,
+                ));
+            } else {
+                phrases.push((
+                    Occur::Should,
+                    Box::new(PhraseQuery::new(processed_terms)) as Box<dyn tantivy::query::Query>,
+                ));
+            }
+        }
+
+        phrases
+    }
+
+    /// Query that only matches the phrase where its tokens are adjacent, i.e. without the
+    /// [`PHRASE_FALLBACK_BOOST`] fallback for fields without positions. Documents that match
+    /// the phrase but not this query only match through the fallback. `None` if the term is
+    /// not a phrase or if none of `default_fields` has a fallback for it.
+    pub fn as_exact_phrase_query(
+        &self,
+        default_fields: &[tantivy::schema::Field],
+        max_token_len: usize,
+    ) -> Option<BooleanQuery> {
+        let phrase = match self {
+            Term::Phrase(phrase) => phrase,
+            _ => return None,
+        };
+
+        let has_fallback = default_fields.iter().any(|tv_field| {
+            Field::get(tv_field.field_id() as usize)
+                .map(|field| field.has_phrase_fallback())
+                .unwrap_or(false)
+                && !Term::process_tantivy_term(phrase, *tv_field, max_token_len).is_empty()
+        });
+
+        if !has_fallback {
+            return None;
+        }
+
+        Some(BooleanQuery::new(Term::exact_phrase_clauses(
+            phrase,
+            default_fields,
+            max_token_len,
+        )))
+    }
+
     fn as_tantivy_query(
         &self,
         fields: &[tantivy::schema::Field],
+        default_fields: &[tantivy::schema::Field],
+        max_token_len: usize,
     ) -> (Occur, Box<dyn tantivy::query::Query + 'static>) {
         match self {
-            Term::Simple(term) => simple_into_tantivy(term, &[], fields),
+            Term::Simple(term) => {
+                simple_into_tantivy(term, &[], default_fields, &HashMap::new(), max_token_len)
+            }
             Term::Phrase(phrase) => {
-                let mut phrases = Vec::with_capacity(fields.len());
-
-                for (field, tv_field) in fields
-                    .iter()
-                    .filter_map(|tv_field| {
-                        Field::get(tv_field.field_id() as usize).map(|mapped| (mapped, *tv_field))
-                    })
-                    .filter(|(field, _)| field.is_searchable())
-                    .filter(|(field, _)| field.has_pos())
-                {
-                    let mut processed_terms = Term::process_tantivy_term(phrase, tv_field);
+                let mut phrases = Term::exact_phrase_clauses(phrase, default_fields, max_token_len);
 
-                    if processed_terms.is_empty() {
+                for tv_field in default_fields.iter().filter(|tv_field| {
+                    Field::get(tv_field.field_id() as usize)
+                        .map(|field| field.has_phrase_fallback())
+                        .unwrap_or(false)
+                }) {
+                    if Term::process_tantivy_term(phrase, *tv_field, max_token_len).is_empty() {
                         continue;
                     }
 
-                    if processed_terms.len() == 1 {
-                        let options = field.as_text().unwrap().index_option();
-
-                        phrases.push((
-                            Occur::Should,
-                            Box::new(TermQuery::new(processed_terms.pop().unwrap(), options))
-                                as Box<dyn tantivy::query::Query>,
-                        ));
-                    } else {
-                        phrases.push((
-                            Occur::Should,
-                            Box::new(PhraseQuery::new(processed_terms))
-                                as Box<dyn tantivy::query::Query>,
-                        ));
-                    }
+                    phrases.push((
+                        Occur::Should,
+                        Box::new(BoostQuery::new(
+                            Term::tantivy_text_query(tv_field, phrase, max_token_len),
+                            PHRASE_FALLBACK_BOOST,
+                        )),
+                    ));
                 }
 
                 (Occur::Must, Box::new(BooleanQuery::new(phrases)))
             }
             Term::Not(subterm) => (
                 Occur::MustNot,
-                Box::new(BooleanQuery::new(vec![subterm.as_tantivy_query(fields)])),
+                Box::new(BooleanQuery::new(vec![subterm.as_tantivy_query(
+                    fields,
+                    default_fields,
+                    max_token_len,
+                )])),
             ),
             Term::Site(site) => (
                 Occur::Must,
-                Box::new(BooleanQuery::new(Term::into_tantivy_site(site, fields))),
+                Box::new(BooleanQuery::new(Term::into_tantivy_site(
+                    site,
+                    fields,
+                    max_token_len,
+                ))),
             ),
-            Term::Title(title) => {
-                let field = fields
-                    .iter()
-                    .find(|field| {
-                        matches!(
-                            Field::get(field.field_id() as usize),
-                            Some(Field::Text(TextField::Title))
-                        )
-                    })
-                    .unwrap();
-
-                (Occur::Must, Term::tantivy_text_query(field, title))
-            }
-            Term::Body(body) => {
-                let field = fields
-                    .iter()
-                    .find(|field| {
-                        matches!(
-                            Field::get(field.field_id() as usize),
-                            Some(Field::Text(TextField::AllBody))
-                        )
-                    })
-                    .unwrap();
-
-                (Occur::Must, Term::tantivy_text_query(field, body))
-            }
-            Term::Url(url) => {
-                let field = fields
-                    .iter()
-                    .find(|field| {
-                        matches!(
-                            Field::get(field.field_id() as usize),
-                            Some(Field::Text(TextField::Url))
-                        )
-                    })
-                    .unwrap();
-
-                (Occur::Must, Term::tantivy_text_query(field, url))
+            Term::Title(title) => (
+                Occur::Must,
+                Term::tantivy_field_query(fields, TextField::Title, title, max_token_len),
+            ),
+            Term::Body(body) => (
+                Occur::Must,
+                Term::tantivy_field_query(fields, TextField::AllBody, body, max_token_len),
+            ),
+            Term::Url(url) => (
+                Occur::Must,
+                Box::new(BooleanQuery::new(vec![
+                    (
+                        Occur::Should,
+                        Term::tantivy_field_query(fields, TextField::Url, url, max_token_len),
+                    ),
+                    (
+                        Occur::Should,
+                        Term::tantivy_url_substring_query(fields, url, max_token_len),
+                    ),
+                ])),
+            ),
+            Term::UrlPrefix(prefix) => {
+                (Occur::Must, Term::tantivy_url_prefix_query(fields, prefix))
             }
-            Term::PossibleBang(text) => {
+            Term::PossibleBang { prefix, bang } => {
                 let mut term = String::new();
 
-                term.push(BANG_PREFIXES[0]);
-                term.push_str(text);
+                term.push(*prefix);
+                term.push_str(bang);
 
-                simple_into_tantivy(&term.into(), &[], fields)
+                simple_into_tantivy(
+                    &term.into(),
+                    &[],
+                    default_fields,
+                    &HashMap::new(),
+                    max_token_len,
+                )
             }
         }
     }
@@ -245,6 +381,7 @@ impl Term {
     fn into_tantivy_simple(
         term: &SimpleTerm,
         fields: &[tantivy::schema::Field],
+        max_token_len: usize,
     ) -> Vec<(Occur, Box<dyn tantivy::query::Query + 'static>)> {
         fields
             .iter()
@@ -253,13 +390,19 @@ impl Term {
                     .filter(|field| field.is_searchable())
                     .map(|_| tv_field)
             })
-            .map(|field| (Occur::Should, Term::tantivy_text_query(field, &term.0)))
+            .map(|field| {
+                (
+                    Occur::Should,
+                    Term::tantivy_text_query(field, &term.0, max_token_len),
+                )
+            })
             .collect()
     }
 
     fn into_tantivy_site(
         term: &str,
         fields: &[tantivy::schema::Field],
+        max_token_len: usize,
     ) -> Vec<(Occur, Box<dyn tantivy::query::Query + 'static>)> {
         fields
             .iter()
@@ -270,7 +413,7 @@ impl Term {
                 )
             })
             .map(|field| {
-                let processed_terms = Term::process_tantivy_term(term, *field);
+                let processed_terms = Term::process_tantivy_term(term, *field, max_token_len);
 
                 if processed_terms.len() == 1 {
                     let term = processed_terms.first().unwrap().clone();
@@ -292,11 +435,146 @@ impl Term {
             .collect()
     }
 
+    /// Query that matches documents from any of `sites`. Sites that are
+    /// not valid hosts are ignored, so if none are valid nothing matches.
+    pub fn into_tantivy_any_site(
+        sites: &[String],
+        fields: &[tantivy::schema::Field],
+        max_token_len: usize,
+    ) -> Box<dyn tantivy::query::Query + 'static> {
+        let queries = sites
+            .iter()
+            .filter_map(|site| normalize_site(site))
+            .map(|site| {
+                (
+                    Occur::Should,
+                    Box::new(BooleanQuery::new(Term::into_tantivy_site(
+                        &site,
+                        fields,
+                        max_token_len,
+                    ))) as Box<dyn tantivy::query::Query>,
+                )
+            })
+            .collect();
+
+        Box::new(BooleanQuery::new(queries))
+    }
+
+    /// Query `text_field` if it is part of `fields`. Queries against a field
+    /// that is not available match nothing instead of panicking.
+    fn tantivy_field_query(
+        fields: &[tantivy::schema::Field],
+        text_field: TextField,
+        term: &str,
+        max_token_len: usize,
+    ) -> Box<dyn tantivy::query::Query + 'static> {
+        let field = fields
+            .iter()
+            .find(|field| Field::get(field.field_id() as usize) == Some(Field::Text(text_field)));
+
+        match field {
+            Some(field) => Term::tantivy_text_query(field, term, max_token_len),
+            None => Box::new(EmptyQuery),
+        }
+    }
+
+    /// Match `phrase` with its case preserved against the clean body. Only
+    /// phrases can be matched case sensitively, so other terms return `None`.
+    /// `None` is also returned if the cased clean body is not part of `fields`.
+    /// Only the body is cased, so the phrase never matches the title.
+    pub fn as_case_sensitive_tantivy_query(
+        &self,
+        fields: &[tantivy::schema::Field],
+        max_token_len: usize,
+    ) -> Option<(Occur, Box<dyn tantivy::query::Query + 'static>)> {
+        let (occur, phrase) = match self {
+            Term::Phrase(phrase) => (Occur::Must, phrase),
+            Term::Not(subterm) => match &**subterm {
+                Term::Phrase(phrase) => (Occur::MustNot, phrase),
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let field = fields.iter().find(|field| {
+            Field::get(field.field_id() as usize) == Some(Field::Text(TextField::CleanBodyCased))
+        })?;
+
+        let mut processed_terms = Term::process_tantivy_term(phrase, *field, max_token_len);
+
+        let query: Box<dyn tantivy::query::Query + 'static> = match processed_terms.len() {
+            0 => Box::new(EmptyQuery),
+            1 => Box::new(TermQuery::new(
+                processed_terms.pop().unwrap(),
+                TextField::CleanBodyCased.index_option(),
+            )),
+            _ => Box::new(PhraseQuery::new(processed_terms)),
+        };
+
+        Some((occur, query))
+    }
+
+    /// Match pages with `substring` anywhere in their url using the character n-grams
+    /// of the url. Substrings shorter than an n-gram and indexes built without
+    /// url n-grams match nothing.
+    fn tantivy_url_substring_query(
+        fields: &[tantivy::schema::Field],
+        substring: &str,
+        max_token_len: usize,
+    ) -> Box<dyn tantivy::query::Query + 'static> {
+        let field = fields.iter().find(|field| {
+            Field::get(field.field_id() as usize) == Some(Field::Text(TextField::UrlCharNgrams))
+        });
+
+        let mut processed_terms = match field {
+            Some(field) => Term::process_tantivy_term(substring, *field, max_token_len),
+            None => return Box::new(EmptyQuery),
+        };
+
+        match processed_terms.len() {
+            0 => Box::new(EmptyQuery),
+            1 => Box::new(TermQuery::new(
+                processed_terms.pop().unwrap(),
+                TextField::UrlCharNgrams.index_option(),
+            )),
+            _ => Box::new(PhraseQuery::new(processed_terms)),
+        }
+    }
+
+    /// Match pages whose url path starts with `prefix`. The paths are indexed
+    /// as single terms, so the prefix is a range in the term dictionary.
+    fn tantivy_url_prefix_query(
+        fields: &[tantivy::schema::Field],
+        prefix: &str,
+    ) -> Box<dyn tantivy::query::Query + 'static> {
+        let field = Field::Text(TextField::UrlPath);
+
+        if !fields
+            .iter()
+            .any(|f| Field::get(f.field_id() as usize) == Some(field))
+        {
+            return Box::new(EmptyQuery);
+        }
+
+        let upper = prefix_successor(prefix);
+        let upper = match &upper {
+            Some(upper) => Bound::Excluded(upper.as_str()),
+            None => Bound::Unbounded,
+        };
+
+        Box::new(RangeQuery::new_str_bounds(
+            field.name().to_string(),
+            Bound::Included(prefix),
+            upper,
+        ))
+    }
+
     fn tantivy_text_query(
         field: &tantivy::schema::Field,
         term: &str,
+        max_token_len: usize,
     ) -> Box<dyn tantivy::query::Query + 'static> {
-        let mut processed_terms = Term::process_tantivy_term(term, *field);
+        let mut processed_terms = Term::process_tantivy_term(term, *field, max_token_len);
 
         let option = Field::get(field.field_id() as usize)
             .unwrap()
@@ -328,74 +606,149 @@ impl Term {
     fn process_tantivy_term(
         term: &str,
         tantivy_field: tantivy::schema::Field,
+        max_token_len: usize,
     ) -> Vec<tantivy::Term> {
         match Field::get(tantivy_field.field_id() as usize) {
             Some(Field::Fast(_)) => vec![tantivy::Term::from_field_text(tantivy_field, term)],
             Some(Field::Text(text_field)) => {
-                let mut terms: Vec<tantivy::Term> = Vec::new();
-                let mut tokenizer = text_field.query_tokenizer();
-                let mut token_stream = tokenizer.token_stream(term);
-                token_stream.process(&mut |token| {
-                    let term = tantivy::Term::from_field_text(tantivy_field, &token.text);
-                    terms.push(term);
-                });
-
-                terms
+                analyze_with_max_token_len(*text_field, term, max_token_len)
+                    .into_iter()
+                    .map(|token| tantivy::Term::from_field_text(tantivy_field, &token))
+                    .collect()
             }
             None => vec![],
         }
     }
 }
 
-fn parse_term(term: &str) -> Box<Term> {
+/// Normalize the value of a `site:` operator. Full urls like `https://example.com/page`
+/// are reduced to their host, while partial sites like `.com` or `example.com/blog` are kept.
+/// Returns `None` if the value is not a valid site.
+fn normalize_site(site: &str) -> Option<String> {
+    if site.contains("://") {
+        let url = url::Url::parse(site).ok()?;
+        let host = url.host_str()?;
+
+        return normalize_site(host);
+    }
+
+    let host = site.split('/').next().unwrap_or_default();
+
+    let is_valid = host.chars().any(|c| c.is_alphanumeric())
+        && host
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '.');
+
+    if is_valid {
+        Some(site.to_lowercase())
+    } else {
+        None
+    }
+}
+
+/// Whether `text` must be escaped to be parsed as a simple term with the same text.
+fn needs_escape(text: &str) -> bool {
+    text.starts_with('"') || !matches!(parse_term(text), Term::Simple(term) if term.0 == text)
+}
+
+fn parse_term(term: &str) -> Term {
     // TODO: re-write this entire function once if-let chains become stable
-    if let Some(not_term) = term.strip_prefix('-') {
+    if let Some(escaped) = term.strip_prefix('\\') {
+        // a lone backslash is kept as is
+        if escaped.is_empty() {
+            Term::Simple(term.to_string().into())
+        } else {
+            Term::Simple(escaped.to_string().into())
+        }
+    } else if let Some(not_term) = term.strip_prefix('-') {
         if !not_term.is_empty() && !not_term.starts_with('-') {
-            Box::new(Term::Not(parse_term(not_term)))
+            Term::Not(Box::new(parse_term(not_term)))
         } else {
-            Box::new(Term::Simple(term.to_string().into()))
+            Term::Simple(term.to_string().into())
         }
     } else if let Some(site) = term.strip_prefix("site:") {
-        if !site.is_empty() {
-            Box::new(Term::Site(site.to_string()))
+        if let Some(site) = normalize_site(site) {
+            Term::Site(site)
         } else {
-            Box::new(Term::Simple(term.to_string().into()))
+            Term::Simple(term.to_string().into())
         }
     } else if let Some(title) = term.strip_prefix("intitle:") {
         if !title.is_empty() {
-            Box::new(Term::Title(title.to_string()))
+            Term::Title(title.to_string())
         } else {
-            Box::new(Term::Simple(term.to_string().into()))
+            Term::Simple(term.to_string().into())
         }
     } else if let Some(body) = term.strip_prefix("inbody:") {
         if !body.is_empty() {
-            Box::new(Term::Body(body.to_string()))
+            Term::Body(body.to_string())
         } else {
-            Box::new(Term::Simple(term.to_string().into()))
+            Term::Simple(term.to_string().into())
         }
     } else if let Some(url) = term.strip_prefix("inurl:") {
-        if !url.is_empty() {
-            Box::new(Term::Url(url.to_string()))
+        if url.starts_with('/') {
+            Term::UrlPrefix(url.to_string())
+        } else if !url.is_empty() {
+            Term::Url(url.to_string())
         } else {
-            Box::new(Term::Simple(term.to_string().into()))
+            Term::Simple(term.to_string().into())
         }
     } else {
         for bang_prefix in BANG_PREFIXES {
             if let Some(bang) = term.strip_prefix(bang_prefix) {
-                return Box::new(Term::PossibleBang(bang.to_string()));
+                return Term::PossibleBang {
+                    prefix: bang_prefix,
+                    bang: bang.to_string(),
+                };
             }
         }
 
-        Box::new(Term::Simple(term.to_string().into()))
+        Term::Simple(term.to_string().into())
     }
 }
 
+/// Tokenize `text` the same way query terms are tokenized before being
+/// matched against `field`. Useful to debug why a query doesn't match a document.
+pub fn analyze(field: TextField, text: &str) -> Vec<String> {
+    analyze_with_max_token_len(field, text, DEFAULT_MAX_TOKEN_LEN)
+}
+
+/// Like [`analyze`], but tokens longer than `max_token_len` are dropped as by an
+/// index built with that maximum token length.
+pub fn analyze_with_max_token_len(
+    field: TextField,
+    text: &str,
+    max_token_len: usize,
+) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut tokenizer = field.query_tokenizer().with_max_token_len(max_token_len);
+    let mut token_stream = tokenizer.token_stream(text);
+
+    token_stream.process(&mut |token| {
+        tokens.push(token.text.clone());
+    });
+
+    tokens
+}
+
+#[deprecated(note = "use `parse_terms` instead, which doesn't box every term")]
 #[allow(clippy::vec_box)]
 pub fn parse(query: &str) -> Vec<Box<Term>> {
-    let query = query.to_lowercase().replace(['“', '”'], "\"");
+    parse_terms(query).into_iter().map(Box::new).collect()
+}
+
+enum RawTerm<'a> {
+    Phrase(&'a str),
+    /// A phrase immediately preceded by `-`.
+    NotPhrase(&'a str),
+    Term(&'a str),
+}
 
+/// Split `query` into phrases and whitespace separated terms.
+fn split_terms(query: &str) -> Vec<RawTerm<'_>> {
     let mut res = Vec::new();
 
+    // always a char boundary in `query`. Whitespace can be more than one byte,
+    // so the next term starts after the entire char and not just the next byte.
     let mut cur_term_begin = 0;
 
     for (offset, c) in query.char_indices() {
@@ -403,59 +756,349 @@ pub fn parse(query: &str) -> Vec<Box<Term>> {
             continue;
         }
 
-        cur_term_begin = floor_char_boundary(&query, cur_term_begin);
+        let negated = query[cur_term_begin..].starts_with("-\"");
 
-        if query[cur_term_begin..].starts_with('"') {
-            if let Some(offset) = query[cur_term_begin + 1..].find('"') {
-                let offset = offset + cur_term_begin + 1;
-                res.push(Box::new(Term::Phrase(
-                    query[cur_term_begin + 1..offset].to_string(),
-                )));
+        if negated || query[cur_term_begin..].starts_with('"') {
+            let phrase_begin = if negated {
+                cur_term_begin + "-\"".len()
+            } else {
+                cur_term_begin + '"'.len_utf8()
+            };
 
-                cur_term_begin = offset + 1;
+            if let Some(len) = query[phrase_begin..].find('"') {
+                let phrase_end = phrase_begin + len;
+                let phrase = &query[phrase_begin..phrase_end];
+
+                if negated {
+                    res.push(RawTerm::NotPhrase(phrase));
+                } else {
+                    res.push(RawTerm::Phrase(phrase));
+                }
+
+                cur_term_begin = phrase_end + '"'.len_utf8();
                 continue;
             }
         }
         if c.is_whitespace() {
-            if offset - cur_term_begin == 0 {
-                cur_term_begin = offset + 1;
-                continue;
+            if offset > cur_term_begin {
+                res.push(RawTerm::Term(&query[cur_term_begin..offset]));
             }
 
-            res.push(parse_term(&query[cur_term_begin..offset]));
-            cur_term_begin = offset + 1;
+            cur_term_begin = offset + c.len_utf8();
         }
     }
 
     if cur_term_begin < query.len() {
-        res.push(parse_term(
-            &query[floor_char_boundary(&query, cur_term_begin)..query.len()],
-        ));
+        res.push(RawTerm::Term(&query[cur_term_begin..]));
     }
 
     res
 }
 
+/// Bidirectional formatting characters are invisible, so users cannot tell
+/// that they are part of a term.
+fn is_bidi_control(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}' | '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}'
+    )
+}
+
+/// Replace typographic quotes and remove bidi and other control characters
+/// that are not whitespace.
+fn normalize(query: &str) -> String {
+    query
+        .chars()
+        .filter(|c| !is_bidi_control(*c) && (c.is_whitespace() || !c.is_control()))
+        .map(|c| match c {
+            '“' | '”' => '"',
+            c => c,
+        })
+        .collect()
+}
+
+/// The smallest string that is larger than every string starting with `prefix`.
+fn prefix_successor(prefix: &str) -> Option<String> {
+    let mut chars: Vec<char> = prefix.chars().collect();
+
+    while let Some(last) = chars.pop() {
+        // skips the surrogate range
+        let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
+
+        if let Some(next) = next {
+            chars.push(next);
+            return Some(chars.into_iter().collect());
+        }
+    }
+
+    None
+}
+
+/// Parse `query` into lowercased terms. Url path prefixes keep
+/// their case, as paths are case sensitive.
+pub fn parse_terms(query: &str) -> Vec<Term> {
+    let lowercased = normalize(&query.to_lowercase());
+
+    let mut terms: Vec<_> = split_terms(&lowercased)
+        .into_iter()
+        .map(|term| match term {
+            RawTerm::Phrase(phrase) => Term::Phrase(phrase.to_string()),
+            RawTerm::NotPhrase(phrase) => Term::Not(Box::new(Term::Phrase(phrase.to_string()))),
+            RawTerm::Term(term) => parse_term(term),
+        })
+        .collect();
+
+    let query = normalize(query);
+    let cased = split_terms(&query);
+
+    if cased.len() == terms.len() {
+        for (term, cased) in terms.iter_mut().zip(cased) {
+            if let RawTerm::Term(cased) = cased {
+                restore_url_prefix_case(term, cased);
+            }
+        }
+    }
+
+    terms
+}
+
+/// Replace the prefix of a (possibly negated) `inurl:` term with
+/// the corresponding suffix of the raw term from the original query.
+fn restore_url_prefix_case(term: &mut Term, cased: &str) {
+    match term {
+        Term::UrlPrefix(prefix) => {
+            let cased = cased
+                .len()
+                .checked_sub(prefix.len())
+                .and_then(|start| cased.get(start..));
+
+            if let Some(cased) = cased {
+                if cased.to_lowercase() == *prefix {
+                    *prefix = cased.to_string();
+                }
+            }
+        }
+        Term::Not(subterm) => restore_url_prefix_case(subterm, cased),
+        _ => {}
+    }
+}
+
+/// Same as [`parse_terms`] except that phrases keep the case from `query`.
+pub fn parse_terms_case_sensitive_phrases(query: &str) -> Vec<Term> {
+    let mut terms = parse_terms(query);
+
+    let query = normalize(query);
+    let cased = split_terms(&query);
+
+    // lowercasing never adds or removes whitespace or quotes,
+    // so both queries are split into the same terms
+    if cased.len() == terms.len() {
+        for (term, cased) in terms.iter_mut().zip(cased) {
+            match (&mut *term, cased) {
+                (Term::Phrase(phrase), RawTerm::Phrase(cased)) => *phrase = cased.to_string(),
+                (Term::Not(subterm), RawTerm::NotPhrase(cased)) => {
+                    if let Term::Phrase(phrase) = &mut **subterm {
+                        *phrase = cased.to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    terms
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use proptest::prelude::*;
 
+    #[test]
+    fn field_operators_with_missing_field() {
+        let schema = crate::schema::create_schema();
+        let fields: Vec<_> = schema
+            .fields()
+            .map(|(field, _)| field)
+            .filter(|field| {
+                Field::get(field.field_id() as usize) != Some(Field::Text(TextField::Title))
+            })
+            .collect();
+
+        let (occur, query) = Term::Title("test".to_string()).as_tantivy_query(
+            &fields,
+            &fields,
+            DEFAULT_MAX_TOKEN_LEN,
+        );
+        assert_eq!(occur, Occur::Must);
+        assert!(query.is::<EmptyQuery>());
+
+        let (_, query) = Term::Body("test".to_string()).as_tantivy_query(
+            &fields,
+            &fields,
+            DEFAULT_MAX_TOKEN_LEN,
+        );
+        assert!(!query.is::<EmptyQuery>());
+
+        for term in parse_terms("intitle:test inbody:test inurl:test") {
+            let _ = term.as_tantivy_query(&[], &[], DEFAULT_MAX_TOKEN_LEN);
+        }
+    }
+
+    #[test]
+    fn compounds_query_eligible_fields() {
+        let schema = crate::schema::create_schema();
+        let fields: Vec<_> = schema.fields().map(|(field, _)| field).collect();
+
+        let term = CompoundAwareTerm {
+            term: Term::Simple("new".to_string().into()),
+            adjacent_terms: vec![TermCompound {
+                terms: vec!["new".to_string().into(), "york".to_string().into()],
+            }],
+        };
+
+        let (_, query) = term
+            .as_tantivy_query(&fields, &fields, &HashMap::new(), DEFAULT_MAX_TOKEN_LEN)
+            .unwrap();
+
+        let mut compound_fields = Vec::new();
+        query.query_terms(&mut |term, _| {
+            if term.value().as_str() == Some("newyork") {
+                compound_fields.push(term.field());
+            }
+        });
+        compound_fields.sort();
+        compound_fields.dedup();
+
+        let expected: Vec<_> = fields
+            .iter()
+            .filter(|field| {
+                Field::get(field.field_id() as usize)
+                    .unwrap()
+                    .is_compound_eligible()
+            })
+            .copied()
+            .collect();
+
+        assert!(!expected.is_empty());
+        assert_eq!(compound_fields, expected);
+    }
+
+    #[test]
+    fn empty_fields() {
+        let schema = crate::schema::create_schema();
+        let fields: Vec<_> = schema.fields().map(|(field, _)| field).collect();
+
+        let compound = |term: Term| CompoundAwareTerm {
+            term,
+            adjacent_terms: Vec::new(),
+        };
+
+        for term in parse_terms("test \"test phrase\" -test site:example.com intitle:test") {
+            assert!(matches!(
+                compound(term).as_tantivy_query(&[], &[], &HashMap::new(), DEFAULT_MAX_TOKEN_LEN),
+                Err(Error::NoQueryFields(_))
+            ));
+        }
+
+        assert!(matches!(
+            compound(Term::Simple("test".to_string().into())).as_tantivy_query(
+                &fields,
+                &[],
+                &HashMap::new(),
+                DEFAULT_MAX_TOKEN_LEN
+            ),
+            Err(Error::NoQueryFields(text)) if text == "test"
+        ));
+        assert!(compound(Term::Simple("test".to_string().into()))
+            .as_tantivy_query(&[], &fields, &HashMap::new(), DEFAULT_MAX_TOKEN_LEN)
+            .is_ok());
+
+        assert!(matches!(
+            compound(Term::Site("example.com".to_string())).as_tantivy_query(
+                &[],
+                &fields,
+                &HashMap::new(),
+                DEFAULT_MAX_TOKEN_LEN
+            ),
+            Err(Error::NoQueryFields(_))
+        ));
+        assert!(compound(Term::Site("example.com".to_string()))
+            .as_tantivy_query(&fields, &[], &HashMap::new(), DEFAULT_MAX_TOKEN_LEN)
+            .is_ok());
+    }
+
+    #[test]
+    fn phrase_fallback_without_positions() {
+        let schema = crate::schema::create_schema();
+        let site = schema
+            .get_field(Field::Text(TextField::SiteNoTokenizer).name())
+            .unwrap();
+        let title = schema
+            .get_field(Field::Text(TextField::Title).name())
+            .unwrap();
+
+        let (_, query) = Term::Phrase("www.example.com".to_string()).as_tantivy_query(
+            &[],
+            &[site],
+            DEFAULT_MAX_TOKEN_LEN,
+        );
+        let query = query.downcast::<BooleanQuery>().ok().unwrap();
+        assert_eq!(query.clauses().len(), 1);
+        assert!(query.clauses()[0].1.is::<BoostQuery>());
+
+        let (_, query) = Term::Phrase("example website".to_string()).as_tantivy_query(
+            &[],
+            &[title],
+            DEFAULT_MAX_TOKEN_LEN,
+        );
+        let query = query.downcast::<BooleanQuery>().ok().unwrap();
+        assert_eq!(query.clauses().len(), 1);
+        assert!(query.clauses()[0].1.is::<PhraseQuery>());
+    }
+
+    #[test]
+    fn analyze_fields() {
+        let text = "The runners were running through all the cities in the country";
+
+        let tokens = analyze(TextField::Title, text);
+        assert_eq!(tokens.len(), 11);
+        assert_eq!(tokens[0], "the");
+        assert!(tokens.contains(&"running".to_string()));
+        assert!(tokens.contains(&"cities".to_string()));
+
+        let stemmed = analyze(TextField::StemmedTitle, text);
+        assert_eq!(stemmed.len(), 11);
+        assert!(stemmed.contains(&"run".to_string()));
+        assert!(stemmed.contains(&"citi".to_string()));
+        assert!(!stemmed.contains(&"running".to_string()));
+
+        assert_eq!(
+            analyze(TextField::SiteNoTokenizer, "www.Example.com"),
+            vec!["www.Example.com".to_string()]
+        );
+
+        assert_eq!(
+            analyze_with_max_token_len(TextField::Title, "short loooooooong", 5),
+            vec!["short".to_string()]
+        );
+    }
+
     #[test]
     fn parse_not() {
         assert_eq!(
-            parse("this -that"),
+            parse_terms("this -that"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Not(Box::new(Term::Simple("that".to_string().into()))))
+                Term::Simple("this".to_string().into()),
+                Term::Not(Box::new(Term::Simple("that".to_string().into())))
             ]
         );
 
         assert_eq!(
-            parse("this -"),
+            parse_terms("this -"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Simple("-".to_string().into()))
+                Term::Simple("this".to_string().into()),
+                Term::Simple("-".to_string().into())
             ]
         );
     }
@@ -463,32 +1106,139 @@ mod tests {
     #[test]
     fn double_not() {
         assert_eq!(
-            parse("this --that"),
+            parse_terms("this --that"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Simple("--that".to_string().into()))
+                Term::Simple("this".to_string().into()),
+                Term::Simple("--that".to_string().into())
             ]
         );
     }
 
+    #[test]
+    fn negated_field_operators() {
+        assert_eq!(
+            parse_terms("this -site:spam.com"),
+            vec![
+                Term::Simple("this".to_string().into()),
+                Term::Not(Box::new(Term::Site("spam.com".to_string())))
+            ]
+        );
+        assert_eq!(
+            parse_terms("-intitle:spam"),
+            vec![Term::Not(Box::new(Term::Title("spam".to_string())))]
+        );
+        assert_eq!(
+            parse_terms("-inurl:spam"),
+            vec![Term::Not(Box::new(Term::Url("spam".to_string())))]
+        );
+        assert_eq!(
+            parse_terms("--site:spam.com"),
+            vec![Term::Simple("--site:spam.com".to_string().into())]
+        );
+
+        let schema = crate::schema::create_schema();
+        let fields: Vec<_> = schema.fields().map(|(field, _)| field).collect();
+
+        for term in parse_terms("-site:spam.com -intitle:spam -inurl:spam") {
+            let (occur, _) = term.as_tantivy_query(&fields, &fields, DEFAULT_MAX_TOKEN_LEN);
+            assert_eq!(occur, Occur::MustNot);
+        }
+    }
+
+    #[test]
+    fn escaped_operators() {
+        assert_eq!(
+            parse_terms("\\-foo"),
+            vec![Term::Simple("-foo".to_string().into())]
+        );
+        assert_eq!(
+            parse_terms("\\site:bar"),
+            vec![Term::Simple("site:bar".to_string().into())]
+        );
+        assert_eq!(
+            parse_terms("-\\site:bar"),
+            vec![Term::Not(Box::new(Term::Simple(
+                "site:bar".to_string().into()
+            )))]
+        );
+        assert_eq!(
+            parse_terms("\\\\site:bar"),
+            vec![Term::Simple("\\site:bar".to_string().into())]
+        );
+        assert_eq!(
+            parse_terms("foo \\"),
+            vec![
+                Term::Simple("foo".to_string().into()),
+                Term::Simple("\\".to_string().into())
+            ]
+        );
+        assert_eq!(
+            parse_terms("foo\\"),
+            vec![Term::Simple("foo\\".to_string().into())]
+        );
+
+        for query in ["\\-foo", "\\site:bar", "\\\\site:bar", "\\", "\\\"a b\""] {
+            let printed = itertools::intersperse(
+                parse_terms(query).iter().map(|term| term.to_string()),
+                " ".to_string(),
+            )
+            .collect::<String>();
+
+            assert_eq!(printed, query);
+        }
+    }
+
     #[test]
     fn site() {
         assert_eq!(
-            parse("this site:test.com"),
+            parse_terms("this site:test.com"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Site("test.com".to_string()))
+                Term::Simple("this".to_string().into()),
+                Term::Site("test.com".to_string())
             ]
         );
     }
 
+    #[test]
+    fn site_normalization() {
+        assert_eq!(
+            parse_terms("site:Example.com"),
+            vec![Term::Site("example.com".to_string())]
+        );
+        assert_eq!(
+            parse_terms("site:https://www.example.com/page?q=1"),
+            vec![Term::Site("www.example.com".to_string())]
+        );
+        assert_eq!(
+            parse_terms("site:example.com/blog"),
+            vec![Term::Site("example.com/blog".to_string())]
+        );
+        assert_eq!(
+            parse_terms("site:.com"),
+            vec![Term::Site(".com".to_string())]
+        );
+
+        assert_eq!(
+            parse_terms("site:!!!"),
+            vec![Term::Simple("site:!!!".to_string().into())]
+        );
+        assert_eq!(
+            parse_terms("site:https://"),
+            vec![Term::Simple("site:https://".to_string().into())]
+        );
+        assert_eq!(
+            parse_terms("site:"),
+            vec![Term::Simple("site:".to_string().into())]
+        );
+    }
+
     #[test]
     fn title() {
         assert_eq!(
-            parse("this intitle:test"),
+            parse_terms("this intitle:test"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Title("test".to_string()))
+                Term::Simple("this".to_string().into()),
+                Term::Title("test".to_string())
             ]
         );
     }
@@ -496,10 +1246,10 @@ mod tests {
     #[test]
     fn body() {
         assert_eq!(
-            parse("this inbody:test"),
+            parse_terms("this inbody:test"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Body("test".to_string()))
+                Term::Simple("this".to_string().into()),
+                Term::Body("test".to_string())
             ]
         );
     }
@@ -507,75 +1257,274 @@ mod tests {
     #[test]
     fn url() {
         assert_eq!(
-            parse("this inurl:test"),
+            parse_terms("this inurl:test"),
+            vec![
+                Term::Simple("this".to_string().into()),
+                Term::Url("test".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn url_prefix() {
+        assert_eq!(
+            parse_terms("this inurl:/blog/"),
+            vec![
+                Term::Simple("this".to_string().into()),
+                Term::UrlPrefix("/blog/".to_string())
+            ]
+        );
+        assert_eq!(
+            parse_terms("inurl:/blog/").first().unwrap().to_string(),
+            "inurl:/blog/"
+        );
+        assert_eq!(
+            parse_terms("This INURL:/Blog/ -inurl:/Blog/Draft"),
+            vec![
+                Term::Simple("this".to_string().into()),
+                Term::UrlPrefix("/Blog/".to_string()),
+                Term::Not(Box::new(Term::UrlPrefix("/Blog/Draft".to_string()))),
+            ]
+        );
+    }
+
+    #[test]
+    fn url_prefix_successor() {
+        assert_eq!(prefix_successor("/blog/"), Some("/blog0".to_string()));
+        assert_eq!(prefix_successor("/a\u{10FFFF}"), Some("/b".to_string()));
+        assert_eq!(prefix_successor(""), None);
+    }
+
+    #[test]
+    fn bang_prefix() {
+        assert_eq!(
+            parse_terms("foo ！g"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Url("test".to_string()))
+                Term::Simple("foo".to_string().into()),
+                Term::PossibleBang {
+                    prefix: '！',
+                    bang: "g".to_string()
+                }
             ]
         );
+
+        for query in ["！g", "!g", "-！g"] {
+            assert_eq!(parse_terms(query).first().unwrap().to_string(), query);
+        }
+    }
+
+    #[test]
+    fn empty_operators() {
+        for query in [
+            "site:", "intitle:", "inbody:", "inurl:", "-", "-site:", "\"\"", "-\"\"",
+        ] {
+            assert!(
+                parse_terms(query).iter().all(Term::is_empty_operator),
+                "{query}"
+            );
+        }
+
+        for query in ["site:example.com", "site", "-test", "inurl:/blog"] {
+            assert!(!parse_terms(query).iter().all(Term::is_empty_operator));
+        }
+    }
+
+    #[test]
+    fn case_sensitive_phrases() {
+        assert_eq!(
+            parse_terms_case_sensitive_phrases("Rust \"The Book\" -Cargo"),
+            vec![
+                Term::Simple("rust".to_string().into()),
+                Term::Phrase("The Book".to_string()),
+                Term::Not(Box::new(Term::Simple("cargo".to_string().into()))),
+            ]
+        );
+        assert_eq!(
+            parse_terms("\"The Book\""),
+            vec![Term::Phrase("the book".to_string())]
+        );
     }
 
     #[test]
     fn empty() {
-        assert_eq!(parse(""), vec![]);
+        assert_eq!(parse_terms(""), vec![]);
     }
 
     #[test]
-    fn phrase() {
+    fn not_phrase() {
         assert_eq!(
-            parse("\"this is a\" inurl:test"),
+            parse_terms("rust -\"the book\" cargo"),
             vec![
-                Box::new(Term::Phrase("this is a".to_string(),)),
-                Box::new(Term::Url("test".to_string()))
+                Term::Simple("rust".to_string().into()),
+                Term::Not(Box::new(Term::Phrase("the book".to_string()))),
+                Term::Simple("cargo".to_string().into()),
             ]
         );
         assert_eq!(
-            parse("\"this is a inurl:test"),
+            parse_terms("-\"\""),
+            vec![Term::Not(Box::new(Term::Phrase("".to_string())))]
+        );
+
+        // the `-` has to be immediately before the quote
+        assert_eq!(
+            parse_terms("- \"x\""),
             vec![
-                Box::new(Term::Simple("\"this".to_string().into())),
-                Box::new(Term::Simple("is".to_string().into())),
-                Box::new(Term::Simple("a".to_string().into())),
-                Box::new(Term::Url("test".to_string()))
+                Term::Simple("-".to_string().into()),
+                Term::Phrase("x".to_string()),
             ]
         );
+
+        // unterminated phrases are parsed as terms
         assert_eq!(
-            parse("this is a\" inurl:test"),
+            parse_terms("-\"the book"),
             vec![
-                Box::new(Term::Simple("this".to_string().into())),
-                Box::new(Term::Simple("is".to_string().into())),
-                Box::new(Term::Simple("a\"".to_string().into())),
-                Box::new(Term::Url("test".to_string()))
+                Term::Not(Box::new(Term::Simple("\"the".to_string().into()))),
+                Term::Simple("book".to_string().into()),
             ]
         );
 
         assert_eq!(
-            parse("\"this is a inurl:test\""),
-            vec![Box::new(Term::Phrase("this is a inurl:test".to_string(),)),]
+            parse_terms_case_sensitive_phrases("-\"The Book\""),
+            vec![Term::Not(Box::new(Term::Phrase("The Book".to_string())))]
+        );
+        assert_eq!(
+            Term::Not(Box::new(Term::Phrase("the book".to_string()))).to_string(),
+            "-\"the book\""
         );
+    }
 
+    #[test]
+    fn phrase() {
         assert_eq!(
-            parse("\"\""),
-            vec![Box::new(Term::Phrase("".to_string(),)),]
+            parse_terms("\"this is a\" inurl:test"),
+            vec![
+                Term::Phrase("this is a".to_string(),),
+                Term::Url("test".to_string())
+            ]
+        );
+        assert_eq!(
+            parse_terms("\"this is a inurl:test"),
+            vec![
+                Term::Simple("\"this".to_string().into()),
+                Term::Simple("is".to_string().into()),
+                Term::Simple("a".to_string().into()),
+                Term::Url("test".to_string())
+            ]
         );
         assert_eq!(
-            parse("“this is a“ inurl:test"),
+            parse_terms("this is a\" inurl:test"),
             vec![
-                Box::new(Term::Phrase("this is a".to_string(),)),
-                Box::new(Term::Url("test".to_string()))
+                Term::Simple("this".to_string().into()),
+                Term::Simple("is".to_string().into()),
+                Term::Simple("a\"".to_string().into()),
+                Term::Url("test".to_string())
+            ]
+        );
+
+        assert_eq!(
+            parse_terms("\"this is a inurl:test\""),
+            vec![Term::Phrase("this is a inurl:test".to_string(),),]
+        );
+
+        assert_eq!(parse_terms("\"\""), vec![Term::Phrase("".to_string(),),]);
+        assert_eq!(
+            parse_terms("“this is a“ inurl:test"),
+            vec![
+                Term::Phrase("this is a".to_string(),),
+                Term::Url("test".to_string())
             ]
         );
     }
 
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_parse() {
+        assert_eq!(
+            parse("this -that"),
+            parse_terms("this -that")
+                .into_iter()
+                .map(Box::new)
+                .collect::<Vec<_>>()
+        );
+    }
+
     #[test]
     fn unicode() {
         let query = "\u{a0}";
-        assert_eq!(parse(query).len(), 1);
+        assert!(parse_terms(query).is_empty());
+
+        assert_eq!(
+            parse_terms("ælg\u{a0}\u{3000}\"søster bror\"\u{a0}ø"),
+            vec![
+                Term::Simple("ælg".to_string().into()),
+                Term::Phrase("søster bror".to_string()),
+                Term::Simple("ø".to_string().into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn bidi_text() {
+        assert_eq!(
+            parse_terms("rust \u{200F}مرحبا\u{200E} world"),
+            vec![
+                Term::Simple("rust".to_string().into()),
+                Term::Simple("مرحبا".to_string().into()),
+                Term::Simple("world".to_string().into()),
+            ]
+        );
+
+        assert_eq!(
+            parse_terms("\u{202B}שלום\u{202C} \"Hello \u{2067}עולם\u{2069}\""),
+            vec![
+                Term::Simple("שלום".to_string().into()),
+                Term::Phrase("hello עולם".to_string()),
+            ]
+        );
+
+        assert_eq!(
+            parse_terms("te\u{200F}st site:\u{200E}example.com"),
+            vec![
+                Term::Simple("test".to_string().into()),
+                Term::Site("example.com".to_string()),
+            ]
+        );
+
+        assert!(parse_terms("\u{200E}\u{200F}\u{061C}").is_empty());
     }
 
     proptest! {
+        #[test]
+        fn prop_bidi(query in "[a-z \u{0590}-\u{05FF}\u{0600}-\u{06FF}\u{200E}\u{200F}\u{202A}-\u{202E}\u{2066}-\u{2069}\":-]{0,40}") {
+            let terms = parse_terms(&query);
+
+            for term in &terms {
+                let text = term.to_string();
+                prop_assert!(std::str::from_utf8(text.as_bytes()).is_ok());
+                prop_assert!(!text.chars().any(is_bidi_control));
+            }
+
+            prop_assert_eq!(parse_terms_case_sensitive_phrases(&query).len(), terms.len());
+        }
+
         #[test]
         fn prop(query: String) {
-            parse(&query);
+            let normalized = normalize(&query.to_lowercase());
+            let terms = parse_terms(&query);
+
+            for term in &terms {
+                if let Term::Simple(SimpleTerm(text)) | Term::Phrase(text) = term {
+                    prop_assert!(normalized.contains(text.as_str()));
+                }
+            }
+
+            let printed = itertools::intersperse(
+                terms.iter().map(|term| term.to_string()),
+                " ".to_string(),
+            )
+            .collect::<String>();
+
+            prop_assert_eq!(parse_terms(&printed), terms);
         }
     }
 }
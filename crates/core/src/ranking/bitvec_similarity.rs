@@ -155,6 +155,11 @@ impl BitVec {
         self.posting.ranks.len()
     }
 
+    /// The sorted and deduplicated ranks the vector was created from.
+    pub fn ranks(&self) -> &[u64] {
+        &self.posting.ranks
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -1,5 +1,7 @@
 // source: https://github.com/quickwit-oss/tantivy/blob/main/src/query/bm25.rs
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 use tantivy::fieldnorm::FieldNormReader;
@@ -28,6 +30,69 @@ fn compute_tf_cache(average_fieldnorm: Score) -> [Score; 256] {
     cache
 }
 
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+struct FieldStats {
+    total_num_tokens: u64,
+    total_num_docs: u64,
+}
+
+/// Corpus statistics used to compute the bm25 weights of a set of terms. Statistics
+/// from several indexes can be merged, so their scores are computed as if the documents
+/// were in a single index and can be compared with each other.
+#[derive(Clone, Default, Debug, PartialEq)]
+pub struct Bm25Stats {
+    fields: HashMap<tantivy::schema::Field, FieldStats>,
+    doc_freqs: HashMap<Term, u64>,
+}
+
+impl Bm25Stats {
+    pub fn collect(searcher: &Searcher, terms: &[Term]) -> tantivy::Result<Self> {
+        let mut stats = Self::default();
+
+        for term in terms {
+            if !stats.fields.contains_key(&term.field()) {
+                let mut field = FieldStats::default();
+
+                for segment_reader in searcher.segment_readers() {
+                    let inverted_index = segment_reader.inverted_index(term.field())?;
+                    field.total_num_tokens += inverted_index.total_num_tokens();
+                    field.total_num_docs += u64::from(segment_reader.max_doc());
+                }
+
+                stats.fields.insert(term.field(), field);
+            }
+
+            if !stats.doc_freqs.contains_key(term) {
+                stats
+                    .doc_freqs
+                    .insert(term.clone(), searcher.doc_freq(term)?);
+            }
+        }
+
+        Ok(stats)
+    }
+
+    pub fn merge(&mut self, other: Self) {
+        for (field, other) in other.fields {
+            let field = self.fields.entry(field).or_default();
+            field.total_num_tokens += other.total_num_tokens;
+            field.total_num_docs += other.total_num_docs;
+        }
+
+        for (term, doc_freq) in other.doc_freqs {
+            *self.doc_freqs.entry(term).or_default() += doc_freq;
+        }
+    }
+
+    fn field(&self, field: tantivy::schema::Field) -> FieldStats {
+        self.fields.get(&field).copied().unwrap_or_default()
+    }
+
+    fn doc_freq(&self, term: &Term) -> u64 {
+        self.doc_freqs.get(term).copied().unwrap_or_default()
+    }
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Bm25Params {
     pub idf: Score,
@@ -53,6 +118,15 @@ impl Bm25Weight {
     }
 
     pub fn for_terms(searcher: &Searcher, terms: &[Term]) -> tantivy::Result<Bm25Weight> {
+        Ok(Self::for_terms_with_stats(
+            &Bm25Stats::collect(searcher, terms)?,
+            terms,
+        ))
+    }
+
+    /// Weight of `terms` using the corpus statistics in `stats` instead of
+    /// the statistics of a single searcher.
+    pub fn for_terms_with_stats(stats: &Bm25Stats, terms: &[Term]) -> Bm25Weight {
         assert!(!terms.is_empty(), "Bm25 requires at least one term");
         let field = terms[0].field();
         for term in &terms[1..] {
@@ -63,30 +137,23 @@ impl Bm25Weight {
             );
         }
 
-        let mut total_num_tokens = 0u64;
-        let mut total_num_docs = 0u64;
-        for segment_reader in searcher.segment_readers() {
-            let inverted_index = segment_reader.inverted_index(field)?;
-            total_num_tokens += inverted_index.total_num_tokens();
-            total_num_docs += u64::from(segment_reader.max_doc());
-        }
+        let FieldStats {
+            total_num_tokens,
+            total_num_docs,
+        } = stats.field(field);
         let average_fieldnorm = total_num_tokens as Score / total_num_docs as Score;
 
         if terms.len() == 1 {
-            let term_doc_freq = searcher.doc_freq(&terms[0])?;
-            Ok(Bm25Weight::for_one_term(
-                term_doc_freq,
-                total_num_docs,
-                average_fieldnorm,
-            ))
+            let term_doc_freq = stats.doc_freq(&terms[0]);
+            Bm25Weight::for_one_term(term_doc_freq, total_num_docs, average_fieldnorm)
         } else {
             let mut idf_sum: Score = 0.0;
             for term in terms {
-                let term_doc_freq = searcher.doc_freq(term)?;
+                let term_doc_freq = stats.doc_freq(term);
                 idf_sum += idf(term_doc_freq, total_num_docs);
             }
             let idf_explain = Explanation::new("idf", idf_sum);
-            Ok(Bm25Weight::new(idf_explain, average_fieldnorm))
+            Bm25Weight::new(idf_explain, average_fieldnorm)
         }
     }
 
@@ -0,0 +1,75 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Human readable explanation of how the score of a single result was computed.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{pipeline::RankingWebsite, Signal, ALL_SIGNALS};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExplanationComponent {
+    #[schema(value_type = String)]
+    pub signal: Signal,
+    pub value: f64,
+    pub coefficient: f64,
+    /// `coefficient * value`
+    pub contribution: f64,
+}
+
+/// Break down of the final score of a result. Without a learned ranking
+/// model, the final score is the sum of the component contributions
+/// multiplied by the optic boost (if any).
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Explanation {
+    pub score: f64,
+    pub components: Vec<ExplanationComponent>,
+    pub optic_boost: Option<f64>,
+}
+
+impl Explanation {
+    pub fn sum_of_contributions(&self) -> f64 {
+        self.components.iter().map(|c| c.contribution).sum()
+    }
+}
+
+impl From<&RankingWebsite> for Explanation {
+    fn from(website: &RankingWebsite) -> Self {
+        let components = ALL_SIGNALS
+            .into_iter()
+            .filter_map(|signal| {
+                website
+                    .signals
+                    .get(signal)
+                    .map(|score| ExplanationComponent {
+                        signal,
+                        value: score.value,
+                        coefficient: score.coefficient,
+                        contribution: score.coefficient * score.value,
+                    })
+            })
+            .collect();
+
+        Self {
+            score: website.score,
+            components,
+            optic_boost: website.optic_boost,
+        }
+    }
+}
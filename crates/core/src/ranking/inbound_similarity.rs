@@ -164,7 +164,7 @@ impl Scorer {
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 struct PreCalculatedSimilarities {
     map: HashMap<NodeID, HashMap<NodeID, f64>>,
 }
@@ -179,13 +179,13 @@ impl PreCalculatedSimilarities {
     }
 }
 
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Serialize, Deserialize, Default, Clone)]
 struct VecMap {
     map: HashMap<NodeID, bitvec_similarity::BitVec>,
 }
 
 impl VecMap {
-    fn build(graph: &Webgraph) -> Self {
+    fn build(graph: &Webgraph, max_inbound: Option<usize>) -> Self {
         let mut map = HashMap::default();
 
         let adjacency: DashMap<NodeID, HashSet<NodeID>> = DashMap::new();
@@ -194,7 +194,31 @@ impl VecMap {
             adjacency.entry(edge.to).or_default().insert(edge.from);
         });
 
+        // the number of inbound links is used as a cheap centrality
+        // to decide which neighbors to keep when pruning
+        let num_inbound: HashMap<NodeID, usize> = match max_inbound {
+            Some(_) => adjacency
+                .iter()
+                .map(|entry| (*entry.key(), entry.value().len()))
+                .collect(),
+            None => HashMap::default(),
+        };
+
         for (node_id, inbound) in adjacency {
+            let mut inbound: Vec<_> = inbound.into_iter().collect();
+
+            if let Some(max_inbound) = max_inbound {
+                if inbound.len() > max_inbound {
+                    inbound.sort_by(|a, b| {
+                        let a_inbound = num_inbound.get(a).copied().unwrap_or_default();
+                        let b_inbound = num_inbound.get(b).copied().unwrap_or_default();
+
+                        b_inbound.cmp(&a_inbound).then(a.cmp(b))
+                    });
+                    inbound.truncate(max_inbound);
+                }
+            }
+
             map.insert(
                 node_id,
                 bitvec_similarity::BitVec::new(inbound.into_iter().map(|n| n.as_u64()).collect()),
@@ -221,7 +245,14 @@ pub struct InboundSimilarity {
 
 impl InboundSimilarity {
     pub fn build(graph: &Webgraph) -> Self {
-        let vectors = VecMap::build(graph);
+        Self::build_with_max_inbound(graph, None)
+    }
+
+    /// Only keep the `max_inbound` most linked-to neighbors in the inbound vector of each node.
+    /// This bounds the size of the vectors for nodes with a huge number of inbound links,
+    /// at the cost of less accurate similarities for those nodes.
+    pub fn build_with_max_inbound(graph: &Webgraph, max_inbound: Option<usize>) -> Self {
+        let vectors = VecMap::build(graph, max_inbound);
 
         tracing::info!("precalculating similarities...");
 
@@ -276,6 +307,52 @@ impl InboundSimilarity {
         }
     }
 
+    /// Add the inbound links `(from, to)` to the vectors without rebuilding the entire
+    /// similarity. Only the vectors of the nodes that got new inbound links are changed
+    /// and the precalculated similarities involving them are recalculated. New
+    /// candidates are not added to the precalculated similarities, and vectors are
+    /// not pruned, so a full rebuild should still happen once in a while.
+    pub fn update(&mut self, new_edges: &[(NodeID, NodeID)]) {
+        let mut new_inbound: HashMap<NodeID, HashSet<NodeID>> = HashMap::default();
+
+        for (from, to) in new_edges {
+            new_inbound.entry(*to).or_default().insert(*from);
+        }
+
+        let vectors = Arc::make_mut(&mut self.vectors);
+
+        for (node, inbound) in new_inbound.iter() {
+            let mut ranks: Vec<u64> = inbound.iter().map(|n| n.as_u64()).collect();
+
+            if let Some(vec) = vectors.get(node) {
+                ranks.extend_from_slice(vec.ranks());
+            }
+
+            vectors
+                .map
+                .insert(*node, bitvec_similarity::BitVec::new(ranks));
+        }
+
+        let precalculated = Arc::make_mut(&mut self.precalculated);
+
+        for (node, similarities) in precalculated.map.iter_mut() {
+            let node_changed = new_inbound.contains_key(node);
+
+            let node_vec = match vectors.get(node) {
+                Some(vec) => vec,
+                None => continue,
+            };
+
+            for (candidate, score) in similarities.iter_mut() {
+                if node_changed || new_inbound.contains_key(candidate) {
+                    if let Some(candidate_vec) = vectors.get(candidate) {
+                        *score = node_vec.sim(candidate_vec);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn scorer(
         &self,
         liked_hosts: &[NodeID],
@@ -343,7 +420,7 @@ mod tests {
     use crate::{
         gen_temp_path,
         index::Index,
-        rand_words,
+        rand_words_seeded,
         searcher::{LocalSearcher, SearchQuery},
         webgraph::{Node, WebgraphWriter},
         webpage::{Html, Webpage},
@@ -380,6 +457,111 @@ mod tests {
         assert!(scorer.score(&e) > scorer.score(&d));
     }
 
+    #[test]
+    fn pruned_inbound() {
+        let mut wrt = WebgraphWriter::new(
+            gen_temp_path(),
+            crate::executor::Executor::single_thread(),
+            crate::webgraph::Compression::default(),
+        );
+
+        for (from, to) in [
+            ("first.com", "nan.com"),
+            ("nan.com", "first.com"),
+            ("third.com", "third.com"),
+            ("nan.com", "second.com"),
+            ("second.com", "nan.com"),
+            ("second.com", "third.com"),
+            ("extra.com", "first.com"),
+            ("second.com", "extra.com"),
+        ] {
+            wrt.insert(Node::from(from), Node::from(to), String::new());
+        }
+
+        let graph = wrt.finalize();
+
+        let full = InboundSimilarity::build(&graph);
+        let pruned = InboundSimilarity::build_with_max_inbound(&graph, Some(1));
+
+        let first = Node::from("first.com").id();
+        let second = Node::from("second.com").id();
+        let third = Node::from("third.com").id();
+
+        assert_eq!(full.get(&first).unwrap().len(), 2);
+        assert_eq!(pruned.get(&first).unwrap().len(), 1);
+
+        for inbound in [full, pruned] {
+            let mut scorer = inbound.scorer(&[first], &[], false);
+
+            assert!(scorer.score(&second) > scorer.score(&third));
+        }
+    }
+
+    #[test]
+    fn incremental_update() {
+        let edges = [
+            ("first.com", "nan.com"),
+            ("nan.com", "first.com"),
+            ("third.com", "third.com"),
+            ("nan.com", "second.com"),
+            ("second.com", "nan.com"),
+            ("second.com", "third.com"),
+            ("extra.com", "first.com"),
+            ("second.com", "extra.com"),
+        ];
+
+        let graph = |edges: &[(&str, &str)]| {
+            let mut wrt = WebgraphWriter::new(
+                gen_temp_path(),
+                crate::executor::Executor::single_thread(),
+                crate::webgraph::Compression::default(),
+            );
+
+            for (from, to) in edges {
+                wrt.insert(Node::from(*from), Node::from(*to), String::new());
+            }
+
+            wrt.finalize()
+        };
+
+        let full = InboundSimilarity::build(&graph(&edges));
+
+        let mut updated = InboundSimilarity::build(&graph(&edges[..5]));
+        updated.update(
+            &edges[5..]
+                .iter()
+                .map(|(from, to)| (Node::from(*from).id(), Node::from(*to).id()))
+                .collect::<Vec<_>>(),
+        );
+
+        let nodes: Vec<_> = [
+            "first.com",
+            "second.com",
+            "third.com",
+            "nan.com",
+            "extra.com",
+        ]
+        .into_iter()
+        .map(|node| Node::from(node).id())
+        .collect();
+
+        for node in &nodes {
+            assert_eq!(
+                full.get(node).map(|vec| vec.ranks().to_vec()),
+                updated.get(node).map(|vec| vec.ranks().to_vec())
+            );
+        }
+
+        for liked in &nodes {
+            let mut full_scorer = full.scorer(&[*liked], &[], false);
+            let mut updated_scorer = updated.scorer(&[*liked], &[], false);
+
+            for node in &nodes {
+                assert!((full_scorer.score(node) - updated_scorer.score(node)).abs() < 1e-6);
+            }
+        }
+    }
+
     #[test]
     fn it_ranks_search_results() {
         let mut wrt = WebgraphWriter::new(
@@ -413,7 +595,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 1)
                     ),
                     "https://e.com",
                 )
@@ -437,7 +619,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        rand_words(1000)
+                        rand_words_seeded(1000, 2)
                     ),
                     "https://d.com",
                 )
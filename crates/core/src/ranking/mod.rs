@@ -16,6 +16,7 @@
 
 pub mod bitvec_similarity;
 pub mod bm25;
+pub mod explain;
 pub mod inbound_similarity;
 pub mod initial;
 pub mod models;
@@ -156,7 +157,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 1)
                     ),
                     "https://www.a.com",
                 )
@@ -179,7 +180,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 2)
                     ),
                     "https://www.b.com",
                 )
@@ -222,7 +223,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 3)
                     ),
                     "https://www.a.com",
                 )
@@ -245,7 +246,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 4)
                     ),
                     "https://www.b.com",
                 )
@@ -289,7 +290,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100),
+                        crate::rand_words_seeded(100, 5),
                     ),
                     "https://www.old.com",
                 )
@@ -314,7 +315,7 @@ mod tests {
                         </body>
                     </html>
                 "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 6)
                     ),
                     "https://www.new.com",
                 )
@@ -599,7 +600,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 7)
                     ),
                     "https://www.first.com",
                 )
@@ -622,7 +623,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 8)
                     ),
                     "https://www.second.com",
                 )
@@ -665,7 +666,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 9)
                     ),
                     "https://www.first.com/one",
                 )
@@ -689,7 +690,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 10)
                     ),
                     "https://www.second.com/one/two",
                 )
@@ -713,7 +714,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 11)
                     ),
                     "https://www.third.com/one/two123",
                 )
@@ -737,4 +738,101 @@ mod tests {
         assert_eq!(result.webpages[1].url, "https://www.second.com/one/two");
         assert_eq!(result.webpages[2].url, "https://www.third.com/one/two123");
     }
+
+    #[test]
+    fn url_length_ranking() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                {CONTENT} {}
+                            </body>
+                        </html>
+                    "#,
+                        crate::rand_words_seeded(100, 12)
+                    ),
+                    "https://www.example.com",
+                )
+                .unwrap(),
+                host_centrality: 1.0,
+                page_centrality: 0.0,
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                {CONTENT} {}
+                            </body>
+                        </html>
+                    "#,
+                        crate::rand_words_seeded(100, 13)
+                    ),
+                    "https://www.example.com/blog/2023/05/some-long-post-title",
+                )
+                .unwrap(),
+                host_centrality: 1.0,
+                page_centrality: 0.5,
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::new(index);
+
+        let search = |url_length: f64| {
+            searcher
+                .search(&SearchQuery {
+                    query: "test".to_string(),
+                    optic: Some(
+                        Optic::parse(&format!(
+                            r#"
+                            Ranking(Signal("is_homepage"), 0);
+                            Ranking(Signal("url_slashes"), 0);
+                            Ranking(Signal("url_digits"), 0);
+                            Ranking(Signal("url_length"), {url_length});
+                        "#
+                        ))
+                        .unwrap(),
+                    ),
+                    ..Default::default()
+                })
+                .expect("Search failed")
+                .webpages
+                .into_iter()
+                .map(|webpage| webpage.url)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            search(0.0),
+            vec![
+                "https://www.example.com/blog/2023/05/some-long-post-title".to_string(),
+                "https://www.example.com/".to_string(),
+            ]
+        );
+        assert_eq!(
+            search(1.0),
+            vec![
+                "https://www.example.com/".to_string(),
+                "https://www.example.com/blog/2023/05/some-long-post-title".to_string(),
+            ]
+        );
+    }
 }
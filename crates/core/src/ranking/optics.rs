@@ -21,7 +21,7 @@ mod tests {
     use crate::{
         gen_temp_path,
         index::Index,
-        ranking::inbound_similarity::InboundSimilarity,
+        ranking::{inbound_similarity::InboundSimilarity, Signal},
         searcher::{LocalSearcher, SearchQuery},
         webgraph::{Node, WebgraphWriter},
         webpage::{Html, Webpage},
@@ -95,7 +95,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 1)
                     ),
                     "https://www.first.com",
                 )
@@ -120,7 +120,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 2)
                     ),
                     "https://www.second.com",
                 )
@@ -145,7 +145,7 @@ mod tests {
                             </body>
                         </html>
                     "#,
-                        crate::rand_words(100)
+                        crate::rand_words_seeded(100, 3)
                     ),
                     "https://www.third.com",
                 )
@@ -247,4 +247,86 @@ mod tests {
         assert_eq!(result.webpages[1].url, "https://www.second.com/");
         assert_eq!(result.webpages[2].url, "https://www.third.com/");
     }
+
+    #[test]
+    fn host_rankings_without_inbound_similarity() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (i, host) in ["first", "second", "third"].into_iter().enumerate() {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+                        <html>
+                            <head>
+                                <title>Test website</title>
+                            </head>
+                            <body>
+                                {CONTENT} {}
+                            </body>
+                        </html>
+                    "#,
+                            crate::rand_words_seeded(100, 10 + i as u64)
+                        ),
+                        &format!("https://www.{host}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: (3 - i) as f64,
+                    fetch_time_ms: 500,
+                    node_id: Some(
+                        Node::from(format!("https://www.{host}.com"))
+                            .into_host()
+                            .id(),
+                    ),
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+        index.commit().expect("failed to commit index");
+        let searcher = LocalSearcher::new(index);
+
+        let search = |host_rankings: Option<HostRankings>| {
+            searcher
+                .search(&SearchQuery {
+                    query: "test".to_string(),
+                    host_rankings,
+                    ..Default::default()
+                })
+                .expect("Search failed")
+        };
+
+        let urls = |result: &crate::searcher::WebsitesResult| -> Vec<String> {
+            result.webpages.iter().map(|w| w.url.clone()).collect()
+        };
+
+        let baseline = search(None);
+        let result = search(Some(HostRankings {
+            liked: vec!["www.third.com".to_string()],
+            disliked: vec!["www.first.com".to_string()],
+            blocked: vec![],
+        }));
+
+        assert_eq!(
+            urls(&result),
+            vec![
+                "https://www.first.com/".to_string(),
+                "https://www.second.com/".to_string(),
+                "https://www.third.com/".to_string(),
+            ]
+        );
+        assert_eq!(urls(&result), urls(&baseline));
+
+        for webpage in &result.webpages {
+            let inbound = webpage
+                .ranking_signals
+                .as_ref()
+                .unwrap()
+                .get(&Signal::InboundSimilarity)
+                .map(|score| score.value)
+                .unwrap_or_default();
+
+            assert_eq!(inbound, 0.0);
+        }
+    }
 }
@@ -478,6 +478,7 @@ mod tests {
                         score: Score { total: 0.0 },
                         hashes: Hashes {
                             site: Prehashed(0),
+                            domain: Prehashed(0),
                             title: Prehashed(0),
                             url: Prehashed(0),
                             url_without_tld: Prehashed(0),
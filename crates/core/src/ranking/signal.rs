@@ -45,7 +45,7 @@ use crate::{
     webpage::region::{Region, RegionCount},
 };
 
-use super::bm25::Bm25Weight;
+use super::bm25::{Bm25Stats, Bm25Weight};
 use super::models::linear::LinearRegression;
 use super::{inbound_similarity, query_centrality};
 
@@ -131,6 +131,12 @@ pub enum Signal {
     UrlSlashes,
     #[serde(rename = "link_density")]
     LinkDensity,
+    #[serde(rename = "url_length")]
+    UrlLength,
+    /// 1 if the document only matches a phrase of the query as a conjunction of its
+    /// tokens in a field without positions, so the tokens might not be adjacent.
+    #[serde(rename = "phrase_fallback")]
+    PhraseFallback,
 }
 
 impl From<Signal> for usize {
@@ -139,7 +145,7 @@ impl From<Signal> for usize {
     }
 }
 
-pub const ALL_SIGNALS: [Signal; 37] = [
+pub const ALL_SIGNALS: [Signal; 39] = [
     Signal::Bm25Title,
     Signal::Bm25TitleBigrams,
     Signal::Bm25TitleTrigrams,
@@ -177,6 +183,8 @@ pub const ALL_SIGNALS: [Signal; 37] = [
     Signal::UrlDigits,
     Signal::UrlSlashes,
     Signal::LinkDensity,
+    Signal::UrlLength,
+    Signal::PhraseFallback,
 ];
 
 fn score_timestamp(timestamp: usize, signal_aggregator: &SignalAggregator) -> f64 {
@@ -214,6 +222,17 @@ fn score_slashes(num_slashes: f64) -> f64 {
     1.0 / (num_slashes + 1.0)
 }
 
+/// Number of characters in the url path and query that counts as much as one extra
+/// path segment in [`score_url_length`].
+const URL_CHARS_PER_SEGMENT: f64 = 20.0;
+
+/// Shallow urls with short paths score higher, as they are more likely
+/// to be authoritative pages like homepages.
+#[inline]
+fn score_url_length(num_slashes: f64, num_chars: f64) -> f64 {
+    1.0 / (num_slashes + num_chars / URL_CHARS_PER_SEGMENT + 1.0)
+}
+
 #[inline]
 fn score_link_density(link_density: f64) -> f64 {
     if link_density > 0.5 {
@@ -308,6 +327,8 @@ impl Signal {
             Signal::UrlSlashes => 0.01,
             Signal::UrlDigits => 0.01,
             Signal::LinkDensity => 0.00,
+            Signal::UrlLength => 0.01,
+            Signal::PhraseFallback => -0.01,
         }
     }
 
@@ -374,6 +395,11 @@ impl Signal {
                 let val = fastfield_reader.get(&self.as_fastfield().unwrap());
                 Some(score_slashes(val as f64))
             }
+            Signal::UrlLength => {
+                let num_slashes = fastfield_reader.get(&FastField::NumPathAndQuerySlashes);
+                let num_chars = fastfield_reader.get(&self.as_fastfield().unwrap());
+                Some(score_url_length(num_slashes as f64, num_chars as f64))
+            }
             Signal::Region => {
                 let val = fastfield_reader.get(&self.as_fastfield().unwrap());
                 let region = Region::from_id(val);
@@ -409,6 +435,17 @@ impl Signal {
                 .get_mut(self.as_textfield().unwrap())
                 .map(|field| bm25(field, doc)),
 
+            Signal::PhraseFallback => {
+                if seg_reader.exact_phrases.is_empty() {
+                    None
+                } else {
+                    let is_fallback = seg_reader.exact_phrases.iter_mut().any(|docset| {
+                        !(docset.doc() == doc || (docset.doc() < doc && docset.seek(doc) == doc))
+                    });
+
+                    Some(if is_fallback { 1.0 } else { 0.0 })
+                }
+            }
             Signal::CrossEncoderSnippet => None, // this is calculated in a later step
             Signal::CrossEncoderTitle => None,   // this is calculated in a later step
             Signal::LambdaMART => None,
@@ -488,6 +525,14 @@ impl Signal {
                     .count() as f64;
                 Some(score_slashes(num_slashes))
             }
+            Signal::UrlLength => {
+                let url = webpage.html.url();
+                let num_slashes = url.path().chars().filter(|c| c == &'/').count() as f64;
+                let num_chars = (url.path().chars().count()
+                    + url.query().unwrap_or_default().chars().count())
+                    as f64;
+                Some(score_url_length(num_slashes, num_chars))
+            }
             Signal::LinkDensity => {
                 let link_density = webpage.html.link_density();
                 Some(score_link_density(link_density))
@@ -516,7 +561,8 @@ impl Signal {
             | Signal::CrossEncoderTitle
             | Signal::InboundSimilarity
             | Signal::LambdaMART
-            | Signal::QueryCentrality => {
+            | Signal::QueryCentrality
+            | Signal::PhraseFallback => {
                 tracing::error!("signal {self:?} cannot be precomputed");
                 None
             }
@@ -545,6 +591,7 @@ impl Signal {
             Signal::UrlSlashes => Some(FastField::NumPathAndQuerySlashes),
             Signal::UrlDigits => Some(FastField::NumPathAndQueryDigits),
             Signal::LinkDensity => Some(FastField::LinkDensity),
+            Signal::UrlLength => Some(FastField::NumPathAndQueryChars),
             _ => None,
         }
     }
@@ -651,12 +698,14 @@ struct OpticBoosts {
 struct SegmentReader {
     text_fields: EnumMap<TextField, TextFieldData>,
     optic_boosts: OpticBoosts,
+    exact_phrases: Vec<Box<dyn Scorer>>,
     fastfield_reader: Arc<fastfield_reader::SegmentReader>,
 }
 
 #[derive(Clone)]
 struct QueryData {
     simple_terms: Vec<String>,
+    exact_phrases: Vec<tantivy::query::BooleanQuery>,
     optic_rules: Vec<optics::Rule>,
     selected_region: Option<Region>,
 }
@@ -672,6 +721,7 @@ pub struct SignalAggregator {
     region_count: Option<Arc<RegionCount>>,
     current_timestamp: Option<usize>,
     linear_regression: Option<Arc<LinearRegression>>,
+    bm25_stats: Option<Arc<Bm25Stats>>,
     order: SignalOrder,
 }
 
@@ -698,6 +748,7 @@ impl Clone for SignalAggregator {
             region_count: self.region_count.clone(),
             current_timestamp: self.current_timestamp,
             linear_regression: self.linear_regression.clone(),
+            bm25_stats: self.bm25_stats.clone(),
             order: self.order.clone(),
         }
     }
@@ -732,6 +783,7 @@ impl SignalAggregator {
 
         let query = query.as_ref().map(|q| QueryData {
             simple_terms: q.simple_terms().to_vec(),
+            exact_phrases: q.exact_phrases().to_vec(),
             optic_rules: q
                 .optics()
                 .iter()
@@ -755,6 +807,7 @@ impl SignalAggregator {
             region_count: None,
             current_timestamp: None,
             linear_regression: None,
+            bm25_stats: None,
             query_data: query,
             order: SignalOrder::empty(),
         };
@@ -765,13 +818,12 @@ impl SignalAggregator {
         s
     }
 
-    fn prepare_textfields(
+    /// The terms of the query for each text field with a bm25 signal.
+    fn textfield_terms(
         &self,
-        tv_searcher: &tantivy::Searcher,
-        segment_reader: &tantivy::SegmentReader,
-    ) -> Result<EnumMap<TextField, TextFieldData>> {
-        let mut text_fields = EnumMap::new();
-        let schema = tv_searcher.schema();
+        schema: &tantivy::schema::Schema,
+    ) -> Vec<(TextField, tantivy::schema::Field, Vec<tantivy::Term>)> {
+        let mut res = Vec::new();
 
         if let Some(query) = &self.query_data {
             if !query.simple_terms.is_empty() {
@@ -793,35 +845,66 @@ impl SignalAggregator {
                             terms.push(term);
                         }
 
-                        if terms.is_empty() {
-                            continue;
+                        if !terms.is_empty() {
+                            res.push((text_field, tv_field, terms));
                         }
+                    }
+                }
+            }
+        }
 
-                        let weight = Bm25Weight::for_terms(tv_searcher, &terms)?;
+        res
+    }
 
-                        let fieldnorm_reader = segment_reader.get_fieldnorms_reader(tv_field)?;
-                        let inverted_index = segment_reader.inverted_index(tv_field)?;
+    /// Corpus statistics of `tv_searcher` for the terms of the query. Statistics of
+    /// several indexes can be merged and set with [`SignalAggregator::set_bm25_stats`].
+    pub fn bm25_stats(&self, tv_searcher: &tantivy::Searcher) -> Result<Bm25Stats> {
+        let terms: Vec<_> = self
+            .textfield_terms(tv_searcher.schema())
+            .into_iter()
+            .flat_map(|(_, _, terms)| terms)
+            .collect();
 
-                        let mut postings = Vec::with_capacity(terms.len());
-                        for term in &terms {
-                            if let Some(p) =
-                                inverted_index.read_postings(term, text_field.index_option())?
-                            {
-                                postings.push(p);
-                            }
-                        }
+        Ok(Bm25Stats::collect(tv_searcher, &terms)?)
+    }
 
-                        text_fields.insert(
-                            text_field,
-                            TextFieldData {
-                                postings,
-                                weight,
-                                fieldnorm_reader,
-                            },
-                        );
-                    }
+    /// Compute the bm25 signals from `stats` instead of the statistics of the
+    /// searched index, so scores from different indexes are comparable.
+    pub fn set_bm25_stats(&mut self, stats: Arc<Bm25Stats>) {
+        self.bm25_stats = Some(stats);
+    }
+
+    fn prepare_textfields(
+        &self,
+        tv_searcher: &tantivy::Searcher,
+        segment_reader: &tantivy::SegmentReader,
+    ) -> Result<EnumMap<TextField, TextFieldData>> {
+        let mut text_fields = EnumMap::new();
+
+        for (text_field, tv_field, terms) in self.textfield_terms(tv_searcher.schema()) {
+            let weight = match &self.bm25_stats {
+                Some(stats) => Bm25Weight::for_terms_with_stats(stats, &terms),
+                None => Bm25Weight::for_terms(tv_searcher, &terms)?,
+            };
+
+            let fieldnorm_reader = segment_reader.get_fieldnorms_reader(tv_field)?;
+            let inverted_index = segment_reader.inverted_index(tv_field)?;
+
+            let mut postings = Vec::with_capacity(terms.len());
+            for term in &terms {
+                if let Some(p) = inverted_index.read_postings(term, text_field.index_option())? {
+                    postings.push(p);
                 }
             }
+
+            text_fields.insert(
+                text_field,
+                TextFieldData {
+                    postings,
+                    weight,
+                    fieldnorm_reader,
+                },
+            );
         }
 
         Ok(text_fields)
@@ -858,6 +941,29 @@ impl SignalAggregator {
         optic_rule_boosts
     }
 
+    fn prepare_exact_phrases(
+        &self,
+        tv_searcher: &tantivy::Searcher,
+        segment_reader: &tantivy::SegmentReader,
+    ) -> Result<Vec<Box<dyn Scorer>>> {
+        let mut exact_phrases = Vec::new();
+
+        if let Some(query) = &self.query_data {
+            for phrase in &query.exact_phrases {
+                let scorer = phrase
+                    .weight(tantivy::query::EnableScoring::Enabled {
+                        searcher: tv_searcher,
+                        statistics_provider: tv_searcher,
+                    })?
+                    .scorer(segment_reader, 0.0)?;
+
+                exact_phrases.push(scorer);
+            }
+        }
+
+        Ok(exact_phrases)
+    }
+
     pub fn register_segment(
         &mut self,
         tv_searcher: &tantivy::Searcher,
@@ -867,6 +973,7 @@ impl SignalAggregator {
         let fastfield_segment_reader = fastfield_reader.get_segment(&segment_reader.segment_id());
         let text_fields = self.prepare_textfields(tv_searcher, segment_reader)?;
         let optic_rule_boosts = self.prepare_optic(tv_searcher, segment_reader, fastfield_reader);
+        let exact_phrases = self.prepare_exact_phrases(tv_searcher, segment_reader)?;
 
         self.segment_reader = Some(RefCell::new(SegmentReader {
             text_fields,
@@ -874,6 +981,7 @@ impl SignalAggregator {
             optic_boosts: OpticBoosts {
                 rules: optic_rule_boosts,
             },
+            exact_phrases,
         }));
 
         Ok(())
@@ -17,7 +17,8 @@
 use tantivy::schema::{IndexRecordOption, NumericOptions, TextFieldIndexing, TextOptions};
 
 use crate::tokenizer::{
-    BigramTokenizer, Identity, JsonField, SiteOperatorUrlTokenizer, Tokenizer, TrigramTokenizer,
+    BigramTokenizer, Cased, CharNgramTokenizer, Identity, JsonField, SiteOperatorUrlTokenizer,
+    Tokenizer, TrigramTokenizer,
 };
 
 pub const FLOAT_SCALING: u64 = 1_000_000_000;
@@ -26,12 +27,22 @@ pub const FLOAT_SCALING: u64 = 1_000_000_000;
 pub enum TextField {
     Title,
     CleanBody,
+    /// clean body where the case of the tokens is preserved. Only used for case sensitive phrases.
+    CleanBodyCased,
     StemmedTitle,
     StemmedCleanBody,
     AllBody,
     Url,
     UrlNoTokenizer,
     UrlForSiteOperator,
+    /// character n-grams of the url so `inurl:` can match substrings within tokens.
+    /// Only set if the index was built with url n-grams enabled.
+    UrlCharNgrams,
+    /// path of the url where the case is preserved. Only used for `inurl:` prefixes.
+    UrlPath,
+    /// locality sensitive hashing bands of the minhash of the clean body.
+    /// Only used to find candidates for similar documents.
+    MinHashBands,
     SiteWithout,
     Domain,
     SiteNoTokenizer,
@@ -74,12 +85,16 @@ impl TextField {
         match self {
             TextField::Title => 1,
             TextField::CleanBody => 1,
+            TextField::CleanBodyCased => 1,
             TextField::StemmedTitle => 1,
             TextField::StemmedCleanBody => 1,
             TextField::AllBody => 1,
             TextField::Url => 1,
             TextField::UrlNoTokenizer => 1,
             TextField::UrlForSiteOperator => 1,
+            TextField::UrlCharNgrams => 1,
+            TextField::UrlPath => 1,
+            TextField::MinHashBands => 1,
             TextField::SiteWithout => 1,
             TextField::Domain => 1,
             TextField::SiteNoTokenizer => 1,
@@ -110,12 +125,16 @@ impl TextField {
         match self {
             TextField::Title => TextField::Title,
             TextField::CleanBody => TextField::CleanBody,
+            TextField::CleanBodyCased => TextField::CleanBodyCased,
             TextField::StemmedTitle => TextField::StemmedTitle,
             TextField::StemmedCleanBody => TextField::StemmedCleanBody,
             TextField::AllBody => TextField::AllBody,
             TextField::Url => TextField::Url,
             TextField::UrlNoTokenizer => TextField::UrlNoTokenizer,
             TextField::UrlForSiteOperator => TextField::UrlForSiteOperator,
+            TextField::UrlCharNgrams => TextField::UrlCharNgrams,
+            TextField::UrlPath => TextField::UrlPath,
+            TextField::MinHashBands => TextField::MinHashBands,
             TextField::SiteWithout => TextField::SiteWithout,
             TextField::Domain => TextField::Domain,
             TextField::SiteNoTokenizer => TextField::SiteNoTokenizer,
@@ -158,12 +177,16 @@ impl TextField {
         match self {
             TextField::Title => Tokenizer::default(),
             TextField::CleanBody => Tokenizer::default(),
+            TextField::CleanBodyCased => Tokenizer::Cased(Cased::default()),
             TextField::StemmedTitle => Tokenizer::new_stemmed(),
             TextField::StemmedCleanBody => Tokenizer::new_stemmed(),
             TextField::AllBody => Tokenizer::default(),
             TextField::Url => Tokenizer::default(),
             TextField::UrlNoTokenizer => Tokenizer::Identity(Identity {}),
             TextField::UrlForSiteOperator => Tokenizer::SiteOperator(SiteOperatorUrlTokenizer),
+            TextField::UrlCharNgrams => Tokenizer::CharNgram(CharNgramTokenizer),
+            TextField::UrlPath => Tokenizer::Identity(Identity {}),
+            TextField::MinHashBands => Tokenizer::Identity(Identity {}),
             TextField::SiteWithout => Tokenizer::default(),
             TextField::Domain => Tokenizer::default(),
             TextField::SiteNoTokenizer => Tokenizer::Identity(Identity {}),
@@ -202,12 +225,16 @@ impl TextField {
         match self {
             TextField::Title => true,
             TextField::CleanBody => true,
+            TextField::CleanBodyCased => true,
             TextField::StemmedTitle => false,
             TextField::StemmedCleanBody => false,
             TextField::AllBody => false,
             TextField::Url => true,
             TextField::UrlNoTokenizer => false,
             TextField::UrlForSiteOperator => true,
+            TextField::UrlCharNgrams => true,
+            TextField::UrlPath => false,
+            TextField::MinHashBands => false,
             TextField::SiteWithout => true,
             TextField::Domain => true,
             TextField::SiteNoTokenizer => false,
@@ -234,13 +261,59 @@ impl TextField {
         }
     }
 
+    /// Whether or not compounds of adjacent query terms
+    /// (e.g. "new york" -> "newyork") should be searched in the field.
+    pub fn is_compound_eligible(&self) -> bool {
+        match self {
+            TextField::Title => true,
+            TextField::CleanBody => false,
+            TextField::CleanBodyCased => false,
+            TextField::StemmedTitle => false,
+            TextField::StemmedCleanBody => false,
+            TextField::AllBody => true,
+            TextField::Url => true,
+            TextField::UrlNoTokenizer => false,
+            TextField::UrlForSiteOperator => false,
+            TextField::UrlCharNgrams => false,
+            TextField::UrlPath => false,
+            TextField::MinHashBands => false,
+            TextField::SiteWithout => false,
+            TextField::Domain => false,
+            TextField::SiteNoTokenizer => false,
+            TextField::SiteIfHomepageNoTokenizer => false,
+            TextField::DomainNoTokenizer => false,
+            TextField::DomainNameNoTokenizer => false,
+            TextField::DomainIfHomepage => false,
+            TextField::DomainNameIfHomepageNoTokenizer => false,
+            TextField::DomainIfHomepageNoTokenizer => false,
+            TextField::TitleIfHomepage => false,
+            TextField::BacklinkText => false,
+            TextField::Description => false,
+            TextField::DmozDescription => false,
+            TextField::SchemaOrgJson => false,
+            TextField::FlattenedSchemaOrgJson => false,
+            TextField::CleanBodyBigrams => false,
+            TextField::TitleBigrams => false,
+            TextField::CleanBodyTrigrams => false,
+            TextField::TitleTrigrams => false,
+            TextField::MicroformatTags => false,
+            TextField::SafetyClassification => false,
+            TextField::InsertionTimestamp => false,
+            TextField::RecipeFirstIngredientTagId => false,
+        }
+    }
+
     pub fn name(&self) -> &str {
         match self {
             TextField::Title => "title",
             TextField::CleanBody => "body",
+            TextField::CleanBodyCased => "body_cased",
             TextField::Url => "url",
             TextField::UrlNoTokenizer => "url_no_tokenizer",
             TextField::UrlForSiteOperator => "url_for_site_operator",
+            TextField::UrlCharNgrams => "url_char_ngrams",
+            TextField::UrlPath => "url_path",
+            TextField::MinHashBands => "min_hash_bands",
             TextField::SiteWithout => "site",
             TextField::Domain => "domain",
             TextField::SiteNoTokenizer => "site_no_tokenizer",
@@ -307,8 +380,13 @@ pub enum FastField {
     NumFlattenedSchemaTokens,
     NumPathAndQuerySlashes,
     NumPathAndQueryDigits,
+    NumPathAndQueryChars,
     LikelyHasAds,
     LikelyHasPaywall,
+    MinHash1,
+    MinHash2,
+    MinHash3,
+    MinHash4,
     LinkDensity,
 }
 
@@ -349,8 +427,13 @@ impl FastField {
             FastField::SimHash => "sim_hash",
             FastField::NumPathAndQuerySlashes => "num_path_and_query_slashes",
             FastField::NumPathAndQueryDigits => "num_path_and_query_digits",
+            FastField::NumPathAndQueryChars => "num_path_and_query_chars",
             FastField::LikelyHasAds => "likely_has_ads",
             FastField::LikelyHasPaywall => "likely_has_paywall",
+            FastField::MinHash1 => "min_hash1",
+            FastField::MinHash2 => "min_hash2",
+            FastField::MinHash3 => "min_hash3",
+            FastField::MinHash4 => "min_hash4",
             FastField::LinkDensity => "link_density",
         }
     }
@@ -368,7 +451,7 @@ pub enum Field {
     Text(TextField),
 }
 
-static ALL_FIELDS: [Field; 66] = [
+static ALL_FIELDS: [Field; 75] = [
     Field::Text(TextField::Title),
     Field::Text(TextField::CleanBody),
     Field::Text(TextField::StemmedTitle),
@@ -399,6 +482,10 @@ static ALL_FIELDS: [Field; 66] = [
     Field::Text(TextField::MicroformatTags),
     Field::Text(TextField::SafetyClassification),
     Field::Text(TextField::InsertionTimestamp),
+    Field::Text(TextField::CleanBodyCased),
+    Field::Text(TextField::UrlCharNgrams),
+    Field::Text(TextField::UrlPath),
+    Field::Text(TextField::MinHashBands),
     // FAST FIELDS
     Field::Fast(FastField::IsHomepage),
     Field::Fast(FastField::HostCentrality),
@@ -434,8 +521,13 @@ static ALL_FIELDS: [Field; 66] = [
     Field::Fast(FastField::SimHash),
     Field::Fast(FastField::NumPathAndQuerySlashes),
     Field::Fast(FastField::NumPathAndQueryDigits),
+    Field::Fast(FastField::NumPathAndQueryChars),
     Field::Fast(FastField::LikelyHasAds),
     Field::Fast(FastField::LikelyHasPaywall),
+    Field::Fast(FastField::MinHash1),
+    Field::Fast(FastField::MinHash2),
+    Field::Fast(FastField::MinHash3),
+    Field::Fast(FastField::MinHash4),
 ];
 
 impl Field {
@@ -472,6 +564,9 @@ impl Field {
                 IndexingOption::Text(self.default_text_options().set_stored())
             }
             Field::Text(TextField::CleanBody) => IndexingOption::Text(self.default_text_options()),
+            Field::Text(TextField::CleanBodyCased) => {
+                IndexingOption::Text(self.default_text_options())
+            }
             Field::Text(TextField::Url) => {
                 IndexingOption::Text(self.default_text_options().set_stored())
             }
@@ -481,6 +576,13 @@ impl Field {
             Field::Text(TextField::UrlForSiteOperator) => {
                 IndexingOption::Text(self.default_text_options())
             }
+            Field::Text(TextField::UrlCharNgrams) => {
+                IndexingOption::Text(self.default_text_options())
+            }
+            Field::Text(TextField::UrlPath) => IndexingOption::Text(self.default_text_options()),
+            Field::Text(TextField::MinHashBands) => {
+                IndexingOption::Text(self.default_text_options())
+            }
             Field::Text(TextField::SiteWithout) => {
                 IndexingOption::Text(self.default_text_options())
             }
@@ -678,6 +780,12 @@ impl Field {
                     .set_indexed()
                     .set_stored(),
             ),
+            Field::Fast(FastField::NumPathAndQueryChars) => IndexingOption::Integer(
+                NumericOptions::default()
+                    .set_fast()
+                    .set_indexed()
+                    .set_stored(),
+            ),
             Field::Fast(FastField::LikelyHasAds) => IndexingOption::Integer(
                 NumericOptions::default()
                     .set_fast()
@@ -690,6 +798,12 @@ impl Field {
                     .set_indexed()
                     .set_stored(),
             ),
+            Field::Fast(FastField::MinHash1)
+            | Field::Fast(FastField::MinHash2)
+            | Field::Fast(FastField::MinHash3)
+            | Field::Fast(FastField::MinHash4) => {
+                IndexingOption::Integer(NumericOptions::default().set_fast())
+            }
             Field::Fast(FastField::LinkDensity) => {
                 IndexingOption::Integer(NumericOptions::default().set_fast().set_stored())
             }
@@ -717,6 +831,9 @@ impl Field {
                 | Field::Text(TextField::SafetyClassification)
                 | Field::Text(TextField::FlattenedSchemaOrgJson)
                 | Field::Text(TextField::UrlForSiteOperator)
+                | Field::Text(TextField::UrlCharNgrams) // only used for `inurl:`
+                | Field::Text(TextField::UrlPath) // only used for `inurl:`
+                | Field::Text(TextField::MinHashBands)
                 | Field::Text(TextField::Description)
                 | Field::Text(TextField::DmozDescription)
                 | Field::Text(TextField::SiteIfHomepageNoTokenizer)
@@ -728,9 +845,36 @@ impl Field {
                 | Field::Text(TextField::Domain) // will match url
                 | Field::Text(TextField::InsertionTimestamp)
                 | Field::Text(TextField::RecipeFirstIngredientTagId)
+                | Field::Text(TextField::CleanBodyCased) // only used for case sensitive phrases
         ) && !self.is_fast()
     }
 
+    pub fn is_compound_eligible(&self) -> bool {
+        match self {
+            Field::Fast(_) => false,
+            Field::Text(text) => text.is_compound_eligible(),
+        }
+    }
+
+    /// Whether phrases should match the field as a conjunction of their tokens
+    /// since the field has no positions. Fields derived from a field with positions
+    /// (stemmed, n-gram and all body fields) are excluded as phrases already get
+    /// exact matches through the original field.
+    pub fn has_phrase_fallback(&self) -> bool {
+        self.is_searchable()
+            && !self.has_pos()
+            && !matches!(
+                self,
+                Field::Text(TextField::StemmedTitle)
+                    | Field::Text(TextField::StemmedCleanBody)
+                    | Field::Text(TextField::AllBody)
+                    | Field::Text(TextField::CleanBodyBigrams)
+                    | Field::Text(TextField::TitleBigrams)
+                    | Field::Text(TextField::CleanBodyTrigrams)
+                    | Field::Text(TextField::TitleTrigrams)
+            )
+    }
+
     pub fn is_fast(&self) -> bool {
         matches!(self, Field::Fast(_))
     }
@@ -764,6 +908,18 @@ pub fn create_schema() -> tantivy::schema::Schema {
     builder.build()
 }
 
+/// Fingerprint of the schema created by [`create_schema`]. Field ids are positional,
+/// so any change to the fields or their order results in a new version.
+pub fn schema_version() -> String {
+    schema_fingerprint(&create_schema())
+}
+
+/// Fingerprint of the fields of `schema`, in order.
+pub fn schema_fingerprint(schema: &tantivy::schema::Schema) -> String {
+    let schema = serde_json::to_string(schema).expect("schema should serialize");
+    format!("{:x}", md5::compute(schema))
+}
+
 pub enum IndexingOption {
     Text(tantivy::schema::TextOptions),
     Integer(tantivy::schema::NumericOptions),
@@ -811,8 +967,13 @@ impl FastField {
             FastField::SimHash => DataType::U64,
             FastField::NumPathAndQuerySlashes => DataType::U64,
             FastField::NumPathAndQueryDigits => DataType::U64,
+            FastField::NumPathAndQueryChars => DataType::U64,
             FastField::LikelyHasAds => DataType::U64,
             FastField::LikelyHasPaywall => DataType::U64,
+            FastField::MinHash1 => DataType::U64,
+            FastField::MinHash2 => DataType::U64,
+            FastField::MinHash3 => DataType::U64,
+            FastField::MinHash4 => DataType::U64,
             FastField::LinkDensity => DataType::U64,
         }
     }
@@ -25,8 +25,8 @@ use url::Url;
 use utoipa::ToSchema;
 
 use crate::{
-    inverted_index::RetrievedWebpage,
-    ranking::{Signal, SignalScore},
+    inverted_index::{PhraseMatch, RetrievedWebpage},
+    ranking::{explain::Explanation, Signal, SignalScore},
     snippet::TextSnippet,
     web_spell::{self, CorrectionTerm},
     webpage::url_ext::UrlExt,
@@ -165,19 +165,59 @@ fn generate_snippet(webpage: &RetrievedWebpage) -> Snippet {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+/// A single search result as returned by the searchers. Empty fields, like the ones
+/// that were not selected with [`crate::searcher::SearchQuery::fields`], are left
+/// out when serialized.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct DisplayedWebpage {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub title: String,
     pub url: String,
+    /// Normalized host of the url (e.g. `example.com` for `https://www.example.com`).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub site: String,
+    /// Root domain of the url (e.g. `example.com` for `https://blog.example.com`).
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub domain: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub pretty_url: String,
-    pub snippet: Snippet,
+    /// Only missing if not selected with [`crate::searcher::SearchQuery::fields`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<Snippet>,
+    /// The ranking signals that contributed to the score of the result.
+    /// Always set by the local searchers. The api searcher only sets them if
+    /// [`crate::searcher::SearchQuery::return_ranking_signals`] is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub ranking_signals: Option<HashMap<Signal, SignalScore>>,
+    /// Final score from the ranking pipeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub score: Option<f64>,
+    /// Only set for the result matching [`crate::searcher::SearchQuery::explain_url`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub explanation: Option<Explanation>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub likely_has_ads: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub likely_has_paywall: bool,
+    /// The `site:` operator from the query that this result matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matched_site: Option<String>,
+    /// Score relative to the top result, between 0 and 1.
+    /// Only set if requested with [`crate::searcher::SearchQuery::normalize_scores`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalized_score: Option<f64>,
+    /// Only set if requested with [`crate::searcher::ReturnField::HostCentrality`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host_centrality: Option<f64>,
+    /// Number of results that were collapsed into this one.
+    /// Only set if requested with [`crate::searcher::SearchQuery::collapse_field`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_count: Option<usize>,
+    /// Where the phrases of the query matched the title and body. Can be
+    /// used to highlight the exact matches.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub phrase_matches: Vec<PhraseMatch>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
@@ -204,11 +244,17 @@ impl From<RetrievedWebpage> for DisplayedWebpage {
             url: webpage.url,
             pretty_url,
             domain,
-            snippet,
+            snippet: Some(snippet),
             ranking_signals: None,
             score: None,
+            explanation: None,
             likely_has_ads: webpage.likely_has_ads,
             likely_has_paywall: webpage.likely_has_paywall,
+            matched_site: None,
+            normalized_score: None,
+            host_centrality: None,
+            group_count: None,
+            phrase_matches: webpage.phrase_matches,
         }
     }
 }
@@ -29,6 +29,7 @@ use crate::collector::Doc;
 use crate::config::{ApiConfig, CollectorConfig};
 use crate::image_store::Image;
 use crate::inverted_index::RetrievedWebpage;
+use crate::ranking::explain::Explanation;
 use crate::ranking::models::cross_encoder::CrossEncoderModel;
 use crate::ranking::pipeline::{AsRankingWebsite, RankingWebsite, RetrievedWebpageRanking};
 use crate::ranking::ALL_SIGNALS;
@@ -45,7 +46,10 @@ use crate::{query, Result};
 use self::sidebar::SidebarManager;
 use self::widget::WidgetManager;
 
-use super::{distributed, live, SearchQuery, SearchResult, WebsitesResult};
+use super::{
+    add_matched_sites, distributed, live, merge_group_counts, merge_host_facets, select_fields,
+    ResultPage, ReturnField, SearchQuery, SearchResult, WebsitesResult,
+};
 
 #[derive(Clone)]
 pub enum ScoredWebsitePointer {
@@ -78,6 +82,7 @@ pub fn combine_results(
     let mut collector = BucketCollector::new(pipeline.collector_top_n(), collector_config);
 
     let mut has_more = false;
+    let mut num_candidates = 0;
     for result in initial_results {
         if result.local_result.has_more {
             has_more = true;
@@ -92,6 +97,7 @@ pub fn combine_results(
             let pointer = ScoredWebsitePointer::Normal(pointer);
 
             collector.insert(pointer);
+            num_candidates += 1;
         }
     }
 
@@ -109,6 +115,7 @@ pub fn combine_results(
             let pointer = ScoredWebsitePointer::Live(pointer);
 
             collector.insert(pointer);
+            num_candidates += 1;
         }
     }
 
@@ -120,8 +127,78 @@ pub fn combine_results(
 
     let res = pipeline.apply(top_websites);
 
+    if num_candidates != res.len() {
+        has_more = true;
+    }
+
     (res, has_more)
 }
+/// Correct the spelling of the simple terms and phrases in `query` using `correct`.
+/// The values of field operators (like `site:`) are left untouched as hosts and
+/// urls are not dictionary words.
+fn correct_query<F>(query: &str, correct: F) -> Option<crate::web_spell::Correction>
+where
+    F: FnOnce(&str) -> Option<crate::web_spell::Correction>,
+{
+    use crate::web_spell::{Correction, CorrectionTerm};
+    use query::parser::Term;
+
+    let query = query.to_lowercase();
+    let terms = query::parser::parse_terms(&query);
+
+    let text = terms
+        .iter()
+        .filter_map(|term| match term {
+            Term::Simple(t) => Some(t.as_str()),
+            Term::Phrase(p) => Some(p.as_str()),
+            _ => None,
+        })
+        .join(" ");
+
+    let corrections = correct(&text)?;
+
+    let correction_map: HashMap<String, String> = corrections
+        .terms
+        .into_iter()
+        .filter_map(|t| match t {
+            CorrectionTerm::Corrected { orig, correction } => Some((orig, correction)),
+            CorrectionTerm::NotCorrected(_) => None,
+        })
+        .collect();
+
+    let mut correction = Correction::empty(query);
+
+    for term in terms {
+        match &term {
+            Term::Simple(t) => match correction_map.get(t.as_str()) {
+                Some(term_correction) => correction.push(CorrectionTerm::Corrected {
+                    orig: t.as_str().to_string(),
+                    correction: term_correction.to_string(),
+                }),
+                None => correction.push(CorrectionTerm::NotCorrected(t.as_str().to_string())),
+            },
+            Term::Phrase(p) => {
+                let corrected = p
+                    .split_whitespace()
+                    .map(|word| correction_map.get(word).map(|c| c.as_str()).unwrap_or(word))
+                    .join(" ");
+
+                if corrected.split_whitespace().eq(p.split_whitespace()) {
+                    correction.push(CorrectionTerm::NotCorrected(term.to_string()));
+                } else {
+                    correction.push(CorrectionTerm::Corrected {
+                        orig: term.to_string(),
+                        correction: Term::Phrase(corrected).to_string(),
+                    });
+                }
+            }
+            _ => correction.push(CorrectionTerm::NotCorrected(term.to_string())),
+        }
+    }
+
+    Some(correction)
+}
+
 pub fn add_ranking_signals(websites: &mut [DisplayedWebpage], pointers: &[ScoredWebsitePointer]) {
     for (website, pointer) in websites.iter_mut().zip(pointers.iter()) {
         let mut signals = HashMap::with_capacity(ALL_SIGNALS.len());
@@ -185,16 +262,16 @@ where
     }
 
     async fn check_bangs(&self, query: &SearchQuery) -> Result<Option<BangHit>> {
-        let parsed_terms = query::parser::parse(&query.query);
+        let parsed_terms = query::parser::parse_terms(&query.query);
 
-        if parsed_terms.iter().any(|term| match term.as_ref() {
-            query::parser::Term::PossibleBang(t) => t.is_empty(),
+        if parsed_terms.iter().any(|term| match term {
+            query::parser::Term::PossibleBang { bang, .. } => bang.is_empty(),
             _ => false,
         }) {
             let q: String = intersperse(
                 parsed_terms
                     .iter()
-                    .filter(|term| !matches!(term.as_ref(), query::parser::Term::PossibleBang(_)))
+                    .filter(|term| !matches!(term, query::parser::Term::PossibleBang { .. }))
                     .map(|term| term.to_string()),
                 " ".to_string(),
             )
@@ -231,60 +308,12 @@ where
     }
 
     pub fn spell_check(&self, query: &str) -> Option<HighlightedSpellCorrection> {
-        let query = query.to_lowercase();
-
-        let terms = query::parser::parse(&query);
-
-        let simple_query = terms
-            .clone()
-            .into_iter()
-            .filter_map(|term| match *term {
-                query::parser::Term::Simple(t) => Some(String::from(t)),
-                _ => None,
-            })
-            .join(" ");
-
-        let corrections = self
-            .spell_checker
-            .as_ref()
-            .and_then(|s| s.correct(&simple_query, &whatlang::Lang::Eng))?;
-
-        let correction_map: HashMap<String, String> = corrections
-            .terms
-            .into_iter()
-            .filter_map(|t| match t {
-                crate::web_spell::CorrectionTerm::Corrected { orig, correction } => {
-                    Some((orig, correction))
-                }
-                crate::web_spell::CorrectionTerm::NotCorrected(_) => None,
-            })
-            .collect();
-
-        let mut correction = crate::web_spell::Correction::empty(query);
-
-        for term in terms {
-            match *term {
-                query::parser::Term::Simple(t) => {
-                    if let Some(term_correction) = correction_map.get(t.as_str()) {
-                        correction.push(crate::web_spell::CorrectionTerm::Corrected {
-                            orig: String::from(t),
-                            correction: term_correction.to_string(),
-                        });
-                    } else {
-                        correction.push(crate::web_spell::CorrectionTerm::NotCorrected(
-                            String::from(t),
-                        ));
-                    }
-                }
-                _ => {
-                    correction.push(crate::web_spell::CorrectionTerm::NotCorrected(
-                        term.to_string(),
-                    ));
-                }
-            }
-        }
+        let spell_checker = self.spell_checker.as_ref()?;
 
-        Some(HighlightedSpellCorrection::from(correction))
+        correct_query(query, |text| {
+            spell_checker.correct(text, &whatlang::Lang::Eng)
+        })
+        .map(HighlightedSpellCorrection::from)
     }
 
     async fn retrieve_webpages(
@@ -359,7 +388,7 @@ where
             return Err(distributed::Error::EmptyQuery.into());
         }
 
-        let mut search_query = query.clone();
+        let mut search_query = query.ranking_query();
         let top_n = search_query.num_results;
 
         // This pipeline should be created before the first search is performed
@@ -381,15 +410,45 @@ where
             .map(|result| result.local_result.num_websites)
             .sum();
 
-        let (top_websites, has_more_results) = combine_results(
+        let host_facets = query.host_facets.map(|top_n| {
+            let shard_facets = initial_results
+                .iter()
+                .filter_map(|result| result.local_result.host_facets.as_deref());
+
+            let live_facets = live_results
+                .iter()
+                .flatten()
+                .filter_map(|result| result.local_result.host_facets.as_deref());
+
+            merge_host_facets(shard_facets.chain(live_facets), top_n)
+        });
+
+        let group_counts = {
+            let shard_counts = initial_results
+                .iter()
+                .filter_map(|result| result.local_result.group_counts.as_deref());
+
+            let live_counts = live_results
+                .iter()
+                .flatten()
+                .filter_map(|result| result.local_result.group_counts.as_deref());
+
+            merge_group_counts(shard_counts.chain(live_counts))
+        };
+
+        let (top_websites, has_more) = combine_results(
             self.collector_config.clone(),
             initial_results,
             live_results.unwrap_or_default(),
             recall_pipeline,
         );
 
+        let page = ResultPage::new(top_websites, query, !has_more, &group_counts);
+        let top_websites = &page.websites;
+
+        // snippets are retrieved even if they are not selected, as the reranker scores them
         let retrieved_webpages = self
-            .retrieve_webpages(&search_query.query, &top_websites)
+            .retrieve_webpages(&search_query.query, top_websites)
             .await;
 
         let mut search_query = SearchQuery {
@@ -418,21 +477,43 @@ where
             return Err(distributed::Error::SearchFailed.into());
         }
 
-        if query.return_ranking_signals {
-            add_ranking_signals(&mut retrieved_webpages, &top_websites);
+        // the host centrality is read from the ranking signals
+        let wants_host_centrality = query
+            .fields
+            .as_ref()
+            .map(|fields| fields.contains(&ReturnField::HostCentrality))
+            .unwrap_or(false);
+
+        if query.return_ranking_signals || wants_host_centrality {
+            add_ranking_signals(&mut retrieved_webpages, top_websites);
         }
 
+        let explain_url = query.normalized_explain_url();
+
         for (website, pointer) in retrieved_webpages.iter_mut().zip(top_websites.iter()) {
             website.score = Some(pointer.score());
+
+            if explain_url.as_deref() == Some(website.url.as_str()) {
+                website.explanation = Some(Explanation::from(pointer.as_ranking()));
+            }
+        }
+
+        add_matched_sites(&mut retrieved_webpages, &query.query);
+        page.annotate(&mut retrieved_webpages);
+
+        if let Some(fields) = &query.fields {
+            select_fields(&mut retrieved_webpages, fields);
         }
 
         let search_duration_ms = start.elapsed().as_millis();
 
         Ok(WebsitesResult {
-            num_hits: num_docs,
+            num_hits: num_docs.map(|num_docs| page.num_results.unwrap_or(num_docs)),
             webpages: retrieved_webpages,
             search_duration_ms,
-            has_more_results,
+            has_more_results: page.has_more,
+            host_facets,
+            empty_query: false,
         })
     }
 
@@ -459,3 +540,66 @@ where
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::web_spell::{Correction, CorrectionTerm};
+
+    use super::*;
+
+    fn fake_correct(text: &str) -> Option<Correction> {
+        let mut correction = Correction::empty(text.to_string());
+
+        for word in text.split_whitespace() {
+            match word {
+                "restaraunt" => correction.push(CorrectionTerm::Corrected {
+                    orig: word.to_string(),
+                    correction: "restaurant".to_string(),
+                }),
+                "exmaple" | "exmaple.com" => correction.push(CorrectionTerm::Corrected {
+                    orig: word.to_string(),
+                    correction: "example".to_string(),
+                }),
+                _ => correction.push(CorrectionTerm::NotCorrected(word.to_string())),
+            }
+        }
+
+        Some(correction)
+    }
+
+    #[test]
+    fn spell_correction_ignores_field_operators() {
+        let mut checked = String::new();
+
+        let correction = correct_query("site:exmaple.com restaraunt", |text| {
+            checked = text.to_string();
+            fake_correct(text)
+        })
+        .unwrap();
+
+        assert_eq!(checked, "restaraunt");
+        assert_eq!(
+            correction.terms,
+            vec![
+                CorrectionTerm::NotCorrected("site:exmaple.com".to_string()),
+                CorrectionTerm::Corrected {
+                    orig: "restaraunt".to_string(),
+                    correction: "restaurant".to_string(),
+                },
+            ]
+        );
+
+        let correction = correct_query("\"best restaraunt\" inurl:exmaple", fake_correct).unwrap();
+
+        assert_eq!(
+            correction.terms,
+            vec![
+                CorrectionTerm::Corrected {
+                    orig: "\"best restaraunt\"".to_string(),
+                    correction: "\"best restaurant\"".to_string(),
+                },
+                CorrectionTerm::NotCorrected("inurl:exmaple".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,303 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Builder for [`SearchQuery`] that checks the options are consistent.
+
+use optics::{HostRankings, Optic};
+use thiserror::Error;
+use url::Url;
+
+use crate::webpage::region::Region;
+
+use super::{ReturnField, SearchQuery};
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum InvalidSearchQuery {
+    #[error("Query cannot be empty")]
+    EmptyQuery,
+
+    #[error("Number of results must be at least 1")]
+    NoResults,
+
+    #[error("Page {page} with {num_results} results per page is out of bounds")]
+    OffsetOutOfBounds { page: usize, num_results: usize },
+
+    #[error("Minimum score {0} must be between 0 and 1")]
+    MinScoreOutOfRange(f32),
+
+    #[error("Explain url '{0}' is not a valid url")]
+    InvalidExplainUrl(String),
+
+    #[error("Number of host facets must be at least 1")]
+    NoHostFacets,
+
+    #[error("Results cannot be collapsed by {0:?}")]
+    InvalidCollapseField(ReturnField),
+}
+
+/// Fluent alternative to constructing a [`SearchQuery`] with a struct literal.
+pub struct SearchQueryBuilder {
+    query: SearchQuery,
+}
+
+impl SearchQueryBuilder {
+    pub fn new<S: Into<String>>(query: S) -> Self {
+        Self {
+            query: SearchQuery {
+                query: query.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn with_page(mut self, page: usize) -> Self {
+        self.query.page = page;
+        self
+    }
+
+    pub fn with_num_results(mut self, num_results: usize) -> Self {
+        self.query.num_results = num_results;
+        self
+    }
+
+    pub fn with_region(mut self, region: Region) -> Self {
+        self.query.selected_region = Some(region);
+        self
+    }
+
+    pub fn with_optic(mut self, optic: Optic) -> Self {
+        self.query.optic = Some(optic);
+        self
+    }
+
+    pub fn with_host_rankings(mut self, host_rankings: HostRankings) -> Self {
+        self.query.host_rankings = Some(host_rankings);
+        self
+    }
+
+    pub fn with_ranking_signals(mut self, return_ranking_signals: bool) -> Self {
+        self.query.return_ranking_signals = return_ranking_signals;
+        self
+    }
+
+    pub fn with_safe_search(mut self, safe_search: bool) -> Self {
+        self.query.safe_search = safe_search;
+        self
+    }
+
+    pub fn with_count_results(mut self, count_results: bool) -> Self {
+        self.query.count_results = count_results;
+        self
+    }
+
+    pub fn with_explain_url<S: Into<String>>(mut self, url: S) -> Self {
+        self.query.explain_url = Some(url.into());
+        self
+    }
+
+    pub fn with_host_facets(mut self, top_n: usize) -> Self {
+        self.query.host_facets = Some(top_n);
+        self
+    }
+
+    pub fn with_min_score(mut self, min_score: f32) -> Self {
+        self.query.min_score = Some(min_score);
+        self
+    }
+
+    pub fn with_normalize_scores(mut self, normalize_scores: bool) -> Self {
+        self.query.normalize_scores = normalize_scores;
+        self
+    }
+
+    pub fn with_fields(mut self, fields: Vec<ReturnField>) -> Self {
+        self.query.fields = Some(fields);
+        self
+    }
+
+    pub fn with_collapse_field(mut self, field: ReturnField) -> Self {
+        self.query.collapse_field = Some(field);
+        self
+    }
+
+    pub fn with_case_sensitive_phrases(mut self, case_sensitive_phrases: bool) -> Self {
+        self.query.case_sensitive_phrases = case_sensitive_phrases;
+        self
+    }
+
+    pub fn with_restrict_to_sites(mut self, sites: Vec<String>) -> Self {
+        self.query.restrict_to_sites = sites;
+        self
+    }
+
+    pub fn build(self) -> Result<SearchQuery, InvalidSearchQuery> {
+        let query = self.query;
+
+        if query.query.trim().is_empty() {
+            return Err(InvalidSearchQuery::EmptyQuery);
+        }
+
+        if query.num_results == 0 {
+            return Err(InvalidSearchQuery::NoResults);
+        }
+
+        // the ranking pipeline needs to collect `(page + 1) * num_results` results
+        if query
+            .page
+            .checked_add(1)
+            .and_then(|pages| pages.checked_mul(query.num_results))
+            .is_none()
+        {
+            return Err(InvalidSearchQuery::OffsetOutOfBounds {
+                page: query.page,
+                num_results: query.num_results,
+            });
+        }
+
+        // results are always ordered by score, as there is no option to sort them
+        // by anything else, so a `min_score` relative to the top result cannot conflict
+        // with the order. A sort option must be rejected here together with `min_score`.
+        if let Some(min_score) = query.min_score {
+            if !(0.0..=1.0).contains(&min_score) {
+                return Err(InvalidSearchQuery::MinScoreOutOfRange(min_score));
+            }
+        }
+
+        if let Some(url) = &query.explain_url {
+            if Url::parse(url).is_err() {
+                return Err(InvalidSearchQuery::InvalidExplainUrl(url.clone()));
+            }
+        }
+
+        if query.host_facets == Some(0) {
+            return Err(InvalidSearchQuery::NoHostFacets);
+        }
+
+        if let Some(field) = query.collapse_field {
+            if !field.can_collapse() {
+                return Err(InvalidSearchQuery::InvalidCollapseField(field));
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+impl SearchQuery {
+    pub fn builder<S: Into<String>>(query: S) -> SearchQueryBuilder {
+        SearchQueryBuilder::new(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_query() {
+        let query = SearchQuery::builder("rust programming")
+            .with_page(2)
+            .with_num_results(10)
+            .with_count_results(true)
+            .with_explain_url("https://www.rust-lang.org/")
+            .with_host_facets(5)
+            .with_min_score(0.5)
+            .build()
+            .unwrap();
+
+        assert_eq!(query.query, "rust programming");
+        assert_eq!(query.page, 2);
+        assert_eq!(query.num_results, 10);
+        assert!(query.count_results);
+        assert_eq!(
+            query.explain_url.as_deref(),
+            Some("https://www.rust-lang.org/")
+        );
+        assert_eq!(query.host_facets, Some(5));
+        assert_eq!(query.min_score, Some(0.5));
+
+        let default = SearchQuery::default();
+        assert_eq!(query.safe_search, default.safe_search);
+        assert_eq!(query.return_ranking_signals, default.return_ranking_signals);
+    }
+
+    #[test]
+    fn invalid_queries() {
+        assert_eq!(
+            SearchQuery::builder("  ").build().unwrap_err(),
+            InvalidSearchQuery::EmptyQuery
+        );
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_num_results(0)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::NoResults
+        );
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_page(usize::MAX)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::OffsetOutOfBounds {
+                page: usize::MAX,
+                num_results: crate::searcher::NUM_RESULTS_PER_PAGE,
+            }
+        );
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_min_score(1.5)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::MinScoreOutOfRange(1.5)
+        );
+
+        assert!(matches!(
+            SearchQuery::builder("test")
+                .with_min_score(f32::NAN)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::MinScoreOutOfRange(_)
+        ));
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_explain_url("not a url")
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::InvalidExplainUrl("not a url".to_string())
+        );
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_host_facets(0)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::NoHostFacets
+        );
+
+        assert_eq!(
+            SearchQuery::builder("test")
+                .with_collapse_field(ReturnField::Snippet)
+                .build()
+                .unwrap_err(),
+            InvalidSearchQuery::InvalidCollapseField(ReturnField::Snippet)
+        );
+    }
+}
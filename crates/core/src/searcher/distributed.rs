@@ -17,7 +17,7 @@
 use crate::{
     distributed::{
         cluster::Cluster,
-        member::Service,
+        member::{Service, ServiceKind},
         sonic::replication::{
             AllShardsSelector, RandomReplicaSelector, RemoteClient, ReplicatedClient, Shard,
             ShardIdentifier, ShardedClient, SpecificShardSelector,
@@ -92,8 +92,8 @@ impl DistributedSearcher {
 
     async fn client(&self) -> ShardedClient<SearchService, ShardId> {
         let mut shards = HashMap::new();
-        for member in self.cluster.members().await {
-            if let Service::Searcher { host, shard } = member.service {
+        for service in self.cluster.services_of_kind(ServiceKind::Searcher).await {
+            if let Service::Searcher { host, shard } = service {
                 shards.entry(shard).or_insert_with(Vec::new).push(host);
             }
         }
@@ -112,10 +112,12 @@ impl DistributedSearcher {
 
     async fn entity_client(&self) -> ReplicatedClient<entity_search_server::SearchService> {
         let mut replicas = Vec::new();
-        for member in self.cluster.members().await {
-            if let Service::EntitySearcher { host } = member.service {
-                replicas.push(RemoteClient::new(host));
-            }
+        for service in self
+            .cluster
+            .services_of_kind(ServiceKind::EntitySearcher)
+            .await
+        {
+            replicas.push(RemoteClient::new(service.host()));
         }
 
         ReplicatedClient::new(replicas)
@@ -17,7 +17,7 @@
 use crate::{
     distributed::{
         cluster::Cluster,
-        member::Service,
+        member::{Service, ServiceKind},
         sonic::replication::{
             AllShardsSelector, RandomReplicaSelector, RemoteClient, ReplicatedClient, Shard,
             ShardIdentifier, ShardedClient, SpecificShardSelector,
@@ -63,8 +63,8 @@ impl LiveSearcher {
 
     async fn client(&self) -> ShardedClient<SearchService, SplitId> {
         let mut shards = HashMap::new();
-        for member in self.cluster.members().await {
-            if let Service::LiveIndex { host, split_id } = member.service {
+        for service in self.cluster.services_of_kind(ServiceKind::LiveIndex).await {
+            if let Service::LiveIndex { host, split_id } = service {
                 shards.entry(split_id).or_insert_with(Vec::new).push(host);
             }
         }
@@ -14,27 +14,35 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, RwLockReadGuard};
 
 use url::Url;
 
-use crate::config::{CollectorConfig, SnippetConfig};
+use crate::bangs::Bangs;
+use crate::config::{CollectorConfig, QueryConfig, ResultCacheConfig, SnippetConfig};
 use crate::index::Index;
 use crate::inverted_index::{InvertedIndex, RetrievedWebpage};
+use crate::prehashed::Prehashed;
+use crate::query::parser::parse_terms;
 use crate::query::Query;
+use crate::ranking::bm25::Bm25Stats;
+use crate::ranking::explain::Explanation;
 use crate::ranking::inbound_similarity::InboundSimilarity;
 use crate::ranking::models::lambdamart::LambdaMART;
 use crate::ranking::models::linear::LinearRegression;
-use crate::ranking::pipeline::{RankingPipeline, RankingWebsite};
+use crate::ranking::pipeline::{AsRankingWebsite, RankingPipeline, RankingWebsite};
 use crate::ranking::{query_centrality, Ranker, Signal, SignalAggregator, ALL_SIGNALS};
 use crate::search_ctx::Ctx;
 use crate::search_prettifier::DisplayedWebpage;
 use crate::webgraph::Node;
 use crate::{inverted_index, live_index, Error, Result};
 
-use super::WebsitesResult;
-use super::{InitialWebsiteResult, SearchQuery};
+use super::{add_matched_sites, merge_group_counts, select_fields, ResultPage, WebsitesResult};
+use super::{
+    HostFacet, InitialWebsiteResult, QueryLogger, ResultCache, ResultCacheStats, ReturnField,
+    SearchQuery, SearchResult,
+};
 
 pub trait SearchableIndex {
     type SearchGuard<'a>: SearchGuard<'a>
@@ -43,6 +51,7 @@ pub trait SearchableIndex {
 
     fn guard(&self) -> Self::SearchGuard<'_>;
     fn set_snippet_config(&mut self, config: SnippetConfig);
+    fn set_query_config(&mut self, config: QueryConfig);
 }
 
 pub trait SearchGuard<'a> {
@@ -62,6 +71,10 @@ impl SearchableIndex for Index {
     fn set_snippet_config(&mut self, config: SnippetConfig) {
         self.inverted_index.set_snippet_config(config);
     }
+
+    fn set_query_config(&mut self, config: QueryConfig) {
+        self.inverted_index.set_query_config(config);
+    }
 }
 
 pub struct NormalIndexSearchGuard<'a> {
@@ -86,6 +99,10 @@ impl SearchableIndex for Arc<live_index::Index> {
     fn set_snippet_config(&mut self, config: SnippetConfig) {
         self.write().inverted_index.set_snippet_config(config);
     }
+
+    fn set_query_config(&mut self, config: QueryConfig) {
+        self.write().inverted_index.set_query_config(config);
+    }
 }
 
 pub struct LiveIndexSearchGuard<'a> {
@@ -104,6 +121,9 @@ pub struct LocalSearcher<I: SearchableIndex> {
     linear_regression: Option<Arc<LinearRegression>>,
     lambda_model: Option<Arc<LambdaMART>>,
     collector_config: CollectorConfig,
+    query_logger: Option<QueryLogger>,
+    bangs: Option<Bangs>,
+    result_cache: Option<ResultCache>,
 }
 
 impl<I> From<I> for LocalSearcher<I>
@@ -119,6 +139,8 @@ struct InvertedIndexResult {
     webpages: Vec<RankingWebsite>,
     num_hits: Option<usize>,
     has_more: bool,
+    host_facets: Option<Vec<HostFacet>>,
+    group_counts: Option<Vec<(Prehashed, u64)>>,
 }
 
 impl<I> LocalSearcher<I>
@@ -132,9 +154,32 @@ where
             linear_regression: None,
             lambda_model: None,
             collector_config: CollectorConfig::default(),
+            query_logger: None,
+            bangs: None,
+            result_cache: None,
         }
     }
 
+    /// Log the queries and returned urls of [`LocalSearcher::search`].
+    pub fn set_query_logger(&mut self, logger: QueryLogger) {
+        self.query_logger = Some(logger);
+    }
+
+    /// Bangs used to redirect queries in [`LocalSearcher::search_with_bangs`].
+    pub fn set_bangs(&mut self, bangs: Bangs) {
+        self.bangs = Some(bangs);
+    }
+
+    /// Cache the results of [`LocalSearcher::search`] for repeated identical queries.
+    /// The cache is cleared when the index has new commits.
+    pub fn set_result_cache(&mut self, config: ResultCacheConfig) {
+        self.result_cache = Some(ResultCache::new(&config));
+    }
+
+    pub fn result_cache_stats(&self) -> Option<ResultCacheStats> {
+        self.result_cache.as_ref().map(|cache| cache.stats())
+    }
+
     pub fn set_inbound_similarity(&mut self, inbound: InboundSimilarity) {
         self.inbound_similarity = Some(inbound);
     }
@@ -155,6 +200,10 @@ where
         self.index.set_snippet_config(config);
     }
 
+    pub fn set_query_config(&mut self, config: QueryConfig) {
+        self.index.set_query_config(config);
+    }
+
     fn parse_query<'a, G: SearchGuard<'a>>(
         &'a self,
         ctx: &Ctx,
@@ -224,6 +273,7 @@ where
         guard: &G,
         query: &SearchQuery,
         de_rank_similar: bool,
+        bm25_stats: Option<Arc<Bm25Stats>>,
     ) -> Result<InvertedIndexResult> {
         let mut query = query.clone();
         let pipeline: RankingPipeline<RankingWebsite> = RankingPipeline::recall_stage(
@@ -236,6 +286,10 @@ where
 
         let mut aggregator = SignalAggregator::new(Some(&parsed_query));
 
+        if let Some(stats) = bm25_stats {
+            aggregator.set_bm25_stats(stats);
+        }
+
         if let Some(inbound_sim) = &self.inbound_similarity {
             let liked_hosts: Vec<_> = parsed_query
                 .host_rankings()
@@ -278,6 +332,15 @@ where
             ranker.collector(ctx.clone()),
         )?;
 
+        let host_facets = match query.host_facets {
+            Some(top_n) => Some(
+                guard
+                    .inverted_index()
+                    .host_facets(&parsed_query, ctx, top_n)?,
+            ),
+            None => None,
+        };
+
         let fastfield_reader = guard.inverted_index().fastfield_reader();
 
         let ranking_websites = guard.inverted_index().retrieve_ranking_websites(
@@ -292,10 +355,36 @@ where
 
         let ranking_websites = pipeline.apply(ranking_websites);
 
+        let mut group_counts = None;
+
+        if let Some(field) = query.collapse_by() {
+            if let Some(hash_fields) = field.collapse_hash_fields() {
+                let counts =
+                    guard
+                        .inverted_index()
+                        .group_counts(&parsed_query, ctx, hash_fields)?;
+
+                // only the groups of the returned websites can be shown
+                let groups: HashSet<_> = ranking_websites
+                    .iter()
+                    .filter_map(|website| field.collapse_key(&website.pointer.hashes))
+                    .collect();
+
+                group_counts = Some(
+                    counts
+                        .into_iter()
+                        .filter(|(group, _)| groups.contains(group))
+                        .collect(),
+                );
+            }
+        }
+
         Ok(InvertedIndexResult {
             webpages: ranking_websites,
             num_hits: res.num_websites,
             has_more,
+            host_facets,
+            group_counts,
         })
     }
 
@@ -303,28 +392,74 @@ where
         &self,
         query: &SearchQuery,
         de_rank_similar: bool,
+    ) -> Result<InitialWebsiteResult> {
+        self.search_initial_with_bm25_stats(query, de_rank_similar, None)
+    }
+
+    /// Corpus statistics of this index for the terms of `query`.
+    /// See [`LocalSearcher::search_initial_with_bm25_stats`].
+    pub fn bm25_stats(&self, query: &SearchQuery) -> Result<Bm25Stats> {
+        let guard = self.index.guard();
+        let ctx = guard.inverted_index().local_search_ctx();
+        let parsed_query = self.parse_query(&ctx, &guard, query)?;
+
+        SignalAggregator::new(Some(&parsed_query)).bm25_stats(&ctx.tv_searcher)
+    }
+
+    /// Like [`LocalSearcher::search_initial`], but the bm25 signals are computed from
+    /// `bm25_stats` if set. Searching several indexes with their merged statistics makes
+    /// the scores comparable across the indexes.
+    pub fn search_initial_with_bm25_stats(
+        &self,
+        query: &SearchQuery,
+        de_rank_similar: bool,
+        bm25_stats: Option<Arc<Bm25Stats>>,
     ) -> Result<InitialWebsiteResult> {
         let guard = self.index.guard();
         let ctx = guard.inverted_index().local_search_ctx();
         let inverted_index_result =
-            self.search_inverted_index(&ctx, &guard, query, de_rank_similar)?;
+            self.search_inverted_index(&ctx, &guard, query, de_rank_similar, bm25_stats)?;
 
         Ok(InitialWebsiteResult {
             websites: inverted_index_result.webpages,
             num_websites: inverted_index_result.num_hits,
             has_more: inverted_index_result.has_more,
+            host_facets: inverted_index_result.host_facets,
+            group_counts: inverted_index_result.group_counts,
         })
     }
 
+    /// Debug representation of the tantivy query that is executed for `query`.
+    /// Useful to see exactly which clauses the parsed terms were lowered into.
+    pub fn lowered_query(&self, query: &SearchQuery) -> Result<String> {
+        let guard = self.index.guard();
+        let ctx = guard.inverted_index().local_search_ctx();
+        let parsed_query = self.parse_query(&ctx, &guard, query)?;
+
+        Ok(format!("{:#?}", parsed_query.tantivy_query()))
+    }
+
     pub fn retrieve_websites(
         &self,
         websites: &[inverted_index::WebsitePointer],
         query: &str,
+    ) -> Result<Vec<inverted_index::RetrievedWebpage>> {
+        self.retrieve_selected_fields(websites, query, None)
+    }
+
+    /// Like [`LocalSearcher::retrieve_websites`], but the snippets are only
+    /// generated if they are in `fields`.
+    pub(crate) fn retrieve_selected_fields(
+        &self,
+        websites: &[inverted_index::WebsitePointer],
+        query: &str,
+        fields: Option<&[ReturnField]>,
     ) -> Result<Vec<inverted_index::RetrievedWebpage>> {
         let guard = self.index.guard();
         let ctx = guard.inverted_index().local_search_ctx();
         let query = SearchQuery {
             query: query.to_string(),
+            fields: fields.map(|fields| fields.to_vec()),
             ..Default::default()
         };
         let query = Query::parse(&ctx, &query, guard.inverted_index())?;
@@ -341,68 +476,173 @@ where
         use std::time::Instant;
 
         let start = Instant::now();
-        let mut search_query = query.clone();
 
-        let pipeline = {
-            use crate::ranking::models::cross_encoder::CrossEncoderModel;
-            match CrossEncoderModel::open("data/cross_encoder") {
-                Ok(model) => RankingPipeline::reranker::<CrossEncoderModel>(
-                    &mut search_query,
-                    Some(Arc::new(model)),
-                    None,
-                    self.collector_config.clone(),
-                    query.num_results,
-                )?,
-                Err(_) => RankingPipeline::reranker::<CrossEncoderModel>(
-                    &mut search_query,
-                    None,
-                    None,
-                    self.collector_config.clone(),
-                    query.num_results,
-                )?,
+        if query.is_empty() {
+            return Ok(WebsitesResult::empty_query(start.elapsed().as_millis()));
+        }
+
+        let result = match &self.result_cache {
+            Some(cache) => {
+                let key = ResultCache::key(query);
+                let generation = self.index_generation();
+
+                match cache.get(&key, generation) {
+                    Some(mut result) => {
+                        result.search_duration_ms = start.elapsed().as_millis();
+                        result
+                    }
+                    None => {
+                        let result = self.search_uncached(query)?;
+                        cache.insert(key, generation, result.clone());
+                        result
+                    }
+                }
             }
+            None => self.search_uncached(query)?,
         };
 
-        let search_result = self.search_initial(&search_query, true)?;
+        if let Some(logger) = &self.query_logger {
+            let urls: Vec<_> = result
+                .webpages
+                .iter()
+                .map(|webpage| webpage.url.clone())
+                .collect();
+
+            if let Err(err) = logger.log(&query.query, &urls) {
+                tracing::error!("failed to log query: {:?}", err);
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Changes every time the index is committed.
+    fn index_generation(&self) -> u64 {
+        self.index
+            .guard()
+            .inverted_index()
+            .tv_searcher()
+            .generation()
+            .generation_id()
+    }
+
+    fn search_uncached(&self, query: &SearchQuery) -> Result<WebsitesResult> {
+        use std::time::Instant;
+
+        let start = Instant::now();
+
+        let mut search_query = query.ranking_query();
+        let top_n = search_query.num_results;
+
+        let pipeline: RankingPipeline<RankingWebsite> =
+            reranking_pipeline(&mut search_query, self.collector_config.clone(), top_n)?;
+
+        let search_result = match self.search_initial(&search_query, true) {
+            Ok(result) => result,
+            // the query can still be empty after parsing, e.g. if all its terms are too long
+            Err(err) => match err.downcast_ref::<Error>() {
+                Some(Error::EmptyQuery) => {
+                    return Ok(WebsitesResult::empty_query(start.elapsed().as_millis()))
+                }
+                _ => return Err(err),
+            },
+        };
 
         let search_len = search_result.websites.len();
 
         let top_websites = pipeline.apply(search_result.websites);
+        let exhausted = search_len == top_websites.len();
 
-        let has_more_results = search_len != top_websites.len();
+        let group_counts = merge_group_counts(search_result.group_counts.as_deref().into_iter());
+        let page = ResultPage::new(top_websites, query, exhausted, &group_counts);
+        let top_websites = &page.websites;
 
         let pointers: Vec<_> = top_websites
             .iter()
             .map(|website| website.pointer.clone())
             .collect();
 
-        let retrieved_sites = self.retrieve_websites(&pointers, &search_query.query)?;
+        let retrieved_sites =
+            self.retrieve_selected_fields(&pointers, &search_query.query, query.fields.as_deref())?;
 
         let mut webpages: Vec<_> = retrieved_sites
             .into_iter()
             .map(DisplayedWebpage::from)
             .collect();
 
-        for (webpage, ranking) in webpages.iter_mut().zip(top_websites) {
-            let mut ranking_signals = HashMap::new();
-
-            for signal in ALL_SIGNALS {
-                if let Some(score) = ranking.signals.get(signal) {
-                    ranking_signals.insert(signal, *score);
-                }
-            }
+        add_rankings(&mut webpages, top_websites, &search_query);
+        add_matched_sites(&mut webpages, &query.query);
+        page.annotate(&mut webpages);
 
-            webpage.ranking_signals = Some(ranking_signals);
+        if let Some(fields) = &query.fields {
+            select_fields(&mut webpages, fields);
         }
 
         Ok(WebsitesResult {
-            num_hits: search_result.num_websites,
+            num_hits: search_result
+                .num_websites
+                .map(|num_websites| page.num_results.unwrap_or(num_websites)),
             webpages,
             search_duration_ms: start.elapsed().as_millis(),
-            has_more_results,
+            has_more_results: page.has_more,
+            host_facets: search_result.host_facets,
+            empty_query: false,
+        })
+    }
+
+    /// Like [`LocalSearcher::search`], but the stored documents of the results are only
+    /// retrieved as the iterator is consumed. Options that need all the results at once
+    /// ([`SearchQuery::min_score`], [`SearchQuery::collapse_field`] and
+    /// [`SearchQuery::normalize_scores`]) are ignored and the query is not logged.
+    pub fn search_iter(&self, query: &SearchQuery) -> Result<SearchIter<'_, I>> {
+        if query.is_empty() {
+            return Err(Error::EmptyQuery.into());
+        }
+
+        let mut search_query = query.clone();
+
+        let pipeline: RankingPipeline<RankingWebsite> = reranking_pipeline(
+            &mut search_query,
+            self.collector_config.clone(),
+            query.num_results,
+        )?;
+
+        let search_result = self.search_initial(&search_query, true)?;
+        let top_websites = pipeline.apply(search_result.websites);
+
+        let guard = self.index.guard();
+        let ctx = guard.inverted_index().local_search_ctx();
+        let parsed_query = Query::parse(
+            &ctx,
+            &SearchQuery {
+                query: search_query.query.clone(),
+                fields: search_query.fields.clone(),
+                ..Default::default()
+            },
+            guard.inverted_index(),
+        )?;
+
+        Ok(SearchIter {
+            guard,
+            parsed_query,
+            query: search_query,
+            websites: top_websites.into_iter(),
+            num_retrieved: 0,
         })
     }
 
+    /// Like [`LocalSearcher::search`], but returns a redirect if the query contains
+    /// a known bang. Unknown bangs are searched for as text.
+    pub fn search_with_bangs(&self, query: &SearchQuery) -> Result<SearchResult> {
+        if let Some(bangs) = &self.bangs {
+            if let Some(hit) = bangs.get(&parse_terms(&query.query)) {
+                return Ok(SearchResult::Bang(Box::new(hit)));
+            }
+        }
+
+        Ok(SearchResult::Websites(self.search(query)?))
+    }
+
     pub fn get_webpage(&self, url: &str) -> Option<RetrievedWebpage> {
         self.index.guard().inverted_index().get_webpage(url)
     }
@@ -410,12 +650,131 @@ where
     pub fn get_homepage(&self, url: &Url) -> Option<RetrievedWebpage> {
         self.index.guard().inverted_index().get_homepage(url)
     }
+
+    /// Find the `limit` pages with content most similar to the page at `url`. The score
+    /// of each result is the estimated jaccard similarity between the two pages.
+    pub fn similar_documents(&self, url: &str, limit: usize) -> Result<Vec<DisplayedWebpage>> {
+        let similar = self
+            .index
+            .guard()
+            .inverted_index()
+            .similar_documents(url, limit)?;
+
+        Ok(similar
+            .into_iter()
+            .map(|(webpage, similarity)| {
+                let mut webpage = DisplayedWebpage::from(webpage);
+                webpage.score = Some(similarity);
+                webpage
+            })
+            .collect())
+    }
+}
+
+/// Results of [`LocalSearcher::search_iter`] in ranked order. The index is
+/// kept open until the iterator is dropped.
+pub struct SearchIter<'a, I: SearchableIndex + 'a> {
+    guard: I::SearchGuard<'a>,
+    parsed_query: Query,
+    query: SearchQuery,
+    websites: std::vec::IntoIter<RankingWebsite>,
+    num_retrieved: usize,
+}
+
+impl<'a, I: SearchableIndex + 'a> SearchIter<'a, I> {
+    /// Number of results whose stored document has been retrieved so far.
+    pub fn num_retrieved(&self) -> usize {
+        self.num_retrieved
+    }
+
+    fn retrieve(&mut self, website: RankingWebsite) -> Result<DisplayedWebpage> {
+        let mut webpages: Vec<_> = self
+            .guard
+            .inverted_index()
+            .retrieve_websites(&[website.pointer.clone()], &self.parsed_query)?
+            .into_iter()
+            .map(DisplayedWebpage::from)
+            .collect();
+
+        self.num_retrieved += webpages.len();
+
+        add_rankings(&mut webpages, &[website], &self.query);
+        add_matched_sites(&mut webpages, &self.query.query);
+
+        if let Some(fields) = &self.query.fields {
+            select_fields(&mut webpages, fields);
+        }
+
+        webpages
+            .pop()
+            .ok_or_else(|| Error::InternalError("failed to retrieve webpage".to_string()).into())
+    }
+}
+
+impl<'a, I: SearchableIndex + 'a> Iterator for SearchIter<'a, I> {
+    type Item = Result<DisplayedWebpage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let website = self.websites.next()?;
+
+        Some(self.retrieve(website))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.websites.size_hint()
+    }
+}
+
+/// Pipeline used by [`LocalSearcher::search`] to re-rank the initial results.
+/// The cross encoder is used if it can be found in `data/cross_encoder`.
+pub(crate) fn reranking_pipeline<T: AsRankingWebsite>(
+    query: &mut SearchQuery,
+    collector_config: CollectorConfig,
+    top_n: usize,
+) -> Result<RankingPipeline<T>> {
+    use crate::ranking::models::cross_encoder::CrossEncoderModel;
+
+    let model = CrossEncoderModel::open("data/cross_encoder")
+        .ok()
+        .map(Arc::new);
+
+    RankingPipeline::reranker::<CrossEncoderModel>(query, model, None, collector_config, top_n)
+}
+
+/// Attach the score and ranking signals of `rankings` to the corresponding webpages,
+/// along with a score explanation for the webpage with [`SearchQuery::explain_url`].
+pub(crate) fn add_rankings<T: AsRankingWebsite>(
+    webpages: &mut [DisplayedWebpage],
+    rankings: &[T],
+    query: &SearchQuery,
+) {
+    let explain_url = query.normalized_explain_url();
+
+    for (webpage, ranking) in webpages.iter_mut().zip(rankings) {
+        let ranking = ranking.as_ranking();
+
+        if explain_url.as_deref() == Some(webpage.url.as_str()) {
+            webpage.explanation = Some(Explanation::from(ranking));
+        }
+
+        let mut ranking_signals = HashMap::new();
+
+        for signal in ALL_SIGNALS {
+            if let Some(score) = ranking.signals.get(signal) {
+                ranking_signals.insert(signal, *score);
+            }
+        }
+
+        webpage.ranking_signals = Some(ranking_signals);
+        webpage.score = Some(ranking.score);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        searcher::NUM_RESULTS_PER_PAGE,
+        gen_temp_path,
+        searcher::{QueryLogEntry, ReturnField, NUM_RESULTS_PER_PAGE},
         webpage::{Html, Webpage},
     };
 
@@ -479,4 +838,1196 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn explain_url() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for (url, centrality) in [
+            ("https://www.first.com", 1.0),
+            ("https://www.second.com", 0.5),
+        ] {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test example
+                </body>
+            </html>
+            "#,
+                        url,
+                    )
+                    .unwrap(),
+                    host_centrality: centrality,
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                explain_url: Some("https://www.second.com".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(res.webpages.len(), 2);
+
+        let (explained, not_explained): (Vec<_>, Vec<_>) = res
+            .webpages
+            .iter()
+            .partition(|webpage| webpage.url == "https://www.second.com/");
+
+        assert!(not_explained[0].explanation.is_none());
+
+        let explanation = explained[0].explanation.as_ref().unwrap();
+        assert_eq!(explanation.score, explained[0].score.unwrap());
+
+        assert!(!explanation.components.is_empty());
+        assert!(explanation
+            .components
+            .iter()
+            .any(|c| c.signal == crate::ranking::Signal::HostCentrality));
+        assert!((explanation.sum_of_contributions() - explanation.score).abs() < 1e-6);
+    }
+
+    #[test]
+    fn matched_site() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for url in ["https://www.example.com", "https://www.other.com"] {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        url,
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.webpages.len(), 2);
+        assert!(res.webpages.iter().all(|w| w.matched_site.is_none()));
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test site:example.com".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.webpages.len(), 1);
+        assert_eq!(res.webpages[0].url, "https://www.example.com/");
+        assert_eq!(res.webpages[0].matched_site.as_deref(), Some("example.com"));
+    }
+
+    #[test]
+    fn operator_only_query() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    site intitle inurl
+                </body>
+            </html>
+            "#,
+                    "https://www.example.com",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        for query in ["site:", "site: intitle: -", ""] {
+            let res = searcher
+                .search(&SearchQuery {
+                    query: query.to_string(),
+                    ..Default::default()
+                })
+                .unwrap();
+
+            assert!(res.empty_query);
+            assert!(res.webpages.is_empty());
+            assert_eq!(res.num_hits, Some(0));
+        }
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "site".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(!res.empty_query);
+        assert_eq!(res.webpages.len(), 1);
+    }
+
+    #[test]
+    fn bangs() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test !unknown
+                </body>
+            </html>
+            "#,
+                    "https://www.example.com",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().unwrap();
+
+        let mut searcher = LocalSearcher::new(index);
+        searcher.set_bangs(Bangs::from_json(
+            r#"[{
+            "c": "Multimedia",
+            "d": "www.youtube.com",
+            "r": 1646,
+            "s": "Youtube",
+            "sc": "Video",
+            "t": "ty",
+            "u": "https://www.youtube.com/results?search_query={{{s}}}"
+        }]"#,
+        ));
+
+        match searcher
+            .search_with_bangs(&SearchQuery {
+                query: "!ty test".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+        {
+            SearchResult::Bang(hit) => assert_eq!(
+                hit.redirect_to.as_str(),
+                "https://www.youtube.com/results?search_query=test"
+            ),
+            SearchResult::Websites(_) => panic!("expected a redirect"),
+        }
+
+        match searcher
+            .search_with_bangs(&SearchQuery {
+                query: "!unknown test".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+        {
+            SearchResult::Websites(res) => {
+                assert_eq!(res.webpages.len(), 1);
+                assert_eq!(res.webpages[0].url, "https://www.example.com/");
+            }
+            SearchResult::Bang(_) => panic!("unknown bang should be searched for"),
+        }
+    }
+
+    #[test]
+    fn lowered_query() {
+        let searcher = LocalSearcher::new(Index::temporary().expect("Unable to open index"));
+
+        let lowered = searcher
+            .lowered_query(&SearchQuery {
+                query: "this -that site:x.com".to_string(),
+                safe_search: false,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(lowered.contains("BooleanQuery"));
+        assert!(lowered.contains("Must,"));
+        assert!(lowered.contains("MustNot,"));
+
+        let lowered = searcher
+            .lowered_query(&SearchQuery {
+                query: "this site:x.com".to_string(),
+                safe_search: false,
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(lowered.contains("Must,"));
+        assert!(!lowered.contains("MustNot,"));
+
+        assert!(searcher
+            .lowered_query(&SearchQuery {
+                query: "site:".to_string(),
+                ..Default::default()
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn similar_documents() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        let bodies = [
+            "the quick brown fox jumps over the lazy dog while the farmer watches from the porch",
+            "rust is a systems programming language focused on safety speed and concurrency",
+            "the quick brown fox jumps over the lazy dog while the farmer sleeps on the porch",
+            "bake the bread at a high temperature until the crust is golden and crisp",
+        ];
+
+        for (i, body) in bodies.iter().enumerate() {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Website {i}</title>
+                </head>
+                <body>
+                    {body}
+                </body>
+            </html>
+            "#
+                        ),
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher.similar_documents("https://www.0.com/", 1).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].url, "https://www.2.com/");
+        assert!(res[0].score.unwrap() > 0.3);
+
+        let res = searcher.similar_documents("https://www.2.com/", 1).unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].url, "https://www.0.com/");
+
+        assert!(searcher
+            .similar_documents("https://www.missing.com/", 1)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn displayed_fields() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                    "https://blog.example.com/post",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(res.webpages.len(), 1);
+
+        let webpage = &res.webpages[0];
+        assert_eq!(webpage.url, "https://blog.example.com/post");
+        assert_eq!(webpage.title, "Example website");
+        assert_eq!(webpage.site, "blog.example.com");
+        assert_eq!(webpage.domain, "example.com");
+        assert!(!webpage.pretty_url.is_empty());
+        assert!(webpage
+            .snippet
+            .as_ref()
+            .and_then(|snippet| snippet.text())
+            .unwrap()
+            .unhighlighted_string()
+            .contains("test"));
+        assert!(webpage.score.is_some());
+        assert!(!webpage.ranking_signals.as_ref().unwrap().is_empty());
+        assert!(webpage.explanation.is_none());
+    }
+
+    #[test]
+    fn result_cache() {
+        let webpage = |i: usize| Webpage {
+            html: Html::parse(
+                r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                &format!("https://www.{i}.com"),
+            )
+            .unwrap(),
+            fetch_time_ms: 500,
+            ..Default::default()
+        };
+
+        let mut index = Index::temporary().expect("Unable to open index");
+        index.insert(webpage(0)).expect("failed to insert webpage");
+        index.commit().unwrap();
+
+        let mut searcher = LocalSearcher::new(index);
+        let query = SearchQuery {
+            query: "test".to_string(),
+            ..Default::default()
+        };
+
+        assert!(searcher.result_cache_stats().is_none());
+        searcher.set_result_cache(ResultCacheConfig::default());
+
+        let first = searcher.search(&query).unwrap();
+        assert_eq!(
+            searcher.result_cache_stats(),
+            Some(ResultCacheStats { hits: 0, misses: 1 })
+        );
+
+        let second = searcher.search(&query).unwrap();
+        assert_eq!(
+            searcher.result_cache_stats(),
+            Some(ResultCacheStats { hits: 1, misses: 1 })
+        );
+        assert_eq!(
+            first.webpages.iter().map(|w| &w.url).collect::<Vec<_>>(),
+            second.webpages.iter().map(|w| &w.url).collect::<Vec<_>>()
+        );
+        assert_eq!(second.webpages.len(), 1);
+
+        searcher
+            .index
+            .insert(webpage(1))
+            .expect("failed to insert webpage");
+        searcher.index.commit().unwrap();
+
+        let third = searcher.search(&query).unwrap();
+        assert_eq!(
+            searcher.result_cache_stats(),
+            Some(ResultCacheStats { hits: 1, misses: 2 })
+        );
+        assert_eq!(third.webpages.len(), 2);
+    }
+
+    #[test]
+    fn search_iter() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..10 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: (10 - i) as f64,
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+        let query = SearchQuery {
+            query: "test".to_string(),
+            ..Default::default()
+        };
+
+        let mut iter = searcher.search_iter(&query).unwrap();
+        assert_eq!(iter.num_retrieved(), 0);
+        assert_eq!(iter.size_hint(), (10, Some(10)));
+
+        let first = iter.next().unwrap().unwrap();
+        assert_eq!(iter.num_retrieved(), 1);
+        assert_eq!(iter.size_hint(), (9, Some(9)));
+        assert_eq!(first.url, "https://www.0.com/");
+        assert!(first.score.is_some());
+
+        let rest: Vec<_> = iter.by_ref().map(|webpage| webpage.unwrap().url).collect();
+        assert_eq!(iter.num_retrieved(), 10);
+        let expected: Vec<_> = searcher
+            .search(&query)
+            .unwrap()
+            .webpages
+            .into_iter()
+            .skip(1)
+            .map(|webpage| webpage.url)
+            .collect();
+
+        assert_eq!(rest, expected);
+    }
+
+    #[test]
+    fn min_score() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..5 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: 10.0_f64.powi(-(i as i32)),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.webpages.len(), 5);
+
+        let top_score = res.webpages[0].score.unwrap();
+        let tail_score = res.webpages[4].score.unwrap();
+        assert!(tail_score < top_score);
+
+        let threshold = ((tail_score / top_score + 1.0) / 2.0) as f32;
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                min_score: Some(threshold),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert!(!res.webpages.is_empty());
+        assert!(res.webpages.len() < 5);
+        assert_eq!(res.webpages[0].url, "https://www.0.com/");
+        assert!(res
+            .webpages
+            .iter()
+            .all(|webpage| webpage.score.unwrap() / top_score >= threshold as f64));
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                min_score: Some(0.0),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.webpages.len(), 5);
+    }
+
+    #[test]
+    fn query_log() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                    "https://www.example.com",
+                )
+                .unwrap(),
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().unwrap();
+
+        let path = gen_temp_path();
+        let mut searcher = LocalSearcher::new(index);
+        searcher.set_query_logger(
+            QueryLogger::open(&path)
+                .unwrap()
+                .with_redaction(QueryLogger::hash_query),
+        );
+
+        searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        drop(searcher);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        let entries: Vec<QueryLogEntry> = log
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].query, QueryLogger::hash_query("test"));
+        assert_ne!(entries[0].query, "test");
+        assert_eq!(
+            entries[0].urls,
+            vec!["https://www.example.com/".to_string()]
+        );
+    }
+
+    #[test]
+    fn normalized_scores() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..5 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: 10.0_f64.powi(-(i as i32)),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(res.webpages.iter().all(|w| w.normalized_score.is_none()));
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                normalize_scores: true,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(res.webpages.len(), 5);
+        assert_eq!(res.webpages[0].normalized_score, Some(1.0));
+
+        let top_score = res.webpages[0].score.unwrap();
+
+        for webpage in &res.webpages[1..] {
+            let normalized = webpage.normalized_score.unwrap();
+            assert!(normalized < 1.0);
+            assert!((normalized - webpage.score.unwrap() / top_score).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn select_fields() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(Webpage {
+                html: Html::parse(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                    "https://www.example.com",
+                )
+                .unwrap(),
+                host_centrality: 0.5,
+                fetch_time_ms: 500,
+                ..Default::default()
+            })
+            .expect("failed to insert webpage");
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        let webpage = &res.webpages[0];
+        assert!(!webpage.site.is_empty());
+        assert!(webpage.score.is_some());
+        assert!(webpage.host_centrality.is_none());
+
+        let res = searcher
+            .search(
+                &SearchQuery::builder("test")
+                    .with_fields(vec![ReturnField::Title, ReturnField::HostCentrality])
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(res.webpages.len(), 1);
+
+        let webpage = &res.webpages[0];
+        assert_eq!(webpage.url, "https://www.example.com/");
+        assert_eq!(webpage.title, "Example website");
+        assert!(webpage.host_centrality.is_some());
+
+        assert!(webpage.site.is_empty());
+        assert!(webpage.domain.is_empty());
+        assert!(webpage.pretty_url.is_empty());
+        assert!(webpage.score.is_none());
+        assert!(webpage.ranking_signals.is_none());
+        assert!(webpage.snippet.is_none());
+
+        let json = serde_json::to_value(webpage).unwrap();
+        let mut keys: Vec<_> = json.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+
+        assert_eq!(keys, vec!["hostCentrality", "title", "url"]);
+    }
+
+    #[test]
+    fn host_facets() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for url in [
+            "https://www.a.com/1",
+            "https://www.a.com/2",
+            "https://www.a.com/3",
+            "https://b.com/1",
+            "https://b.com/2",
+            "https://c.com/1",
+        ] {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Example website {url}</title>
+                </head>
+                <body>
+                    test example
+                </body>
+            </html>
+            "#
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(res.host_facets.is_none());
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                host_facets: Some(2),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(
+            res.host_facets,
+            Some(vec![
+                HostFacet {
+                    host: "a.com".to_string(),
+                    count: 3
+                },
+                HostFacet {
+                    host: "b.com".to_string(),
+                    count: 2
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn collapse_by_host() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for url in [
+            "https://www.a.com/1",
+            "https://www.a.com/2",
+            "https://www.a.com/3",
+            "https://b.com/1",
+            "https://b.com/2",
+            "https://c.com/1",
+        ] {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Example website {url}</title>
+                </head>
+                <body>
+                    test example
+                </body>
+            </html>
+            "#
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let all = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+            .webpages;
+        assert_eq!(all.len(), 6);
+        assert!(all.iter().all(|webpage| webpage.group_count.is_none()));
+
+        let collapsed = searcher
+            .search(
+                &SearchQuery::builder("example")
+                    .with_collapse_field(ReturnField::Site)
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap()
+            .webpages;
+
+        let groups: HashMap<_, _> = collapsed
+            .iter()
+            .map(|webpage| (webpage.site.as_str(), webpage.group_count))
+            .collect();
+
+        assert_eq!(collapsed.len(), 3);
+        assert_eq!(groups["a.com"], Some(3));
+        assert_eq!(groups["b.com"], Some(2));
+        assert_eq!(groups["c.com"], Some(1));
+
+        // the representative is the highest ranked result of the group
+        for webpage in &collapsed {
+            let first = all.iter().find(|w| w.site == webpage.site).unwrap();
+            assert_eq!(webpage.url, first.url);
+        }
+    }
+
+    #[test]
+    fn collapse_across_pages() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for url in [
+            "https://www.a.com/1",
+            "https://www.a.com/2",
+            "https://www.a.com/3",
+            "https://b.com/1",
+            "https://b.com/2",
+            "https://c.com/1",
+        ] {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Example website {url}</title>
+                </head>
+                <body>
+                    test example
+                </body>
+            </html>
+            "#
+                        ),
+                        url,
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let search = |page| {
+            searcher
+                .search(&SearchQuery {
+                    query: "example".to_string(),
+                    page,
+                    num_results: 2,
+                    collapse_field: Some(ReturnField::Site),
+                    count_results: true,
+                    ..Default::default()
+                })
+                .unwrap()
+        };
+
+        let first = search(0);
+        let second = search(1);
+
+        assert_eq!(first.webpages.len(), 2);
+        assert!(first.has_more_results);
+        assert_eq!(first.num_hits, Some(3));
+
+        assert_eq!(second.webpages.len(), 1);
+        assert!(!second.has_more_results);
+        assert_eq!(second.num_hits, Some(3));
+
+        let groups: HashMap<_, _> = first
+            .webpages
+            .iter()
+            .chain(second.webpages.iter())
+            .map(|webpage| (webpage.site.as_str(), webpage.group_count))
+            .collect();
+
+        // no site is repeated on the second page
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups["a.com"], Some(3));
+        assert_eq!(groups["b.com"], Some(2));
+        assert_eq!(groups["c.com"], Some(1));
+
+        assert!(search(2).webpages.is_empty());
+    }
+
+    #[test]
+    fn collapse_counts_whole_group() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..10 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        &format!(
+                            r#"
+            <html>
+                <head>
+                    <title>Example website {i}</title>
+                </head>
+                <body>
+                    test example
+                </body>
+            </html>
+            "#
+                        ),
+                        &format!("https://www.a.com/{i}"),
+                    )
+                    .unwrap(),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        // the group has more documents than the candidates that are ranked for one result
+        let res = searcher
+            .search(&SearchQuery {
+                query: "example".to_string(),
+                num_results: 1,
+                collapse_field: Some(ReturnField::Site),
+                ..Default::default()
+            })
+            .unwrap();
+
+        assert_eq!(res.webpages.len(), 1);
+        assert_eq!(res.webpages[0].group_count, Some(10));
+    }
+
+    #[test]
+    fn min_score_across_pages() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..5 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: 10.0_f64.powi(-(i as i32)),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let all = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+            .webpages;
+        assert_eq!(all.len(), 5);
+
+        let top_score = all[0].score.unwrap();
+
+        let threshold = ((all[2].score.unwrap() + all[3].score.unwrap()) / 2.0 / top_score) as f32;
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                page: 1,
+                num_results: 2,
+                min_score: Some(threshold),
+                count_results: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // only the third result is above the threshold on the second page
+        assert_eq!(res.webpages.len(), 1);
+        assert_eq!(res.webpages[0].url, all[2].url);
+        assert!(!res.has_more_results);
+        assert_eq!(res.num_hits, Some(3));
+    }
+
+    #[test]
+    fn normalized_scores_across_pages() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        for i in 0..5 {
+            index
+                .insert(Webpage {
+                    html: Html::parse(
+                        r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#,
+                        &format!("https://www.{i}.com"),
+                    )
+                    .unwrap(),
+                    host_centrality: 10.0_f64.powi(-(i as i32)),
+                    fetch_time_ms: 500,
+                    ..Default::default()
+                })
+                .expect("failed to insert webpage");
+        }
+
+        index.commit().unwrap();
+
+        let searcher = LocalSearcher::new(index);
+
+        let all = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                ..Default::default()
+            })
+            .unwrap()
+            .webpages;
+        assert_eq!(all.len(), 5);
+
+        let top_score = all[0].score.unwrap();
+
+        let res = searcher
+            .search(&SearchQuery {
+                query: "test".to_string(),
+                page: 1,
+                num_results: 2,
+                normalize_scores: true,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // the scores are normalized by the top result of the first page
+        assert_eq!(res.webpages.len(), 2);
+        for webpage in &res.webpages {
+            let normalized = webpage.normalized_score.unwrap();
+            assert!(normalized < 1.0);
+            assert!((normalized - webpage.score.unwrap() / top_score).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn merge_facets() {
+        let shard1 = vec![
+            HostFacet {
+                host: "a.com".to_string(),
+                count: 1,
+            },
+            HostFacet {
+                host: "b.com".to_string(),
+                count: 2,
+            },
+        ];
+        let shard2 = vec![HostFacet {
+            host: "a.com".to_string(),
+            count: 2,
+        }];
+
+        assert_eq!(
+            crate::searcher::merge_host_facets([&shard1[..], &shard2[..]].into_iter(), 1),
+            vec![HostFacet {
+                host: "a.com".to_string(),
+                count: 3
+            }]
+        );
+    }
 }
@@ -15,19 +15,40 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 pub mod api;
+pub mod builder;
 pub mod distributed;
 pub mod live;
 pub mod local;
+pub mod multi;
+pub mod query_log;
+pub mod result_cache;
 
+pub use builder::*;
 pub use distributed::*;
 pub use local::*;
+pub use multi::*;
+pub use query_log::*;
+pub use result_cache::*;
+
 use optics::{HostRankings, Optic};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
 use utoipa::ToSchema;
 
 use crate::{
-    bangs::BangHit, config::defaults, ranking::pipeline::RankingWebsite,
-    search_prettifier::DisplayedWebpage, webpage::region::Region,
+    bangs::BangHit,
+    collector::Hashes,
+    config::defaults,
+    prehashed::Prehashed,
+    query::parser::{parse_terms, Term},
+    ranking::{
+        pipeline::{AsRankingWebsite, RankingWebsite},
+        Signal,
+    },
+    schema::FastField,
+    search_prettifier::DisplayedWebpage,
+    webpage::region::Region,
 };
 
 pub const NUM_RESULTS_PER_PAGE: usize = 20;
@@ -38,13 +59,364 @@ pub enum SearchResult {
     Bang(Box<BangHit>),
 }
 
-#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct WebsitesResult {
     pub webpages: Vec<DisplayedWebpage>,
     pub num_hits: Option<usize>,
     pub search_duration_ms: u128,
     pub has_more_results: bool,
+    pub host_facets: Option<Vec<HostFacet>>,
+    /// Set when the query had nothing to search for (e.g. `site:` on its own),
+    /// in which case the index was not searched and there are no results.
+    #[serde(default)]
+    pub empty_query: bool,
+}
+
+impl WebsitesResult {
+    fn empty_query(search_duration_ms: u128) -> Self {
+        Self {
+            webpages: Vec::new(),
+            num_hits: Some(0),
+            search_duration_ms,
+            has_more_results: false,
+            host_facets: None,
+            empty_query: true,
+        }
+    }
+}
+
+/// Number of results from a host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HostFacet {
+    pub host: String,
+    pub count: u64,
+}
+
+/// Sum the counts of the same group across the group counts of several indexes.
+pub fn merge_group_counts<'a>(
+    counts: impl Iterator<Item = &'a [(Prehashed, u64)]>,
+) -> HashMap<Prehashed, u64> {
+    let mut res = HashMap::new();
+
+    for (group, count) in counts.flatten() {
+        *res.entry(*group).or_default() += count;
+    }
+
+    res
+}
+
+/// Sum the counts of the same host across `facets` and keep the `top_n` hosts with most results.
+pub fn merge_host_facets<'a>(
+    facets: impl Iterator<Item = &'a [HostFacet]>,
+    top_n: usize,
+) -> Vec<HostFacet> {
+    let mut counts: HashMap<&str, u64> = HashMap::new();
+
+    for facet in facets.flatten() {
+        *counts.entry(facet.host.as_str()).or_default() += facet.count;
+    }
+
+    let mut res: Vec<_> = counts
+        .into_iter()
+        .map(|(host, count)| HostFacet {
+            host: host.to_string(),
+            count,
+        })
+        .collect();
+
+    res.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.host.cmp(&b.host)));
+    res.truncate(top_n);
+
+    res
+}
+
+fn site_matches(site: &str, url: &str) -> bool {
+    let site = site.trim_end_matches('/');
+    let (site_host, site_path) = match site.split_once('/') {
+        Some((host, path)) => (host, Some(path)),
+        None => (site, None),
+    };
+
+    let url = url.to_lowercase();
+    let url = url.split_once("://").map(|(_, rest)| rest).unwrap_or(&url);
+    let (host, path) = url.split_once('/').unwrap_or((url, ""));
+
+    let host_matches = if site_host.starts_with('.') {
+        host.ends_with(site_host)
+    } else {
+        host == site_host || host.ends_with(&format!(".{site_host}"))
+    };
+
+    host_matches
+        && site_path
+            .map(|site_path| path.starts_with(site_path))
+            .unwrap_or(true)
+}
+
+/// Set [`DisplayedWebpage::matched_site`] on the webpages that match
+/// one of the `site:` operators in `query`.
+pub fn add_matched_sites(webpages: &mut [DisplayedWebpage], query: &str) {
+    let sites: Vec<_> = parse_terms(query)
+        .into_iter()
+        .filter_map(|term| match term {
+            Term::Site(site) => Some(site.to_lowercase()),
+            _ => None,
+        })
+        .collect();
+
+    if sites.is_empty() {
+        return;
+    }
+
+    for webpage in webpages {
+        webpage.matched_site = sites
+            .iter()
+            .find(|site| site_matches(site, &webpage.url))
+            .cloned();
+    }
+}
+
+/// Fields of a [`DisplayedWebpage`] that can be selected with [`SearchQuery::fields`].
+/// The url is always returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ReturnField {
+    Title,
+    Site,
+    Domain,
+    PrettyUrl,
+    Snippet,
+    Score,
+    RankingSignals,
+    LikelyHasAds,
+    LikelyHasPaywall,
+    HostCentrality,
+}
+
+impl ReturnField {
+    /// Whether results can be collapsed by this field with [`SearchQuery::collapse_field`].
+    pub fn can_collapse(&self) -> bool {
+        matches!(
+            self,
+            ReturnField::Title | ReturnField::Site | ReturnField::Domain
+        )
+    }
+
+    fn collapse_key(&self, hashes: &Hashes) -> Option<Prehashed> {
+        match self {
+            ReturnField::Title => Some(hashes.title),
+            ReturnField::Site => Some(hashes.site),
+            ReturnField::Domain => Some(hashes.domain),
+            _ => None,
+        }
+    }
+
+    /// The fast fields holding the hash returned by `collapse_key`.
+    fn collapse_hash_fields(&self) -> Option<[FastField; 2]> {
+        match self {
+            ReturnField::Title => Some([FastField::TitleHash1, FastField::TitleHash2]),
+            ReturnField::Site => Some([FastField::SiteHash1, FastField::SiteHash2]),
+            ReturnField::Domain => Some([FastField::DomainHash1, FastField::DomainHash2]),
+            _ => None,
+        }
+    }
+}
+
+/// How many candidates are ranked for each requested result when the results
+/// are collapsed, as several candidates can collapse into a single result.
+const COLLAPSE_CANDIDATES_FACTOR: usize = 4;
+
+/// Score of the highest scoring result if it can be used to normalize the other scores.
+fn top_score(scores: impl Iterator<Item = f64>) -> Option<f64> {
+    let top_score = scores.fold(f64::NEG_INFINITY, f64::max);
+
+    if top_score > 0.0 {
+        Some(top_score)
+    } else {
+        None
+    }
+}
+
+/// The requested page of the ranked results after applying [`SearchQuery::min_score`],
+/// [`SearchQuery::collapse_field`] and [`SearchQuery::normalize_scores`]. These options
+/// depend on all the results, so they are applied to every result up to and including
+/// the requested page (see [`SearchQuery::ranking_query`]) before the page is cut.
+pub struct ResultPage<T> {
+    pub websites: Vec<T>,
+    /// Whether there are more results after this page.
+    pub has_more: bool,
+    /// The total number of results after filtering and collapsing, if all the
+    /// results were ranked.
+    pub num_results: Option<usize>,
+    group_counts: Option<Vec<usize>>,
+    normalized_scores: Option<Vec<f64>>,
+}
+
+impl<T: AsRankingWebsite> ResultPage<T> {
+    /// Cut the page requested by `query` from `ranked`, which must be sorted by rank and
+    /// ranked with [`SearchQuery::ranking_query`]. `exhausted` is whether `ranked` holds
+    /// all the results of the query. `exact_counts` are the number of matching documents
+    /// in each group when the results are collapsed (see [`InitialWebsiteResult::group_counts`]).
+    /// Groups without a count are counted among the ranked results.
+    pub fn new(
+        ranked: Vec<T>,
+        query: &SearchQuery,
+        exhausted: bool,
+        exact_counts: &HashMap<Prehashed, u64>,
+    ) -> Self {
+        if !query.needs_all_results() {
+            return Self {
+                websites: ranked,
+                has_more: !exhausted,
+                num_results: None,
+                group_counts: None,
+                normalized_scores: None,
+            };
+        }
+
+        let mut exhausted = exhausted;
+        let mut websites = ranked;
+        let top_score = top_score(websites.iter().map(|website| website.as_ranking().score));
+
+        if let (Some(min_score), Some(top_score)) = (query.min_score, top_score) {
+            let num_ranked = websites.len();
+            websites.retain(|website| website.as_ranking().score / top_score >= min_score as f64);
+
+            // the results that were not ranked score below the ones that were dropped
+            if websites.len() != num_ranked {
+                exhausted = true;
+            }
+        }
+
+        let mut group_counts = None;
+
+        if let Some(field) = query.collapse_by() {
+            let mut groups: HashMap<Option<Prehashed>, usize> = HashMap::new();
+            let mut representatives = Vec::new();
+            let mut counts = Vec::new();
+
+            for website in websites {
+                let key = field.collapse_key(&website.as_ranking().pointer.hashes);
+
+                match groups.get(&key) {
+                    Some(group) => counts[*group] += 1,
+                    None => {
+                        groups.insert(key, representatives.len());
+                        representatives.push(website);
+                        counts.push(1);
+                    }
+                }
+            }
+
+            for (website, count) in representatives.iter().zip(counts.iter_mut()) {
+                if let Some(exact) = field
+                    .collapse_key(&website.as_ranking().pointer.hashes)
+                    .and_then(|key| exact_counts.get(&key))
+                {
+                    *count = *exact as usize;
+                }
+            }
+
+            websites = representatives;
+            group_counts = Some(counts);
+        }
+
+        let num_candidates = websites.len();
+        let offset = (query.page * query.num_results).min(num_candidates);
+        let end = (offset + query.num_results).min(num_candidates);
+
+        let websites: Vec<_> = websites.drain(offset..end).collect();
+        let group_counts = group_counts.map(|counts| counts[offset..end].to_vec());
+
+        let normalized_scores = if query.normalize_scores {
+            top_score.map(|top_score| {
+                websites
+                    .iter()
+                    .map(|website| (website.as_ranking().score / top_score).clamp(0.0, 1.0))
+                    .collect()
+            })
+        } else {
+            None
+        };
+
+        Self {
+            websites,
+            has_more: end < num_candidates || !exhausted,
+            num_results: exhausted.then_some(num_candidates),
+            group_counts,
+            normalized_scores,
+        }
+    }
+
+    /// Set [`DisplayedWebpage::group_count`] and [`DisplayedWebpage::normalized_score`]
+    /// of the webpages retrieved for the websites of the page.
+    pub fn annotate(&self, webpages: &mut [DisplayedWebpage]) {
+        if let Some(counts) = &self.group_counts {
+            for (webpage, count) in webpages.iter_mut().zip(counts) {
+                webpage.group_count = Some(*count);
+            }
+        }
+
+        if let Some(scores) = &self.normalized_scores {
+            for (webpage, score) in webpages.iter_mut().zip(scores) {
+                webpage.normalized_score = Some(*score);
+            }
+        }
+    }
+}
+
+/// Clear the fields of the webpages that are not in `fields`.
+pub fn select_fields(webpages: &mut [DisplayedWebpage], fields: &[ReturnField]) {
+    let has = |field| fields.contains(&field);
+
+    for webpage in webpages {
+        if has(ReturnField::HostCentrality) {
+            webpage.host_centrality = webpage
+                .ranking_signals
+                .as_ref()
+                .and_then(|signals| signals.get(&Signal::HostCentrality))
+                .map(|score| score.value);
+        }
+
+        if !has(ReturnField::Title) {
+            webpage.title = String::new();
+        }
+
+        if !has(ReturnField::Site) {
+            webpage.site = String::new();
+        }
+
+        if !has(ReturnField::Domain) {
+            webpage.domain = String::new();
+        }
+
+        if !has(ReturnField::PrettyUrl) {
+            webpage.pretty_url = String::new();
+        }
+
+        if !has(ReturnField::Snippet) {
+            webpage.snippet = None;
+        }
+
+        if !has(ReturnField::Score) {
+            webpage.score = None;
+            webpage.normalized_score = None;
+        }
+
+        if !has(ReturnField::RankingSignals) {
+            webpage.ranking_signals = None;
+        }
+
+        if !has(ReturnField::LikelyHasAds) {
+            webpage.likely_has_ads = false;
+        }
+
+        if !has(ReturnField::LikelyHasPaywall) {
+            webpage.likely_has_paywall = false;
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -58,6 +430,29 @@ pub struct SearchQuery {
     pub return_ranking_signals: bool,
     pub safe_search: bool,
     pub count_results: bool,
+    /// Attach a score explanation to the result with this url.
+    pub explain_url: Option<String>,
+    /// Return the number of matching documents for this many of the hosts with most matches.
+    pub host_facets: Option<usize>,
+    /// Drop results whose score relative to the top result is below this threshold.
+    pub min_score: Option<f32>,
+    /// Add a score in [0, 1] relative to the top result to each result.
+    pub normalize_scores: bool,
+    /// Only return these fields of the results. All fields except the
+    /// host centrality are returned if not set.
+    pub fields: Option<Vec<ReturnField>>,
+    /// Only return the highest ranked result for each distinct value of this field.
+    /// The number of documents matching the query in each group is returned in
+    /// [`DisplayedWebpage::group_count`]. When the results are merged from several
+    /// shards, only the shards that ranked a result of the group add to its count.
+    pub collapse_field: Option<ReturnField>,
+    /// Match phrases with the case they were written in. Only the body is cased, so
+    /// phrases that only occur in the title do not match. Indexes built without
+    /// a cased body match the phrases case insensitively.
+    pub case_sensitive_phrases: bool,
+    /// Only return results from one of these sites. This is combined with
+    /// any `site:` operators in the query, so both must match.
+    pub restrict_to_sites: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -65,6 +460,10 @@ pub struct InitialWebsiteResult {
     pub num_websites: Option<usize>,
     pub websites: Vec<RankingWebsite>,
     pub has_more: bool,
+    pub host_facets: Option<Vec<HostFacet>>,
+    /// Number of documents matching the query in each [`SearchQuery::collapse_field`]
+    /// group that one of the websites belongs to.
+    pub group_counts: Option<Vec<(Prehashed, u64)>>,
 }
 
 impl Default for SearchQuery {
@@ -82,12 +481,106 @@ impl Default for SearchQuery {
             return_ranking_signals: defaults::SearchQuery::return_ranking_signals(),
             safe_search: defaults::SearchQuery::safe_search(),
             count_results: defaults::SearchQuery::count_results(),
+            explain_url: Default::default(),
+            host_facets: Default::default(),
+            min_score: Default::default(),
+            normalize_scores: defaults::SearchQuery::normalize_scores(),
+            fields: Default::default(),
+            collapse_field: Default::default(),
+            case_sensitive_phrases: defaults::SearchQuery::case_sensitive_phrases(),
+            restrict_to_sites: Default::default(),
         }
     }
 }
 
 impl SearchQuery {
+    /// Whether the query has nothing to search for. This is also the case
+    /// for queries that only consist of operators without values, like `site:`.
     pub fn is_empty(&self) -> bool {
-        self.query.is_empty()
+        parse_terms(&self.query).iter().all(Term::is_empty_operator)
+    }
+
+    /// Whether the results must be processed together with the results of the previous
+    /// pages, as [`SearchQuery::min_score`], [`SearchQuery::collapse_field`] and
+    /// [`SearchQuery::normalize_scores`] depend on the whole result set.
+    pub fn needs_all_results(&self) -> bool {
+        self.min_score.is_some() || self.normalize_scores || self.collapse_by().is_some()
+    }
+
+    /// [`SearchQuery::explain_url`] normalized like the urls of the results,
+    /// so it can be compared with them.
+    pub fn normalized_explain_url(&self) -> Option<String> {
+        self.explain_url.as_ref().map(|url| {
+            Url::parse(url)
+                .map(|url| url.to_string())
+                .unwrap_or_else(|_| url.clone())
+        })
+    }
+
+    /// The field to collapse the results by, if they can be collapsed by it.
+    fn collapse_by(&self) -> Option<ReturnField> {
+        self.collapse_field.filter(ReturnField::can_collapse)
+    }
+
+    /// The query to rank the results with before [`ResultPage`] cuts the requested page.
+    /// Queries that [need all results](SearchQuery::needs_all_results) rank every
+    /// result up to and including the requested page from the top.
+    pub fn ranking_query(&self) -> SearchQuery {
+        if !self.needs_all_results() {
+            return self.clone();
+        }
+
+        let mut num_results = self.page.saturating_add(1).saturating_mul(self.num_results);
+
+        if self.collapse_by().is_some() {
+            num_results = num_results.saturating_mul(COLLAPSE_CANDIDATES_FACTOR);
+        }
+
+        SearchQuery {
+            page: 0,
+            num_results,
+            ..self.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalized_explain_url() {
+        let query = |url: Option<&str>| SearchQuery {
+            explain_url: url.map(|url| url.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(query(None).normalized_explain_url(), None);
+        assert_eq!(
+            query(Some("https://www.example.com")).normalized_explain_url(),
+            Some("https://www.example.com/".to_string())
+        );
+        assert_eq!(
+            query(Some("not a url")).normalized_explain_url(),
+            Some("not a url".to_string())
+        );
+    }
+
+    #[test]
+    fn site_operator_matches() {
+        assert!(site_matches("example.com", "https://www.example.com/"));
+        assert!(site_matches("example.com", "https://example.com/page"));
+        assert!(site_matches(".com", "https://www.example.com/"));
+        assert!(site_matches(
+            "example.com/blog",
+            "https://www.example.com/blog/post"
+        ));
+
+        assert!(!site_matches("example.com", "https://www.notexample.com/"));
+        assert!(!site_matches(
+            "example.com/blog",
+            "https://www.example.com/"
+        ));
+        assert!(!site_matches(".org", "https://www.example.com/"));
     }
 }
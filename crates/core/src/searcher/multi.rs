@@ -0,0 +1,357 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Search several local indices as if they were a single index.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::config::CollectorConfig;
+use crate::index::Index;
+use crate::ranking::bm25::Bm25Stats;
+use crate::ranking::pipeline::{AsRankingWebsite, RankingPipeline, RankingWebsite};
+use crate::search_prettifier::DisplayedWebpage;
+use crate::{Error, Result};
+
+use super::local::{add_rankings, reranking_pipeline};
+use super::{
+    add_matched_sites, merge_group_counts, merge_host_facets, select_fields, LocalSearcher,
+    ResultPage, SearchQuery, WebsitesResult,
+};
+
+#[derive(Clone)]
+struct ShardWebsite {
+    shard: usize,
+    website: RankingWebsite,
+}
+
+impl AsRankingWebsite for ShardWebsite {
+    fn as_ranking(&self) -> &RankingWebsite {
+        &self.website
+    }
+
+    fn as_mut_ranking(&mut self) -> &mut RankingWebsite {
+        &mut self.website
+    }
+}
+
+pub struct MultiSearcher {
+    shards: Vec<LocalSearcher<Index>>,
+    collector_config: CollectorConfig,
+}
+
+impl From<Vec<Index>> for MultiSearcher {
+    fn from(indexes: Vec<Index>) -> Self {
+        Self::new(indexes)
+    }
+}
+
+impl MultiSearcher {
+    pub fn new(indexes: Vec<Index>) -> Self {
+        Self {
+            shards: indexes.into_iter().map(LocalSearcher::new).collect(),
+            collector_config: CollectorConfig::default(),
+        }
+    }
+
+    pub fn set_collector_config(&mut self, config: CollectorConfig) {
+        for shard in self.shards.iter_mut() {
+            shard.set_collector_config(config.clone());
+        }
+
+        self.collector_config = config;
+    }
+
+    /// Search all shards and merge their results by score. The bm25 signals of all shards
+    /// are computed from the merged corpus statistics of the shards, so the scores are
+    /// comparable. Results with the same score are ordered by shard and then by their
+    /// rank within the shard.
+    pub fn search(&self, query: &SearchQuery) -> Result<WebsitesResult> {
+        let start = Instant::now();
+
+        if query.is_empty() {
+            return Ok(WebsitesResult::empty_query(start.elapsed().as_millis()));
+        }
+
+        let mut search_query = query.ranking_query();
+        let top_n = search_query.num_results;
+
+        let pipeline: RankingPipeline<ShardWebsite> =
+            reranking_pipeline(&mut search_query, self.collector_config.clone(), top_n)?;
+
+        // each shard would otherwise use its own idf and average field lengths
+        let mut bm25_stats = Bm25Stats::default();
+        for searcher in &self.shards {
+            bm25_stats.merge(searcher.bm25_stats(&search_query)?);
+        }
+        let bm25_stats = Arc::new(bm25_stats);
+
+        let mut websites = Vec::new();
+        let mut num_hits = Some(0);
+        let mut has_more = false;
+        let mut shard_facets = Vec::new();
+        let mut shard_group_counts = Vec::new();
+
+        for (shard, searcher) in self.shards.iter().enumerate() {
+            let res = searcher.search_initial_with_bm25_stats(
+                &search_query,
+                true,
+                Some(Arc::clone(&bm25_stats)),
+            )?;
+
+            num_hits = num_hits.zip(res.num_websites).map(|(a, b)| a + b);
+            has_more |= res.has_more;
+            shard_facets.extend(res.host_facets);
+            shard_group_counts.extend(res.group_counts);
+            websites.extend(
+                res.websites
+                    .into_iter()
+                    .map(|website| ShardWebsite { shard, website }),
+            );
+        }
+
+        // the sort is stable, so ties keep their shard and in-shard order
+        websites.sort_by(|a, b| b.website.score.total_cmp(&a.website.score));
+        websites.truncate(pipeline.collector_top_n());
+
+        let search_len = websites.len();
+        let top_websites = pipeline.apply(websites);
+        let exhausted = !has_more && search_len == top_websites.len();
+
+        let group_counts = merge_group_counts(shard_group_counts.iter().map(|c| c.as_slice()));
+        let page = ResultPage::new(top_websites, query, exhausted, &group_counts);
+        let top_websites = &page.websites;
+
+        let mut retrieved: Vec<Option<DisplayedWebpage>> = std::iter::repeat_with(|| None)
+            .take(top_websites.len())
+            .collect();
+
+        for (shard, searcher) in self.shards.iter().enumerate() {
+            let (positions, pointers): (Vec<_>, Vec<_>) = top_websites
+                .iter()
+                .enumerate()
+                .filter(|(_, website)| website.shard == shard)
+                .map(|(pos, website)| (pos, website.website.pointer.clone()))
+                .unzip();
+
+            if pointers.is_empty() {
+                continue;
+            }
+
+            let webpages = searcher.retrieve_selected_fields(
+                &pointers,
+                &search_query.query,
+                query.fields.as_deref(),
+            )?;
+
+            for (pos, webpage) in positions.into_iter().zip(webpages) {
+                retrieved[pos] = Some(DisplayedWebpage::from(webpage));
+            }
+        }
+
+        let mut webpages = retrieved
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::InternalError("failed to retrieve webpage".to_string()))?;
+
+        add_rankings(&mut webpages, top_websites, &search_query);
+        add_matched_sites(&mut webpages, &query.query);
+        page.annotate(&mut webpages);
+
+        if let Some(fields) = &query.fields {
+            select_fields(&mut webpages, fields);
+        }
+
+        let host_facets = query
+            .host_facets
+            .map(|top_n| merge_host_facets(shard_facets.iter().map(|f| f.as_slice()), top_n));
+
+        Ok(WebsitesResult {
+            num_hits: num_hits.map(|num_hits| page.num_results.unwrap_or(num_hits)),
+            webpages,
+            search_duration_ms: start.elapsed().as_millis(),
+            has_more_results: page.has_more,
+            host_facets,
+            empty_query: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::webpage::{Html, Webpage};
+
+    use super::*;
+
+    fn webpage(i: usize) -> Webpage {
+        Webpage {
+            html: Html::parse(
+                &format!(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website {i}</title>
+                </head>
+                <body>
+                    test
+                </body>
+            </html>
+            "#
+                ),
+                &format!("https://www.{i}.com"),
+            )
+            .unwrap(),
+            host_centrality: (10 - i) as f64,
+            fetch_time_ms: 500,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merged_results_match_single_index() {
+        let mut single = Index::temporary().expect("Unable to open index");
+        let mut first = Index::temporary().expect("Unable to open index");
+        let mut second = Index::temporary().expect("Unable to open index");
+
+        for i in 0..10 {
+            single.insert(webpage(i)).expect("failed to insert webpage");
+
+            if i % 2 == 0 {
+                first.insert(webpage(i)).expect("failed to insert webpage");
+            } else {
+                second.insert(webpage(i)).expect("failed to insert webpage");
+            }
+        }
+
+        single.commit().unwrap();
+        first.commit().unwrap();
+        second.commit().unwrap();
+
+        let query = SearchQuery {
+            query: "test".to_string(),
+            count_results: true,
+            ..Default::default()
+        };
+
+        let baseline = LocalSearcher::new(single).search(&query).unwrap();
+        let merged = MultiSearcher::new(vec![first, second])
+            .search(&query)
+            .unwrap();
+
+        let baseline_urls: Vec<_> = baseline.webpages.iter().map(|w| w.url.clone()).collect();
+        let merged_urls: Vec<_> = merged.webpages.iter().map(|w| w.url.clone()).collect();
+
+        assert_eq!(merged_urls.len(), 10);
+        assert_eq!(merged_urls, baseline_urls);
+        assert_eq!(
+            merged_urls,
+            (0..10)
+                .map(|i| format!("https://www.{i}.com/"))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(merged.num_hits, Some(10));
+        assert_eq!(merged.num_hits, baseline.num_hits);
+        assert!(merged.webpages.iter().all(|w| w.ranking_signals.is_some()));
+    }
+
+    #[test]
+    fn bm25_uses_statistics_of_all_shards() {
+        let page = |url: &str, body: &str| Webpage {
+            html: Html::parse(
+                &format!(
+                    r#"
+            <html>
+                <head>
+                    <title>Example website</title>
+                </head>
+                <body>
+                    {body}
+                </body>
+            </html>
+            "#
+                ),
+                url,
+            )
+            .unwrap(),
+            fetch_time_ms: 500,
+            ..Default::default()
+        };
+
+        let mut single = Index::temporary().expect("Unable to open index");
+        let mut first = Index::temporary().expect("Unable to open index");
+        let mut second = Index::temporary().expect("Unable to open index");
+
+        // "rust" is common in the first shard and rare in the second
+        for (i, shard, body) in [
+            (0, 0, "rust programming language"),
+            (1, 0, "rust compiler errors explained"),
+            (2, 0, "rust ownership and borrowing"),
+            (3, 0, "rust on an old car"),
+            (4, 1, "rust programming language"),
+            (5, 1, "gardening with tomatoes and potatoes"),
+            (6, 1, "a recipe for sourdough bread"),
+            (7, 1, "the history of the bicycle"),
+        ] {
+            let url = format!("https://www.{i}.com");
+            single.insert(page(&url, body)).unwrap();
+
+            if shard == 0 {
+                first.insert(page(&url, body)).unwrap();
+            } else {
+                second.insert(page(&url, body)).unwrap();
+            }
+        }
+
+        single.commit().unwrap();
+        first.commit().unwrap();
+        second.commit().unwrap();
+
+        let query = SearchQuery {
+            query: "rust".to_string(),
+            return_ranking_signals: true,
+            ..Default::default()
+        };
+
+        let bm25 = |webpages: &[DisplayedWebpage], url: &str| {
+            webpages
+                .iter()
+                .find(|w| w.url == url)
+                .and_then(|w| w.ranking_signals.as_ref())
+                .and_then(|signals| signals.get(&crate::ranking::Signal::Bm25CleanBody))
+                .map(|signal| signal.value)
+                .unwrap()
+        };
+
+        let baseline = LocalSearcher::new(single).search(&query).unwrap();
+        let merged = MultiSearcher::new(vec![first, second])
+            .search(&query)
+            .unwrap();
+
+        assert_eq!(merged.webpages.len(), 5);
+
+        // the same page in each shard is scored as in a single index
+        for url in ["https://www.0.com/", "https://www.4.com/"] {
+            let expected = bm25(&baseline.webpages, url);
+            assert!((bm25(&merged.webpages, url) - expected).abs() < 1e-6);
+        }
+        assert!(
+            (bm25(&merged.webpages, "https://www.0.com/")
+                - bm25(&merged.webpages, "https://www.4.com/"))
+            .abs()
+                < 1e-6
+        );
+    }
+}
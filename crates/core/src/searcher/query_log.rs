@@ -0,0 +1,188 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Append-only log of the queries and their results that can be used to evaluate rankings.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::Path,
+    thread::JoinHandle,
+};
+
+use crossbeam_channel::{unbounded, Sender};
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+
+type Redaction = Box<dyn Fn(&str) -> String + Send + Sync>;
+
+/// A single line in the query log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    pub query: String,
+    pub urls: Vec<String>,
+    /// Unix timestamp in seconds.
+    pub timestamp: i64,
+}
+
+enum Message {
+    Entry(QueryLogEntry),
+    Flush(Sender<io::Result<()>>),
+}
+
+/// Writes a [`QueryLogEntry`] as a json line for every logged query.
+/// Entries are sent to a background thread that owns the file, so logging never
+/// waits for disk. Entries might only be visible in the file after
+/// [`QueryLogger::flush`] is called or the logger is dropped.
+pub struct QueryLogger {
+    sender: Option<Sender<Message>>,
+    writer: Option<JoinHandle<()>>,
+    redaction: Redaction,
+}
+
+impl QueryLogger {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let (sender, receiver) = unbounded();
+
+        let writer = std::thread::Builder::new()
+            .name("query-log".to_string())
+            .spawn(move || {
+                let mut writer = BufWriter::new(file);
+
+                for message in receiver {
+                    match message {
+                        Message::Entry(entry) => {
+                            if let Err(err) = write_entry(&mut writer, &entry) {
+                                tracing::error!("failed to write query log entry: {:?}", err);
+                            }
+                        }
+                        Message::Flush(ack) => {
+                            let _ = ack.send(writer.flush());
+                        }
+                    }
+                }
+
+                if let Err(err) = writer.flush() {
+                    tracing::error!("failed to flush query log: {:?}", err);
+                }
+            })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+            redaction: Box::new(|query| query.to_string()),
+        })
+    }
+
+    /// Transform the queries before they are written, e.g. to remove personal information.
+    pub fn with_redaction<F>(mut self, redaction: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.redaction = Box::new(redaction);
+        self
+    }
+
+    /// Redaction that replaces the query by its md5 hash. Identical queries can still
+    /// be grouped without storing the query itself.
+    pub fn hash_query(query: &str) -> String {
+        format!("{:x}", md5::compute(query))
+    }
+
+    pub fn log(&self, query: &str, urls: &[String]) -> Result<()> {
+        let entry = QueryLogEntry {
+            query: (self.redaction)(query),
+            urls: urls.to_vec(),
+            timestamp: chrono::Utc::now().timestamp(),
+        };
+
+        self.send(Message::Entry(entry))
+    }
+
+    /// Wait until all previously logged entries have been written to the file.
+    pub fn flush(&self) -> Result<()> {
+        let (ack, done) = unbounded();
+        self.send(Message::Flush(ack))?;
+
+        done.recv()
+            .map_err(|_| Error::InternalError("query log writer stopped".to_string()))??;
+
+        Ok(())
+    }
+
+    fn send(&self, message: Message) -> Result<()> {
+        self.sender
+            .as_ref()
+            .and_then(|sender| sender.send(message).ok())
+            .ok_or_else(|| Error::InternalError("query log writer stopped".to_string()))?;
+
+        Ok(())
+    }
+}
+
+impl Drop for QueryLogger {
+    fn drop(&mut self) {
+        // closing the channel makes the writer flush and exit
+        self.sender.take();
+
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn write_entry<W: Write>(writer: &mut W, entry: &QueryLogEntry) -> Result<()> {
+    serde_json::to_writer(&mut *writer, entry)?;
+    writer.write_all(b"\n")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gen_temp_path;
+
+    use super::*;
+
+    #[test]
+    fn append() {
+        let path = gen_temp_path();
+
+        let logger = QueryLogger::open(&path).unwrap();
+        logger
+            .log("first query", &["https://www.a.com/".to_string()])
+            .unwrap();
+        drop(logger);
+
+        let logger = QueryLogger::open(&path).unwrap();
+        logger.log("second query", &[]).unwrap();
+        logger.flush().unwrap();
+
+        let entries: Vec<QueryLogEntry> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].query, "first query");
+        assert_eq!(entries[0].urls, vec!["https://www.a.com/".to_string()]);
+        assert_eq!(entries[1].query, "second query");
+        assert!(entries[1].urls.is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Cache of search results so repeated identical queries don't search the index again.
+
+use std::{sync::Mutex, time::Duration};
+
+use itertools::Itertools;
+
+use crate::config::ResultCacheConfig;
+use crate::ttl_cache::TTLCache;
+
+use super::{SearchQuery, WebsitesResult};
+
+/// Number of lookups in a [`ResultCache`] that were served from the cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResultCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+struct Inner {
+    cache: TTLCache<String, WebsitesResult>,
+    /// Generation of the index searcher the cached results were found with.
+    generation: u64,
+    stats: ResultCacheStats,
+}
+
+impl Inner {
+    /// Results from an older generation of the index might be outdated,
+    /// so the entire cache is cleared when the index has been committed.
+    /// Returns whether `generation` is the current generation.
+    fn update_generation(&mut self, generation: u64) -> bool {
+        if generation > self.generation {
+            self.cache.clear();
+            self.generation = generation;
+        }
+
+        generation == self.generation
+    }
+}
+
+pub struct ResultCache {
+    inner: Mutex<Inner>,
+}
+
+impl ResultCache {
+    pub fn new(config: &ResultCacheConfig) -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                cache: TTLCache::with_ttl_and_max_size(
+                    Duration::from_secs(config.ttl_secs),
+                    Some(config.capacity.max(1)),
+                ),
+                generation: 0,
+                stats: ResultCacheStats::default(),
+            }),
+        }
+    }
+
+    /// Key of all the options of `query` that can change its results. Queries
+    /// that only differ in their whitespace have the same key. The key is the
+    /// whole serialized query, so different queries never share cached results.
+    pub fn key(query: &SearchQuery) -> String {
+        let mut normalized = query.clone();
+        normalized.query = normalized.query.split_whitespace().join(" ");
+
+        serde_json::to_string(&normalized).expect("search query should serialize")
+    }
+
+    /// The cached result for `key` if it was found by a searcher of the same
+    /// `generation` as the current one.
+    pub fn get(&self, key: &str, generation: u64) -> Option<WebsitesResult> {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        let res = if inner.update_generation(generation) {
+            inner.cache.get(key).cloned()
+        } else {
+            None
+        };
+
+        match res {
+            Some(_) => inner.stats.hits += 1,
+            None => inner.stats.misses += 1,
+        }
+
+        res
+    }
+
+    pub fn insert(&self, key: String, generation: u64, result: WebsitesResult) {
+        let mut inner = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+
+        // the result is outdated if the index was committed while searching
+        if inner.update_generation(generation) {
+            inner.cache.insert(key, result);
+        }
+    }
+
+    pub fn stats(&self) -> ResultCacheStats {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner()).stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key() {
+        let query = |query: &str, page: usize| SearchQuery {
+            query: query.to_string(),
+            page,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            ResultCache::key(&query("rust  lang ", 0)),
+            ResultCache::key(&query("rust lang", 0))
+        );
+        assert_ne!(
+            ResultCache::key(&query("rust lang", 0)),
+            ResultCache::key(&query("rust lang", 1))
+        );
+        assert_ne!(
+            ResultCache::key(&query("rust lang", 0)),
+            ResultCache::key(&query("rust", 0))
+        );
+    }
+}
@@ -71,12 +71,15 @@ struct Block {
     mask: HashType,
 }
 
+fn block_mask(block_idx: usize) -> HashType {
+    (HashType::MAX << (HashType::BITS as usize - BLOCK_SIZE)) >> (BLOCK_SIZE * block_idx)
+}
+
 impl Block {
     fn new(block_idx: usize) -> Self {
         Self {
             hashes: Default::default(),
-            mask: (HashType::MAX << (HashType::BITS as usize - BLOCK_SIZE))
-                >> (BLOCK_SIZE * block_idx),
+            mask: block_mask(block_idx),
         }
     }
 }
@@ -131,6 +134,66 @@ impl Table {
     }
 }
 
+fn find_root(parents: &mut [usize], mut idx: usize) -> usize {
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+
+    idx
+}
+
+/// Group the items whose hashes are within `K` distance of each other.
+/// Items are grouped transitively, so two items in the same cluster can be more than `K` apart.
+/// Only clusters with more than one item are returned.
+pub fn near_duplicate_clusters<T>(items: impl IntoIterator<Item = (HashType, T)>) -> Vec<Vec<T>> {
+    let mut exact: HashMap<HashType, Vec<T>> = HashMap::new();
+
+    for (hash, item) in items {
+        exact.entry(hash).or_default().push(item);
+    }
+
+    let hashes: Vec<HashType> = exact.keys().copied().collect();
+    let mut parents: Vec<usize> = (0..hashes.len()).collect();
+
+    // by the pigeonhole principle, two hashes within `K` distance
+    // must have at least one of the `K + 1` blocks in common.
+    for block_idx in 0..NUM_BLOCKS {
+        let mask = block_mask(block_idx);
+        let mut buckets: HashMap<HashType, Vec<usize>> = HashMap::new();
+
+        for (idx, hash) in hashes.iter().enumerate() {
+            buckets.entry(hash & mask).or_default().push(idx);
+        }
+
+        for bucket in buckets.values() {
+            for (i, a) in bucket.iter().enumerate() {
+                for b in &bucket[i + 1..] {
+                    if hamming_distance(hashes[*a], hashes[*b]) as usize <= K {
+                        let root_a = find_root(&mut parents, *a);
+                        let root_b = find_root(&mut parents, *b);
+                        parents[root_a] = root_b;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut clusters: HashMap<usize, Vec<T>> = HashMap::new();
+
+    for (idx, hash) in hashes.iter().enumerate() {
+        let root = find_root(&mut parents, idx);
+        if let Some(items) = exact.remove(hash) {
+            clusters.entry(root).or_default().extend(items);
+        }
+    }
+
+    clusters
+        .into_values()
+        .filter(|cluster| cluster.len() > 1)
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -142,6 +205,24 @@ mod tests {
         1.0 - (distance / (HashType::BITS as f64))
     }
 
+    #[test]
+    fn clusters() {
+        let mut clusters = near_duplicate_clusters([
+            (0b0000, "a"),
+            (0b0011, "b"),
+            (HashType::MAX, "c"),
+            (0b0000_0001_1111, "d"),
+            (HashType::MAX, "e"),
+        ]);
+
+        for cluster in &mut clusters {
+            cluster.sort();
+        }
+        clusters.sort();
+
+        assert_eq!(clusters, vec![vec!["a", "b", "d"], vec!["c", "e"]]);
+    }
+
     #[test]
     fn simhash_test() {
         assert_eq!(hash("The cat sat on the mat"), 1696787384511938835);
@@ -87,6 +87,12 @@ impl TextSnippet {
             .collect::<Vec<_>>()
             .join("")
     }
+
+    pub fn has_highlights(&self) -> bool {
+        self.fragments
+            .iter()
+            .any(|f| f.kind == TextSnippetFragmentKind::Highlighted)
+    }
 }
 
 struct SnippetBuilder {
@@ -297,12 +303,7 @@ fn snippet_string(
     let tokenizer = Tokenizer::Normal(Normal::default());
     let snip = snippet_string_builder(text, terms, lang, config.clone(), tokenizer).build();
 
-    if !snip.fragments.is_empty()
-        && snip
-            .fragments
-            .iter()
-            .any(|f| f.kind == TextSnippetFragmentKind::Highlighted)
-    {
+    if !snip.fragments.is_empty() && snip.has_highlights() {
         return snip;
     }
 
@@ -425,7 +426,7 @@ Survey in 2016, 2017, and 2018."#;
             .expect("Search failed");
 
         assert_eq!(result.webpages.len(), 1);
-        assert_eq!(highlight(result.webpages[0].snippet.clone()), format!("{HIGHLIGHTEN_PREFIX}Rust{HIGHLIGHTEN_POSTFIX} is a systems programming {HIGHLIGHTEN_PREFIX}language{HIGHLIGHTEN_POSTFIX} sponsored by Mozilla which describes it as a \"safe, concurrent, practical {HIGHLIGHTEN_PREFIX}language{HIGHLIGHTEN_POSTFIX}\", supporting functional and imperative-procedural paradigms. {HIGHLIGHTEN_PREFIX}Rust{HIGHLIGHTEN_POSTFIX} is syntactically similar to C++[according to whom?"));
+        assert_eq!(highlight(result.webpages[0].snippet.clone().unwrap()), format!("{HIGHLIGHTEN_PREFIX}Rust{HIGHLIGHTEN_POSTFIX} is a systems programming {HIGHLIGHTEN_PREFIX}language{HIGHLIGHTEN_POSTFIX} sponsored by Mozilla which describes it as a \"safe, concurrent, practical {HIGHLIGHTEN_PREFIX}language{HIGHLIGHTEN_POSTFIX}\", supporting functional and imperative-procedural paradigms. {HIGHLIGHTEN_PREFIX}Rust{HIGHLIGHTEN_POSTFIX} is syntactically similar to C++[according to whom?"));
     }
 
     #[test]
@@ -464,7 +465,7 @@ Survey in 2016, 2017, and 2018."#;
             .expect("Search failed");
 
         assert_eq!(result.webpages.len(), 1);
-        assert_eq!(highlight(result.webpages[0].snippet.clone()), format!("Rust is a systems programming language sponsored by Mozilla which {HIGHLIGHTEN_PREFIX}describes{HIGHLIGHTEN_POSTFIX} it as a \"safe, concurrent, practical language\", supporting functional and imperative-procedural paradigms. Rust is syntactically similar to C++[according to whom?"));
+        assert_eq!(highlight(result.webpages[0].snippet.clone().unwrap()), format!("Rust is a systems programming language sponsored by Mozilla which {HIGHLIGHTEN_PREFIX}describes{HIGHLIGHTEN_POSTFIX} it as a \"safe, concurrent, practical language\", supporting functional and imperative-procedural paradigms. Rust is syntactically similar to C++[according to whom?"));
     }
 
     #[test]
@@ -504,11 +505,54 @@ Survey in 2016, 2017, and 2018."#;
 
         assert_eq!(result.webpages.len(), 1);
         assert_eq!(
-            highlight(result.webpages[0].snippet.clone()),
+            highlight(result.webpages[0].snippet.clone().unwrap()),
             format!("Rust is a systems programming language sponsored by Mozilla which describes it as a \"safe, concurrent, practical language\", supporting functional and imperative-procedural {HIGHLIGHTEN_PREFIX}paradigms{HIGHLIGHTEN_POSTFIX}. Rust is syntactically similar to C++[according to whom?")
         );
     }
 
+    #[test]
+    fn description_fallback_without_body_match() {
+        let mut index = Index::temporary().expect("Unable to open index");
+
+        index
+            .insert(
+                Webpage::new(
+                    &format!(
+                        r#"
+                        <html>
+                            <head>
+                                <title>Website for runners</title>
+                                <meta name="description" content="The best place on the web to find shoes for jogging" />
+                            </head>
+                            <body>
+                                {TEST_TEXT}
+                            </body>
+                        </html>
+                    "#
+                    ),
+                    "https://www.example.com/shoes",
+                )
+                .unwrap(),
+            )
+            .expect("failed to insert webpage");
+        index.commit().expect("failed to commit index");
+
+        let searcher = LocalSearcher::from(index);
+
+        let result = searcher
+            .search(&SearchQuery {
+                query: "runners".to_string(),
+                ..Default::default()
+            })
+            .expect("Search failed");
+
+        assert_eq!(result.webpages.len(), 1);
+        assert_eq!(
+            highlight(result.webpages[0].snippet.clone().unwrap()),
+            "The best place on the web to find shoes for jogging"
+        );
+    }
+
     #[test]
     fn empty_query() {
         assert_eq!(
@@ -18,7 +18,7 @@ use std::{array, collections::VecDeque, str::CharIndices};
 
 use logos::{Lexer, Logos};
 use tantivy::tokenizer::{
-    BoxTokenStream, Language, LowerCaser, Stemmer, StopWordFilter, TextAnalyzer,
+    BoxTokenStream, Language, LowerCaser, RemoveLongFilter, Stemmer, StopWordFilter, TextAnalyzer,
 };
 
 use whatlang::Lang;
@@ -30,6 +30,33 @@ use self::{add_space_last::AddSpaceLast, split_preserve::StrSplitPreserve};
 mod add_space_last;
 mod split_preserve;
 
+/// Tokens longer than this many bytes are dropped by the text tokenizers unless
+/// they are configured with another limit.
+/// Long "words" are usually base64 blobs or similar that would only bloat the index.
+pub const DEFAULT_MAX_TOKEN_LEN: usize = 100;
+
+fn remove_long_filter(max_token_len: usize) -> RemoveLongFilter {
+    // the filter only keeps tokens strictly shorter than the limit
+    RemoveLongFilter::limit(max_token_len.saturating_add(1))
+}
+
+/// Whether all the words in `text` are dropped for being longer than `max_token_len`.
+/// Text without any words is not considered too long.
+pub fn has_only_long_tokens(text: &str, max_token_len: usize) -> bool {
+    let mut stream = tantivy::tokenizer::Tokenizer::token_stream(&mut Simple, text);
+
+    let mut has_tokens = false;
+    while let Some(token) = stream.next() {
+        if token.text.len() <= max_token_len {
+            return false;
+        }
+
+        has_tokens = true;
+    }
+
+    has_tokens
+}
+
 struct MyStemmer(Stemmer);
 
 impl From<Lang> for MyStemmer {
@@ -58,12 +85,14 @@ impl From<Lang> for MyStemmer {
 #[derive(Clone)]
 pub enum Tokenizer {
     Normal(Normal),
+    Cased(Cased),
     Identity(Identity),
     Stemmed(Stemmed),
     Bigram(BigramTokenizer),
     Trigram(TrigramTokenizer),
     Json(JsonField),
     SiteOperator(SiteOperatorUrlTokenizer),
+    CharNgram(CharNgramTokenizer),
 }
 
 impl Tokenizer {
@@ -71,15 +100,44 @@ impl Tokenizer {
         Self::Stemmed(Stemmed::default())
     }
 
+    /// Drop tokens longer than `max_token_len` bytes instead of [`DEFAULT_MAX_TOKEN_LEN`].
+    /// Tokenizers that don't split text into words are not affected.
+    pub fn with_max_token_len(self, max_token_len: usize) -> Self {
+        match self {
+            Tokenizer::Normal(tokenizer) => {
+                Tokenizer::Normal(tokenizer.with_max_token_len(max_token_len))
+            }
+            Tokenizer::Cased(tokenizer) => {
+                Tokenizer::Cased(tokenizer.with_max_token_len(max_token_len))
+            }
+            Tokenizer::Stemmed(tokenizer) => {
+                Tokenizer::Stemmed(tokenizer.with_max_token_len(max_token_len))
+            }
+            Tokenizer::Bigram(mut tokenizer) => {
+                tokenizer.inner_tokenizer =
+                    tokenizer.inner_tokenizer.with_max_token_len(max_token_len);
+                Tokenizer::Bigram(tokenizer)
+            }
+            Tokenizer::Trigram(mut tokenizer) => {
+                tokenizer.inner_tokenizer =
+                    tokenizer.inner_tokenizer.with_max_token_len(max_token_len);
+                Tokenizer::Trigram(tokenizer)
+            }
+            tokenizer => tokenizer,
+        }
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             Tokenizer::Normal(_) => Normal::as_str(),
+            Tokenizer::Cased(_) => Cased::as_str(),
             Tokenizer::Stemmed(_) => Stemmed::as_str(),
             Tokenizer::Identity(_) => Identity::as_str(),
             Tokenizer::Bigram(_) => BigramTokenizer::as_str(),
             Tokenizer::Trigram(_) => TrigramTokenizer::as_str(),
             Tokenizer::Json(_) => JsonField::as_str(),
             Tokenizer::SiteOperator(_) => SiteOperatorUrlTokenizer::as_str(),
+            Tokenizer::CharNgram(_) => CharNgramTokenizer::as_str(),
         }
     }
 }
@@ -90,12 +148,23 @@ impl Default for Tokenizer {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Normal {
     stopwords: Option<Vec<String>>,
+    max_token_len: usize,
     analyzer: Option<TextAnalyzer>,
 }
 
+impl Default for Normal {
+    fn default() -> Self {
+        Self {
+            stopwords: None,
+            max_token_len: DEFAULT_MAX_TOKEN_LEN,
+            analyzer: None,
+        }
+    }
+}
+
 impl Normal {
     pub fn as_str() -> &'static str {
         "tokenizer"
@@ -104,11 +173,43 @@ impl Normal {
     pub fn with_stopwords(stopwords: Vec<String>) -> Self {
         Self {
             stopwords: Some(stopwords),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_max_token_len(mut self, max_token_len: usize) -> Self {
+        self.max_token_len = max_token_len;
+        self
+    }
+}
+
+/// Same as [`Normal`] but preserves the case of the tokens.
+#[derive(Clone)]
+pub struct Cased {
+    max_token_len: usize,
+    analyzer: Option<TextAnalyzer>,
+}
+
+impl Default for Cased {
+    fn default() -> Self {
+        Self {
+            max_token_len: DEFAULT_MAX_TOKEN_LEN,
             analyzer: None,
         }
     }
 }
 
+impl Cased {
+    pub fn as_str() -> &'static str {
+        "cased_tokenizer"
+    }
+
+    pub fn with_max_token_len(mut self, max_token_len: usize) -> Self {
+        self.max_token_len = max_token_len;
+        self
+    }
+}
+
 #[derive(Clone)]
 pub struct BigramTokenizer {
     inner_tokenizer: Normal,
@@ -147,12 +248,23 @@ impl TrigramTokenizer {
     }
 }
 
-#[derive(Clone, Default)]
+#[derive(Clone)]
 pub struct Stemmed {
     force_language: Option<Lang>,
+    max_token_len: usize,
     analyzer: Option<TextAnalyzer>,
 }
 
+impl Default for Stemmed {
+    fn default() -> Self {
+        Self {
+            force_language: None,
+            max_token_len: DEFAULT_MAX_TOKEN_LEN,
+            analyzer: None,
+        }
+    }
+}
+
 impl Stemmed {
     pub fn as_str() -> &'static str {
         "stemmed_tokenizer"
@@ -160,9 +272,14 @@ impl Stemmed {
     pub fn with_forced_language(lang: Lang) -> Self {
         Self {
             force_language: Some(lang),
-            analyzer: None,
+            ..Default::default()
         }
     }
+
+    pub fn with_max_token_len(mut self, max_token_len: usize) -> Self {
+        self.max_token_len = max_token_len;
+        self
+    }
 }
 
 #[derive(Clone, Default, Debug)]
@@ -180,12 +297,14 @@ impl tantivy::tokenizer::Tokenizer for Tokenizer {
     fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
         match self {
             Tokenizer::Normal(tokenizer) => tokenizer.token_stream(text),
+            Tokenizer::Cased(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::Stemmed(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::Identity(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::Json(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::Bigram(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::Trigram(tokenizer) => tokenizer.token_stream(text),
             Tokenizer::SiteOperator(tokenizer) => tokenizer.token_stream(text),
+            Tokenizer::CharNgram(tokenizer) => tokenizer.token_stream(text),
         }
     }
 }
@@ -194,7 +313,9 @@ impl tantivy::tokenizer::Tokenizer for Normal {
     type TokenStream<'a> = BoxTokenStream<'a>;
 
     fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
-        let builder = TextAnalyzer::builder(Simple).filter(LowerCaser);
+        let builder = TextAnalyzer::builder(Simple)
+            .filter(remove_long_filter(self.max_token_len))
+            .filter(LowerCaser);
 
         self.analyzer = if let Some(stopwords) = &self.stopwords {
             Some(
@@ -210,11 +331,27 @@ impl tantivy::tokenizer::Tokenizer for Normal {
     }
 }
 
+impl tantivy::tokenizer::Tokenizer for Cased {
+    type TokenStream<'a> = BoxTokenStream<'a>;
+
+    fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
+        self.analyzer = Some(
+            TextAnalyzer::builder(Simple)
+                .filter(remove_long_filter(self.max_token_len))
+                .build(),
+        );
+
+        self.analyzer.as_mut().unwrap().token_stream(text)
+    }
+}
+
 impl tantivy::tokenizer::Tokenizer for Stemmed {
     type TokenStream<'a> = BoxTokenStream<'a>;
 
     fn token_stream<'a>(&'a mut self, text: &'a str) -> Self::TokenStream<'a> {
-        let builder = TextAnalyzer::builder(Simple).filter(LowerCaser);
+        let builder = TextAnalyzer::builder(Simple)
+            .filter(remove_long_filter(self.max_token_len))
+            .filter(LowerCaser);
 
         let lang = match self.force_language {
             Some(lang) => Some(lang),
@@ -737,6 +874,77 @@ impl tantivy::tokenizer::TokenStream for SiteOperatorUrlTokenStream {
     }
 }
 
+/// Number of characters in each token of the [`CharNgramTokenizer`].
+pub const CHAR_NGRAM_SIZE: usize = 3;
+
+/// Overlapping lowercase character n-grams of the entire text, so `"Slug"`
+/// becomes `"slu"` and `"lug"`. Consecutive n-grams have consecutive positions,
+/// which lets a phrase of n-grams match any substring of the text.
+#[derive(Debug, Clone, Default)]
+pub struct CharNgramTokenizer;
+
+impl CharNgramTokenizer {
+    pub fn as_str() -> &'static str {
+        "char_ngram_tokenizer"
+    }
+}
+
+impl tantivy::tokenizer::Tokenizer for CharNgramTokenizer {
+    type TokenStream<'a> = BoxTokenStream<'a>;
+
+    fn token_stream<'a>(&mut self, text: &'a str) -> Self::TokenStream<'a> {
+        let text = text.to_lowercase();
+        let char_boundaries = text
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+
+        BoxTokenStream::new(CharNgramTokenStream {
+            text,
+            char_boundaries,
+            next_pos: 0,
+            token: tantivy::tokenizer::Token::default(),
+        })
+    }
+}
+
+pub struct CharNgramTokenStream {
+    text: String,
+    char_boundaries: Vec<usize>,
+    next_pos: usize,
+    token: tantivy::tokenizer::Token,
+}
+
+impl tantivy::tokenizer::TokenStream for CharNgramTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.next_pos + CHAR_NGRAM_SIZE >= self.char_boundaries.len() {
+            return false;
+        }
+
+        let from = self.char_boundaries[self.next_pos];
+        let to = self.char_boundaries[self.next_pos + CHAR_NGRAM_SIZE];
+
+        self.token.text.clear();
+        self.token.text.push_str(&self.text[from..to]);
+        self.token.offset_from = from;
+        self.token.offset_to = to;
+        self.token.position = self.next_pos;
+
+        self.next_pos += 1;
+
+        true
+    }
+
+    fn token(&self) -> &tantivy::tokenizer::Token {
+        &self.token
+    }
+
+    fn token_mut(&mut self) -> &mut tantivy::tokenizer::Token {
+        &mut self.token
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use tantivy::tokenizer::Tokenizer as _;
@@ -820,6 +1028,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn long_tokens() {
+        let long = "a".repeat(DEFAULT_MAX_TOKEN_LEN + 1);
+        let limit = "b".repeat(DEFAULT_MAX_TOKEN_LEN);
+
+        assert_eq!(
+            tokenize_simple(&format!("this {long} {limit} test")),
+            vec!["this".to_string(), limit.clone(), "test".to_string()]
+        );
+
+        assert!(has_only_long_tokens(&long, DEFAULT_MAX_TOKEN_LEN));
+        assert!(has_only_long_tokens(
+            &format!("{long}.{long}"),
+            DEFAULT_MAX_TOKEN_LEN
+        ));
+        assert!(!has_only_long_tokens(
+            &format!("{long}.test"),
+            DEFAULT_MAX_TOKEN_LEN
+        ));
+        assert!(!has_only_long_tokens(&limit, DEFAULT_MAX_TOKEN_LEN));
+        assert!(!has_only_long_tokens("...", DEFAULT_MAX_TOKEN_LEN));
+    }
+
+    #[test]
+    fn configured_max_token_len() {
+        let tokenize = |tokenizer: Tokenizer, s: &str| {
+            let mut tokenizer = tokenizer;
+            let mut stream = tokenizer.token_stream(s);
+            let mut res = Vec::new();
+
+            while let Some(token) = stream.next() {
+                res.push(token.text.clone());
+            }
+
+            res
+        };
+
+        assert_eq!(
+            tokenize(
+                Tokenizer::default().with_max_token_len(5),
+                "short longer tokens"
+            ),
+            vec!["short".to_string()]
+        );
+        assert_eq!(
+            tokenize(
+                Tokenizer::Cased(Cased::default()).with_max_token_len(5),
+                "Short Longer"
+            ),
+            vec!["Short".to_string()]
+        );
+        assert_eq!(
+            tokenize(
+                Tokenizer::Stemmed(Stemmed::with_forced_language(Lang::Eng)).with_max_token_len(5),
+                "runs running"
+            ),
+            vec!["run".to_string()]
+        );
+
+        let long = "a".repeat(DEFAULT_MAX_TOKEN_LEN + 1);
+        assert_eq!(
+            tokenize(
+                Tokenizer::default().with_max_token_len(DEFAULT_MAX_TOKEN_LEN * 2),
+                &long
+            ),
+            vec![long.clone()]
+        );
+
+        assert!(has_only_long_tokens("longer", 5));
+        assert!(!has_only_long_tokens("short", 5));
+        assert!(!has_only_long_tokens(&long, DEFAULT_MAX_TOKEN_LEN * 2));
+    }
+
     #[test]
     fn out_of_bounds_crash() {
         tokenize_json(
@@ -1002,6 +1283,38 @@ key1.key2="this\" is @ a # test""#;
         );
     }
 
+    #[test]
+    fn char_ngram_tokenizer() {
+        let tokenize = |s: &str| {
+            let mut res = Vec::new();
+            let mut tokenizer = Tokenizer::CharNgram(CharNgramTokenizer);
+            let mut stream = tokenizer.token_stream(s);
+
+            while let Some(token) = stream.next() {
+                res.push((token.text.clone(), token.position));
+            }
+
+            res
+        };
+
+        assert!(tokenize("").is_empty());
+        assert!(tokenize("ab").is_empty());
+        assert_eq!(tokenize("abc"), vec![("abc".to_string(), 0)]);
+        assert_eq!(
+            tokenize("A/b-Cd"),
+            vec![
+                ("a/b".to_string(), 0),
+                ("/b-".to_string(), 1),
+                ("b-c".to_string(), 2),
+                ("-cd".to_string(), 3),
+            ]
+        );
+        assert_eq!(
+            tokenize("æøåä"),
+            vec![("æøå".to_string(), 0), ("øåä".to_string(), 1)]
+        );
+    }
+
     #[test]
     fn han() {
         assert_eq!(
@@ -14,17 +14,37 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 use std::{
+    borrow::Borrow,
     collections::{HashMap, VecDeque},
     hash::Hash,
     time::{Duration, SystemTime},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// The entry was older than the ttl.
+    Expired,
+    /// The entry was the oldest when the cache was full.
+    MaxSize,
+}
+
+/// Number of entries that have been evicted from the cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EvictionStats {
+    pub expired: u64,
+    pub max_size: u64,
+}
+
+type EvictionCallback<K, V> = Box<dyn FnMut(&K, &V, EvictionReason) + Send + Sync>;
+
 pub struct TTLCache<K, V> {
     ttl: Duration,
     data: HashMap<K, V>,
     insertion_order: VecDeque<K>,
     insertion_times: HashMap<K, SystemTime>,
     max_size: Option<usize>,
+    eviction_stats: EvictionStats,
+    on_evict: Option<EvictionCallback<K, V>>,
 }
 
 impl<K: Hash + Eq + Clone, V> TTLCache<K, V> {
@@ -38,9 +58,24 @@ impl<K: Hash + Eq + Clone, V> TTLCache<K, V> {
             insertion_order: VecDeque::new(),
             insertion_times: HashMap::new(),
             max_size,
+            eviction_stats: EvictionStats::default(),
+            on_evict: None,
         }
     }
 
+    /// Call `on_evict` whenever an entry is evicted from the cache.
+    pub fn with_eviction_callback<F>(mut self, on_evict: F) -> Self
+    where
+        F: FnMut(&K, &V, EvictionReason) + Send + Sync + 'static,
+    {
+        self.on_evict = Some(Box::new(on_evict));
+        self
+    }
+
+    pub fn eviction_stats(&self) -> EvictionStats {
+        self.eviction_stats
+    }
+
     pub fn insert(&mut self, key: K, val: V) {
         self.prune_old_entries();
         let current_time = SystemTime::now();
@@ -60,7 +95,11 @@ impl<K: Hash + Eq + Clone, V> TTLCache<K, V> {
         self.insertion_order.push_back(key);
     }
 
-    pub fn get(&self, key: &K) -> Option<&V> {
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
         let current_time = SystemTime::now();
 
         self.insertion_times.get(key).and_then(|insertion_time| {
@@ -72,14 +111,35 @@ impl<K: Hash + Eq + Clone, V> TTLCache<K, V> {
         })
     }
 
+    /// Remove all entries without counting them as evicted.
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.insertion_order.clear();
+        self.insertion_times.clear();
+    }
+
+    fn evict_front(&mut self, reason: EvictionReason) {
+        let front = self.insertion_order.pop_front().unwrap();
+        self.insertion_times.remove(&front);
+
+        if let Some(val) = self.data.remove(&front) {
+            match reason {
+                EvictionReason::Expired => self.eviction_stats.expired += 1,
+                EvictionReason::MaxSize => self.eviction_stats.max_size += 1,
+            }
+
+            if let Some(on_evict) = self.on_evict.as_mut() {
+                on_evict(&front, &val, reason);
+            }
+        }
+    }
+
     fn prune_old_entries(&mut self) {
         let current_time = SystemTime::now();
 
         if let Some(max_size) = self.max_size {
             while self.data.len() >= max_size {
-                let front = self.insertion_order.pop_front().unwrap();
-                self.insertion_times.remove(&front);
-                self.data.remove(&front);
+                self.evict_front(EvictionReason::MaxSize);
             }
         }
 
@@ -92,9 +152,7 @@ impl<K: Hash + Eq + Clone, V> TTLCache<K, V> {
                 break;
             }
 
-            let front = self.insertion_order.pop_front().unwrap();
-            self.insertion_times.remove(&front);
-            self.data.remove(&front);
+            self.evict_front(EvictionReason::Expired);
         }
     }
 }
@@ -140,5 +198,46 @@ mod tests {
 
         assert_eq!(cache.get(&0), None);
         assert_eq!(cache.get(&1), Some(&1));
+        assert_eq!(
+            cache.eviction_stats(),
+            EvictionStats {
+                expired: 0,
+                max_size: 1
+            }
+        );
+    }
+
+    #[test]
+    fn eviction_stats() {
+        use std::sync::{Arc, Mutex};
+
+        let evicted = Arc::new(Mutex::new(Vec::new()));
+        let mut cache = TTLCache::with_ttl(Duration::from_millis(20)).with_eviction_callback({
+            let evicted = Arc::clone(&evicted);
+            move |key, val, reason| evicted.lock().unwrap().push((*key, *val, reason))
+        });
+
+        cache.insert(0, 10);
+        cache.insert(1, 11);
+        assert_eq!(cache.eviction_stats(), EvictionStats::default());
+
+        std::thread::sleep(Duration::from_millis(30));
+        cache.insert(2, 12);
+
+        assert_eq!(
+            cache.eviction_stats(),
+            EvictionStats {
+                expired: 2,
+                max_size: 0
+            }
+        );
+        assert_eq!(
+            *evicted.lock().unwrap(),
+            vec![
+                (0, 10, EvictionReason::Expired),
+                (1, 11, EvictionReason::Expired)
+            ]
+        );
+        assert_eq!(cache.get(&2), Some(&12));
     }
 }
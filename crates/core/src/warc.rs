@@ -15,11 +15,14 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::distributed::retry_strategy::ExponentialBackoff;
-use crate::{config::S3Config, config::WarcSource, Error, Result};
+use crate::{
+    config::DuplicateRecordStrategy, config::HttpConfig, config::S3Config, config::WarcSource,
+    Error, Result,
+};
 use std::collections::BTreeMap;
 use std::fmt::Display;
 use std::fs::File;
-use std::io::{BufRead, BufReader, Cursor, Read, Seek, Write};
+use std::io::{BufRead, BufReader, BufWriter, Cursor, Read, Seek, Write};
 use std::path::Path;
 use std::str::FromStr;
 use std::thread::sleep;
@@ -28,9 +31,10 @@ use std::time::Duration;
 use flate2::read::MultiGzDecoder;
 use flate2::write::GzEncoder;
 use flate2::Compression;
-use fnv::FnvHashSet;
+use fnv::{FnvHashMap, FnvHashSet};
 #[cfg(test)]
 use proptest_derive::Arbitrary;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 
 use tracing::{debug, trace};
 
@@ -81,10 +85,45 @@ impl WarcFile {
     }
 
     pub fn records(&self) -> RecordIterator<&[u8]> {
-        RecordIterator {
-            reader: BufReader::new(MultiGzDecoder::new(&self.bytes[..])),
-            num_reads: 0,
-        }
+        RecordIterator::new(&self.bytes[..])
+    }
+
+    /// Iterate the records of the file where only a single record is kept for each url.
+    /// Records that fail to parse are passed through untouched.
+    pub fn deduplicated_records(
+        &self,
+        strategy: DuplicateRecordStrategy,
+    ) -> impl Iterator<Item = Result<WarcRecord>> + '_ {
+        let last_occurrence: Option<FnvHashMap<md5::Digest, usize>> = match strategy {
+            DuplicateRecordStrategy::KeepFirst => None,
+            DuplicateRecordStrategy::KeepLast => Some(
+                self.records()
+                    .enumerate()
+                    .filter_map(|(idx, record)| {
+                        record
+                            .ok()
+                            .map(|record| (md5::compute(&record.request.url), idx))
+                    })
+                    .collect(),
+            ),
+        };
+
+        let mut seen_url_hashes = FnvHashSet::default();
+
+        self.records()
+            .enumerate()
+            .filter(move |(idx, record)| match record {
+                Ok(record) => {
+                    let url_hash = md5::compute(&record.request.url);
+
+                    match &last_occurrence {
+                        Some(last_occurrence) => last_occurrence.get(&url_hash) == Some(idx),
+                        None => seen_url_hashes.insert(url_hash),
+                    }
+                }
+                Err(_) => true,
+            })
+            .map(|(_, record)| record)
     }
 
     pub(crate) fn download(source: &WarcSource, warc_path: &str) -> Result<Self> {
@@ -108,9 +147,7 @@ impl WarcFile {
             .take(35)
         {
             let res = match source.clone() {
-                WarcSource::HTTP(config) => {
-                    WarcFile::download_from_http(warc_path, config.base_url, buf)
-                }
+                WarcSource::HTTP(config) => WarcFile::download_from_http(warc_path, &config, buf),
                 WarcSource::Local(config) => {
                     WarcFile::load_from_folder(warc_path, &config.folder, buf)
                 }
@@ -143,24 +180,87 @@ impl WarcFile {
         Ok(())
     }
 
-    fn download_from_http<W: Write + Seek>(
-        warc_path: &str,
-        base_url: String,
-        buf: &mut W,
-    ) -> Result<()> {
-        let mut url = base_url;
+    fn http_url(base_url: &str, warc_path: &str) -> String {
+        let mut url = base_url.to_string();
         if !url.ends_with('/') {
             url += "/";
         }
         url += warc_path;
 
-        let client = reqwest::blocking::ClientBuilder::new()
+        url
+    }
+
+    fn http_client(config: &HttpConfig) -> Result<reqwest::blocking::Client> {
+        let mut headers = HeaderMap::new();
+
+        for (key, value) in &config.headers {
+            headers.insert(
+                HeaderName::from_bytes(key.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        if let Some(token) = &config.bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {token}"))?;
+            value.set_sensitive(true);
+            headers.insert(reqwest::header::AUTHORIZATION, value);
+        }
+
+        let mut builder = reqwest::blocking::ClientBuilder::new()
+            .default_headers(headers)
             .tcp_keepalive(None)
             .pool_idle_timeout(Duration::from_secs(30 * 60))
             .timeout(Duration::from_secs(30 * 60))
-            .connect_timeout(Duration::from_secs(30 * 60))
-            .build()?;
-        let res = client.get(url).send()?;
+            .connect_timeout(Duration::from_secs(30 * 60));
+
+        if let Some(user_agent) = &config.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Download `length` bytes starting at `offset` of the warc file using a http range request.
+    /// Each record in a warc file is usually compressed as a separate gzip member,
+    /// so a single record can be re-extracted if its offset and length are known.
+    /// Fails with [`Error::RangeIgnored`] if the server returns the entire file instead,
+    /// as the caller is better off downloading the whole file once in that case.
+    pub fn download_range_from_http(
+        config: &HttpConfig,
+        warc_path: &str,
+        offset: u64,
+        length: u64,
+    ) -> Result<Self> {
+        if length == 0 {
+            return Ok(Self::new(Vec::new()));
+        }
+
+        let last = offset
+            .checked_add(length - 1)
+            .ok_or(Error::InvalidByteRange { offset, length })?;
+
+        let url = Self::http_url(&config.base_url, warc_path);
+        let res = Self::http_client(config)?
+            .get(url)
+            .header(reqwest::header::RANGE, format!("bytes={offset}-{last}"))
+            .send()?;
+
+        let bytes = match res.status().as_u16() {
+            206 => res.bytes()?.to_vec(),
+            200 => return Err(Error::RangeIgnored.into()),
+            _ => return Err(Error::DownloadFailed.into()),
+        };
+
+        Ok(Self::new(bytes))
+    }
+
+    fn download_from_http<W: Write + Seek>(
+        warc_path: &str,
+        config: &HttpConfig,
+        buf: &mut W,
+    ) -> Result<()> {
+        let url = Self::http_url(&config.base_url, warc_path);
+        let res = Self::http_client(config)?.get(url).send()?;
 
         if res.status().as_u16() != 200 {
             return Err(Error::DownloadFailed.into());
@@ -179,22 +279,9 @@ impl WarcFile {
         config: &S3Config,
         buf: &mut W,
     ) -> Result<()> {
-        let bucket = s3::Bucket::new(
-            &config.bucket,
-            s3::Region::Custom {
-                region: "".to_string(),
-                endpoint: config.endpoint.clone(),
-            },
-            s3::creds::Credentials {
-                access_key: Some(config.access_key.clone()),
-                secret_key: Some(config.secret_key.clone()),
-                security_token: None,
-                session_token: None,
-                expiration: None,
-            },
-        )?
-        .with_path_style()
-        .with_request_timeout(Duration::from_secs(30 * 60));
+        let bucket = config
+            .bucket()?
+            .with_request_timeout(Duration::from_secs(30 * 60));
 
         let res = bucket.get_object_blocking(warc_path)?;
 
@@ -244,21 +331,36 @@ pub enum PayloadType {
     Pdf,
     Rss,
     Atom,
+    /// Any other mime type, e.g. `application/json` or `image/png`.
+    Other(
+        // must not generate any of the known types, or it would not survive a roundtrip
+        #[cfg_attr(test, proptest(regex = "x-[a-z]{1,10}/[a-z]{1,10}"))] String,
+    ),
 }
 
 impl FromStr for PayloadType {
     type Err = Error;
 
+    /// Parse a mime type. Parameters like `; charset=utf-8` are ignored.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
+        let mime = s
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+
+        match mime.as_str() {
+            "" => Err(Error::WarcParse("Empty payload type".to_string())),
             "application/html" => Ok(Self::Html),
             "text/html" => Ok(Self::Html),
+            "application/xhtml+xml" => Ok(Self::Html),
             "application/pdf" => Ok(Self::Pdf),
             "application/rss" => Ok(Self::Rss),
             "application/rss+xml" => Ok(Self::Rss),
             "application/atom" => Ok(Self::Atom),
             "application/atom+xml" => Ok(Self::Atom),
-            _ => Err(Error::WarcParse("Unknown payload type".to_string())),
+            _ => Ok(Self::Other(mime)),
         }
     }
 }
@@ -270,6 +372,7 @@ impl Display for PayloadType {
             Self::Pdf => "application/pdf",
             Self::Rss => "application/rss",
             Self::Atom => "application/atom",
+            Self::Other(mime) => mime,
         };
         write!(f, "{}", str)
     }
@@ -286,16 +389,31 @@ impl Response {
     fn from_raw(record: RawWarcRecord) -> Result<Self> {
         let content = decode(&record.content[..]);
 
-        let (_header, content) = content
+        let (header, content) = content
             .split_once("\r\n\r\n")
             .ok_or(Error::WarcParse("Invalid http body".to_string()))?;
 
+        // the identified payload type is based on the content itself, so it is
+        // preferred over the content type reported by the server
+        let payload_type = record
+            .header
+            .get("WARC-IDENTIFIED-PAYLOAD-TYPE")
+            .and_then(|p| PayloadType::from_str(p).ok())
+            .or_else(|| {
+                header.lines().find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+
+                    if name.trim().eq_ignore_ascii_case("content-type") {
+                        PayloadType::from_str(value).ok()
+                    } else {
+                        None
+                    }
+                })
+            });
+
         Ok(Self {
             body: content.to_string(),
-            payload_type: record
-                .header
-                .get("WARC-IDENTIFIED-PAYLOAD-TYPE")
-                .and_then(|p| PayloadType::from_str(p).ok()),
+            payload_type,
         })
     }
 }
@@ -334,6 +452,16 @@ pub struct RecordIterator<R: Read> {
 }
 
 impl<R: Read> RecordIterator<R> {
+    /// Iterate the records of a gzipped warc stream without loading it into memory.
+    /// The stream may consist of several concatenated gzip members (multistream),
+    /// which is how most warc files are compressed.
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(MultiGzDecoder::new(reader)),
+            num_reads: 0,
+        }
+    }
+
     fn next_raw(&mut self) -> Option<Result<RawWarcRecord>> {
         let mut version = String::new();
 
@@ -433,8 +561,21 @@ impl<R: Read> Iterator for RecordIterator<R> {
     type Item = Result<WarcRecord>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        let mut pending = None;
         if self.num_reads == 0 {
-            self.next_raw()?.ok()?; // skip warc_info
+            let first = self.next_raw()?.ok()?;
+
+            // skip warc_info. A stream fetched from the middle of a warc file
+            // (e.g. with a range request) starts directly with a record.
+            let is_warcinfo = first
+                .header
+                .get("WARC-TYPE")
+                .map(|warc_type| warc_type.eq_ignore_ascii_case("warcinfo"))
+                .unwrap_or(false);
+
+            if !is_warcinfo {
+                pending = Some(first);
+            }
         }
         self.num_reads += 1;
 
@@ -442,7 +583,7 @@ impl<R: Read> Iterator for RecordIterator<R> {
         let mut response = None;
         let mut metadata = None;
 
-        while let Some(item) = self.next_raw() {
+        while let Some(item) = pending.take().map(Ok).or_else(|| self.next_raw()) {
             if item.is_err() {
                 return Some(Err(item.err().unwrap()));
             }
@@ -565,6 +706,69 @@ impl DeduplicatedWarcWriter {
     }
 }
 
+fn write_warcinfo<W: Write>(writer: &mut W) -> std::io::Result<()> {
+    writer.write_all("WARC/1.0\r\n".as_bytes())?;
+    writer.write_all("WARC-Type: warcinfo\r\n".as_bytes())?;
+
+    let date = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
+    let content = format!("ISPARTOF: crawl[{}]", date);
+    let content_len = content.len();
+
+    writer.write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+    writer.write_all("\r\n".as_bytes())?;
+    writer.write_all(content.as_bytes())?;
+    writer.write_all("\r\n\r\n".as_bytes())?;
+
+    Ok(())
+}
+
+fn write_record<W: Write>(writer: &mut W, record: &WarcRecord) -> std::io::Result<()> {
+    writer.write_all("WARC/1.0\r\n".as_bytes())?;
+
+    writer.write_all("WARC-Type: request\r\n".as_bytes())?;
+    writer.write_all(format!("WARC-Target-URI: {}\r\n", record.request.url).as_bytes())?;
+    writer.write_all("Content-Length: 0\r\n".as_bytes())?;
+    writer.write_all("\r\n".as_bytes())?;
+    writer.write_all("\r\n\r\n".as_bytes())?;
+
+    writer.write_all("WARC/1.0\r\n".as_bytes())?;
+    writer.write_all("WARC-Type: response\r\n".as_bytes())?;
+
+    if let Some(payload_type) = &record.response.payload_type {
+        writer.write_all(
+            format!(
+                "WARC-Identified-Payload-Type: {}\r\n",
+                payload_type.to_string()
+            )
+            .as_bytes(),
+        )?;
+    }
+
+    let body = record.response.body.as_bytes();
+    let content_len = body.len() + 4; // +4 is for the \r\n\r\n between http header and body
+    writer.write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+
+    writer.write_all("\r\n".as_bytes())?;
+    // write the http-header here if we want to in the future
+    writer.write_all("\r\n\r\n".as_bytes())?;
+
+    writer.write_all(body)?;
+    writer.write_all("\r\n\r\n".as_bytes())?;
+
+    writer.write_all("WARC/1.0\r\n".as_bytes())?;
+    writer.write_all("WARC-Type: metadata\r\n".as_bytes())?;
+
+    let body = format!("fetchTimeMs: {}", record.metadata.fetch_time_ms);
+    let content_len = body.len();
+
+    writer.write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+    writer.write_all("\r\n".as_bytes())?;
+    writer.write_all(body.as_bytes())?;
+    writer.write_all("\r\n\r\n".as_bytes())?;
+
+    Ok(())
+}
+
 pub struct WarcWriter {
     num_writes: usize,
     writer: GzEncoder<Vec<u8>>,
@@ -574,22 +778,7 @@ impl WarcWriter {
     pub fn new() -> Self {
         let mut writer = GzEncoder::new(Default::default(), Compression::best());
 
-        writer.write_all("WARC/1.0\r\n".as_bytes()).unwrap();
-        writer
-            .write_all("WARC-Type: warcinfo\r\n".as_bytes())
-            .unwrap();
-
-        let date = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string();
-        let content = format!("ISPARTOF: crawl[{}]", date);
-        let content_len = content.len();
-
-        writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())
-            .unwrap();
-        writer.write_all("\r\n".as_bytes()).unwrap();
-        writer.write_all(content.as_bytes()).unwrap();
-        writer.write_all("\r\n\r\n".as_bytes()).unwrap();
-
+        write_warcinfo(&mut writer).unwrap();
         writer.flush().unwrap();
 
         Self {
@@ -599,67 +788,87 @@ impl WarcWriter {
     }
 
     pub fn write(&mut self, record: &WarcRecord) -> Result<()> {
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
-
-        self.writer.write_all("WARC-Type: request\r\n".as_bytes())?;
-        self.writer
-            .write_all(format!("WARC-Target-URI: {}\r\n", record.request.url).as_bytes())?;
-        self.writer.write_all("Content-Length: 0\r\n".as_bytes())?;
-        self.writer.write_all("\r\n".as_bytes())?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
-
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
-        self.writer
-            .write_all("WARC-Type: response\r\n".as_bytes())?;
-
-        if let Some(payload_type) = &record.response.payload_type {
-            self.writer.write_all(
-                format!(
-                    "WARC-Identified-Payload-Type: {}\r\n",
-                    payload_type.to_string()
-                )
-                .as_bytes(),
-            )?;
-        }
+        write_record(&mut self.writer, record)?;
 
-        let body = record.response.body.as_bytes();
-        let content_len = body.len() + 4; // +4 is for the \r\n\r\n between http header and body
-        self.writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
+        self.writer.flush().unwrap();
 
-        self.writer.write_all("\r\n".as_bytes())?;
-        // write the http-header here if we want to in the future
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+        self.num_writes += 1;
 
-        self.writer.write_all(body)?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+        Ok(())
+    }
 
-        self.writer.write_all("WARC/1.0\r\n".as_bytes())?;
-        self.writer
-            .write_all("WARC-Type: metadata\r\n".as_bytes())?;
+    pub fn finish(self) -> Result<Vec<u8>> {
+        Ok(self.writer.finish()?)
+    }
 
-        let body = format!("fetchTimeMs: {}", record.metadata.fetch_time_ms);
-        let content_len = body.len();
+    pub fn num_bytes(&self) -> usize {
+        self.writer.get_ref().len()
+    }
 
-        self.writer
-            .write_all(format!("Content-Length: {content_len}\r\n").as_bytes())?;
-        self.writer.write_all("\r\n".as_bytes())?;
-        self.writer.write_all(body.as_bytes())?;
-        self.writer.write_all("\r\n\r\n".as_bytes())?;
+    pub fn num_writes(&self) -> usize {
+        self.num_writes
+    }
+}
 
-        self.writer.flush().unwrap();
+/// Writes warc records directly to the underlying writer instead of buffering
+/// the entire file in memory. Each record is compressed as a separate gzip member,
+/// so the output can be read back with [`RecordIterator`] and files can be
+/// concatenated or appended to.
+pub struct StreamingWarcWriter<W: Write> {
+    num_writes: usize,
+    num_bytes: usize,
+    writer: W,
+}
+
+impl StreamingWarcWriter<BufWriter<File>> {
+    /// Create a new warc file at `path`. An existing file will be truncated.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = File::create(path)?;
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write> StreamingWarcWriter<W> {
+    pub fn new(writer: W) -> Result<Self> {
+        let mut res = Self {
+            num_writes: 0,
+            num_bytes: 0,
+            writer,
+        };
+
+        let mut member = GzEncoder::new(Vec::new(), Compression::best());
+        write_warcinfo(&mut member)?;
+        res.write_member(member)?;
+
+        Ok(res)
+    }
+
+    fn write_member(&mut self, member: GzEncoder<Vec<u8>>) -> Result<()> {
+        let bytes = member.finish()?;
+        self.writer.write_all(&bytes)?;
+        self.num_bytes += bytes.len();
+
+        Ok(())
+    }
+
+    pub fn write(&mut self, record: &WarcRecord) -> Result<()> {
+        let mut member = GzEncoder::new(Vec::new(), Compression::best());
+        write_record(&mut member, record)?;
+        self.write_member(member)?;
 
         self.num_writes += 1;
 
         Ok(())
     }
 
-    pub fn finish(self) -> Result<Vec<u8>> {
-        Ok(self.writer.finish()?)
+    /// Flush the underlying writer and return it.
+    pub fn finish(mut self) -> Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
     }
 
     pub fn num_bytes(&self) -> usize {
-        self.writer.get_ref().len()
+        self.num_bytes
     }
 
     pub fn num_writes(&self) -> usize {
@@ -680,6 +889,7 @@ mod tests {
     use flate2::write::GzEncoder;
     use flate2::Compression;
     use proptest::prelude::*;
+    use std::collections::HashMap;
     use std::io::Write;
 
     #[test]
@@ -727,6 +937,87 @@ mod tests {
         assert_eq!(records[0].metadata.fetch_time_ms, 937);
     }
 
+    #[test]
+    fn multistream_gzip() {
+        let first = b"\
+                warc/1.0\r\n\
+                warc-tYPE: WARCINFO\r\n\
+                cONTENT-lENGTH: 25\r\n\
+                \r\n\
+                ISpARToF: cc-main-2022-05\r\n\
+                \r\n\
+                warc/1.0\r\n\
+                WARC-Target-URI: https://a.com\r\n\
+                warc-tYPE: request\r\n\
+                cONTENT-lENGTH: 15\r\n\
+                \r\n\
+                body of request\r\n\
+                \r\n\
+                warc/1.0\r\n\
+                warc-tYPE: response\r\n\
+                cONTENT-lENGTH: 22\r\n\
+                \r\n\
+                http-body\r\n\
+                \r\n\
+                body of a\r\n\
+                \r\n\
+                warc/1.0\r\n\
+                warc-tYPE: metadata\r\n\
+                cONTENT-lENGTH: 16\r\n\
+                \r\n\
+                fetchTimeMs: 937\r\n\
+                \r\n";
+
+        let second = b"\
+                warc/1.0\r\n\
+                WARC-Target-URI: https://b.com\r\n\
+                warc-tYPE: request\r\n\
+                cONTENT-lENGTH: 15\r\n\
+                \r\n\
+                body of request\r\n\
+                \r\n\
+                warc/1.0\r\n\
+                warc-tYPE: response\r\n\
+                cONTENT-lENGTH: 22\r\n\
+                \r\n\
+                http-body\r\n\
+                \r\n\
+                body of b\r\n\
+                \r\n\
+                warc/1.0\r\n\
+                warc-tYPE: metadata\r\n\
+                cONTENT-lENGTH: 16\r\n\
+                \r\n\
+                fetchTimeMs: 123\r\n\
+                \r\n";
+
+        let mut compressed = Vec::new();
+        for member in [&first[..], &second[..]] {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(member).unwrap();
+            compressed.extend(e.finish().unwrap());
+        }
+
+        let records: Vec<WarcRecord> = WarcFile::new(compressed.clone())
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(&records[0].request.url, "https://a.com");
+        assert_eq!(&records[0].response.body, "body of a");
+        assert_eq!(records[0].metadata.fetch_time_ms, 937);
+        assert_eq!(&records[1].request.url, "https://b.com");
+        assert_eq!(&records[1].response.body, "body of b");
+        assert_eq!(records[1].metadata.fetch_time_ms, 123);
+
+        let streamed: Vec<WarcRecord> = RecordIterator::new(&compressed[..])
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(streamed, records);
+    }
+
     #[test]
     fn internet_archive_parse() {
         if !Path::new("../../data/internet_archive.warc.gz").exists() {
@@ -748,6 +1039,26 @@ mod tests {
         assert!(records > 0);
     }
 
+    #[test]
+    fn parse_payload_type() {
+        assert_eq!(
+            PayloadType::from_str("text/html").unwrap(),
+            PayloadType::Html
+        );
+        assert_eq!(
+            PayloadType::from_str("Text/HTML; charset=utf-8").unwrap(),
+            PayloadType::Html
+        );
+        assert_eq!(
+            PayloadType::from_str("application/json").unwrap(),
+            PayloadType::Other("application/json".to_string())
+        );
+        assert!(PayloadType::from_str("").is_err());
+
+        let other = PayloadType::Other("image/png".to_string());
+        assert_eq!(PayloadType::from_str(&other.to_string()).unwrap(), other);
+    }
+
     #[test]
     fn writer_reader_invariant() {
         let mut writer = WarcWriter::new();
@@ -796,6 +1107,126 @@ mod tests {
         assert_eq!(records[1].metadata.fetch_time_ms, 4242);
     }
 
+    #[test]
+    fn streaming_writer_reader_invariant() {
+        let records = vec![
+            WarcRecord {
+                request: Request {
+                    url: "https://a.com".to_string(),
+                },
+                response: Response {
+                    body: "body of a".to_string(),
+                    payload_type: Some(PayloadType::Html),
+                },
+                metadata: Metadata {
+                    fetch_time_ms: 1337,
+                },
+            },
+            WarcRecord {
+                request: Request {
+                    url: "https://b.com/🦀".to_string(),
+                },
+                response: Response {
+                    body: "body of b\r\n\r\nwith blank lines".to_string(),
+                    payload_type: Some(PayloadType::Rss),
+                },
+                metadata: Metadata {
+                    fetch_time_ms: 4242,
+                },
+            },
+            WarcRecord {
+                request: Request {
+                    url: "https://c.com".to_string(),
+                },
+                response: Response {
+                    body: String::new(),
+                    payload_type: None,
+                },
+                metadata: Metadata { fetch_time_ms: 0 },
+            },
+        ];
+
+        let path = crate::gen_temp_path().join("streaming.warc.gz");
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        let mut writer = StreamingWarcWriter::create(&path).unwrap();
+        for record in &records {
+            writer.write(record).unwrap();
+        }
+        assert_eq!(writer.num_writes(), records.len());
+        writer.finish().unwrap();
+
+        let read_records: Vec<WarcRecord> = RecordIterator::new(File::open(&path).unwrap())
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records, read_records);
+        assert_eq!(
+            WarcFile::open(&path)
+                .unwrap()
+                .records()
+                .map(|res| res.unwrap())
+                .collect::<Vec<_>>(),
+            records
+        );
+    }
+
+    fn html_record(url: &str, body: &str) -> WarcRecord {
+        WarcRecord {
+            request: Request {
+                url: url.to_string(),
+            },
+            response: Response {
+                body: body.to_string(),
+                payload_type: Some(PayloadType::Html),
+            },
+            metadata: Metadata { fetch_time_ms: 0 },
+        }
+    }
+
+    #[test]
+    fn deduplicated_records() {
+        let mut writer = WarcWriter::new();
+        writer
+            .write(&html_record("https://a.com", "first a"))
+            .unwrap();
+        writer.write(&html_record("https://b.com", "b")).unwrap();
+        writer
+            .write(&html_record("https://a.com", "second a"))
+            .unwrap();
+        let file = WarcFile::new(writer.finish().unwrap());
+
+        assert_eq!(file.records().count(), 3);
+
+        let first: Vec<_> = file
+            .deduplicated_records(DuplicateRecordStrategy::KeepFirst)
+            .map(|res| res.unwrap())
+            .map(|record| (record.request.url, record.response.body))
+            .collect();
+
+        assert_eq!(
+            first,
+            vec![
+                ("https://a.com".to_string(), "first a".to_string()),
+                ("https://b.com".to_string(), "b".to_string()),
+            ]
+        );
+
+        let last: Vec<_> = file
+            .deduplicated_records(DuplicateRecordStrategy::KeepLast)
+            .map(|res| res.unwrap())
+            .map(|record| (record.request.url, record.response.body))
+            .collect();
+
+        assert_eq!(
+            last,
+            vec![
+                ("https://b.com".to_string(), "b".to_string()),
+                ("https://a.com".to_string(), "second a".to_string()),
+            ]
+        );
+    }
+
     #[test]
     fn writer_utf8() {
         let utf8 = "🦀";
@@ -857,6 +1288,199 @@ mod tests {
         assert_eq!(records[0].metadata.fetch_time_ms, 0);
     }
 
+    /// Serve a single http request on a random local port. `respond` gets the request
+    /// headers (with lowercase keys) and returns the status line and body of the response.
+    fn serve_once<F>(respond: F) -> (String, std::thread::JoinHandle<BTreeMap<String, String>>)
+    where
+        F: FnOnce(&BTreeMap<String, String>) -> (&'static str, Vec<u8>) + Send + 'static,
+    {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+
+            let mut headers = BTreeMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+
+                if let Some((key, value)) = line.split_once(':') {
+                    headers.insert(key.to_lowercase(), value.trim().to_string());
+                }
+            }
+
+            let (status, body) = respond(&headers);
+            write!(
+                stream,
+                "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len()
+            )
+            .unwrap();
+            stream.write_all(&body).unwrap();
+            stream.flush().unwrap();
+
+            headers
+        });
+
+        (format!("http://{addr}"), server)
+    }
+
+    fn http_config(base_url: String) -> HttpConfig {
+        HttpConfig {
+            base_url,
+            warc_paths_file: String::new(),
+            user_agent: None,
+            bearer_token: None,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn download_range_from_http() {
+        let mut writer = StreamingWarcWriter::new(Vec::new()).unwrap();
+        writer
+            .write(&html_record("https://a.com", "body of a"))
+            .unwrap();
+        let offset = writer.num_bytes();
+        writer
+            .write(&html_record("https://b.com", "body of b"))
+            .unwrap();
+        let length = writer.num_bytes() - offset;
+        writer
+            .write(&html_record("https://c.com", "body of c"))
+            .unwrap();
+        let file = writer.finish().unwrap();
+
+        let (base_url, server) = serve_once(move |headers| {
+            let (start, end) = headers
+                .get("range")
+                .and_then(|range| range.strip_prefix("bytes="))
+                .and_then(|range| range.split_once('-'))
+                .map(|(start, end)| {
+                    (
+                        start.parse::<usize>().unwrap(),
+                        end.parse::<usize>().unwrap(),
+                    )
+                })
+                .unwrap_or((0, file.len() - 1));
+
+            ("206 Partial Content", file[start..=end].to_vec())
+        });
+
+        let warc = WarcFile::download_range_from_http(
+            &http_config(base_url),
+            "test.warc.gz",
+            offset as u64,
+            length as u64,
+        )
+        .unwrap();
+
+        let headers = server.join().unwrap();
+        assert_eq!(
+            headers.get("range"),
+            Some(&format!("bytes={}-{}", offset, offset + length - 1))
+        );
+
+        let records: Vec<WarcRecord> = warc.records().map(|res| res.unwrap()).collect();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0].request.url, "https://b.com");
+        assert_eq!(&records[0].response.body, "body of b");
+    }
+
+    #[test]
+    fn download_range_ignored_by_server() {
+        let mut writer = StreamingWarcWriter::new(Vec::new()).unwrap();
+        writer
+            .write(&html_record("https://a.com", "body of a"))
+            .unwrap();
+        let file = writer.finish().unwrap();
+
+        let (base_url, server) = serve_once(move |_| ("200 OK", file));
+
+        let err = WarcFile::download_range_from_http(&http_config(base_url), "test.warc.gz", 1, 2)
+            .unwrap_err();
+        server.join().unwrap();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::RangeIgnored)
+        ));
+    }
+
+    #[test]
+    fn download_range_out_of_bounds() {
+        let err = WarcFile::download_range_from_http(
+            &http_config("http://127.0.0.1:0".to_string()),
+            "test.warc.gz",
+            u64::MAX,
+            2,
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::InvalidByteRange {
+                offset: u64::MAX,
+                length: 2
+            })
+        ));
+    }
+
+    #[test]
+    fn http_headers() {
+        let mut writer = WarcWriter::new();
+        writer
+            .write(&html_record("https://a.com", "body of a"))
+            .unwrap();
+        let file = writer.finish().unwrap();
+
+        let (base_url, server) = serve_once(move |_| ("200 OK", file));
+
+        let mut config = http_config(base_url);
+        config.user_agent = Some("stract-test/1.0".to_string());
+        config.bearer_token = Some("secret".to_string());
+        config
+            .headers
+            .insert("X-Custom-Header".to_string(), "custom value".to_string());
+
+        let mut buf = Cursor::new(Vec::new());
+        WarcFile::download_from_http("test.warc.gz", &config, &mut buf).unwrap();
+
+        let headers = server.join().unwrap();
+        assert_eq!(
+            headers.get("user-agent").map(|s| s.as_str()),
+            Some("stract-test/1.0")
+        );
+        assert_eq!(
+            headers.get("authorization").map(|s| s.as_str()),
+            Some("Bearer secret")
+        );
+        assert_eq!(
+            headers.get("x-custom-header").map(|s| s.as_str()),
+            Some("custom value")
+        );
+
+        let records: Vec<WarcRecord> = WarcFile::new(buf.into_inner())
+            .records()
+            .map(|res| res.unwrap())
+            .collect();
+
+        assert_eq!(records.len(), 1);
+        assert_eq!(&records[0].request.url, "https://a.com");
+    }
+
     proptest! {
         #[test]
         fn write_read_invariant_prop(records: Vec<WarcRecord>) {
@@ -0,0 +1,158 @@
+// Stract is an open source web search engine.
+// Copyright (C) 2023 Stract ApS
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>.
+
+//! Weighted edit distances used to re-rank spelling candidates.
+
+const QWERTY_ROWS: [&str; 3] = ["qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Cost of each edit operation. The defaults correspond to plain levenshtein distance.
+pub trait EditCost: Send + Sync {
+    fn insertion(&self, _c: char) -> f64 {
+        1.0
+    }
+
+    fn deletion(&self, _c: char) -> f64 {
+        1.0
+    }
+
+    fn substitution(&self, _from: char, _to: char) -> f64 {
+        1.0
+    }
+
+    /// Cost of swapping two adjacent characters. `None` means transpositions
+    /// are not considered a single edit.
+    fn transposition(&self, _a: char, _b: char) -> Option<f64> {
+        None
+    }
+}
+
+/// Unit cost for insertions, deletions and substitutions.
+pub struct Levenshtein;
+
+impl EditCost for Levenshtein {}
+
+/// Damerau-levenshtein distance where substitutions between keys that
+/// are adjacent on a qwerty keyboard are cheaper than other substitutions.
+#[derive(Debug, Clone)]
+pub struct KeyboardDamerau {
+    pub transposition: f64,
+    pub substitution: f64,
+    pub adjacent_key_substitution: f64,
+}
+
+impl Default for KeyboardDamerau {
+    fn default() -> Self {
+        Self {
+            transposition: 1.0,
+            substitution: 1.0,
+            adjacent_key_substitution: 0.5,
+        }
+    }
+}
+
+fn key_position(c: char) -> Option<(usize, usize)> {
+    QWERTY_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, keys)| keys.find(c).map(|col| (row, col)))
+}
+
+fn is_adjacent_key(a: char, b: char) -> bool {
+    match (
+        key_position(a.to_ascii_lowercase()),
+        key_position(b.to_ascii_lowercase()),
+    ) {
+        (Some((row_a, col_a)), Some((row_b, col_b))) => {
+            row_a.abs_diff(row_b) <= 1 && col_a.abs_diff(col_b) <= 1 && a != b
+        }
+        _ => false,
+    }
+}
+
+impl EditCost for KeyboardDamerau {
+    fn substitution(&self, from: char, to: char) -> f64 {
+        if is_adjacent_key(from, to) {
+            self.adjacent_key_substitution
+        } else {
+            self.substitution
+        }
+    }
+
+    fn transposition(&self, _a: char, _b: char) -> Option<f64> {
+        Some(self.transposition)
+    }
+}
+
+/// Weighted optimal string alignment distance between `a` and `b`.
+pub fn weighted_distance(a: &str, b: &str, cost: &dyn EditCost) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut dist = vec![vec![0.0; b.len() + 1]; a.len() + 1];
+
+    for i in 1..=a.len() {
+        dist[i][0] = dist[i - 1][0] + cost.deletion(a[i - 1]);
+    }
+
+    for j in 1..=b.len() {
+        dist[0][j] = dist[0][j - 1] + cost.insertion(b[j - 1]);
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = if a[i - 1] == b[j - 1] {
+                0.0
+            } else {
+                cost.substitution(a[i - 1], b[j - 1])
+            };
+
+            let mut best = (dist[i - 1][j] + cost.deletion(a[i - 1]))
+                .min(dist[i][j - 1] + cost.insertion(b[j - 1]))
+                .min(dist[i - 1][j - 1] + substitution);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                if let Some(transposition) = cost.transposition(a[i - 2], a[i - 1]) {
+                    best = best.min(dist[i - 2][j - 2] + transposition);
+                }
+            }
+
+            dist[i][j] = best;
+        }
+    }
+
+    dist[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein() {
+        assert_eq!(weighted_distance("kitten", "sitting", &Levenshtein), 3.0);
+        assert_eq!(weighted_distance("teh", "the", &Levenshtein), 2.0);
+        assert_eq!(weighted_distance("", "abc", &Levenshtein), 3.0);
+    }
+
+    #[test]
+    fn keyboard_damerau() {
+        let cost = KeyboardDamerau::default();
+
+        assert_eq!(weighted_distance("teh", "the", &cost), 1.0);
+        assert_eq!(weighted_distance("cat", "cst", &cost), 0.5);
+        assert_eq!(weighted_distance("cat", "cut", &cost), 1.0);
+    }
+}
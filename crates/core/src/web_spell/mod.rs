@@ -17,16 +17,18 @@
 //! This module contains the spell checker. It is based on the paper
 //! http://static.googleusercontent.com/media/research.google.com/en/us/pubs/archive/36180.pdf
 //! from google.
+pub mod edit_distance;
 mod error_model;
 pub mod spell_checker;
 mod stupid_backoff;
 mod term_freqs;
 mod trainer;
 
+pub use edit_distance::EditCost;
 pub use error_model::ErrorModel;
 pub use spell_checker::SpellChecker;
 pub use stupid_backoff::StupidBackoff;
-pub use term_freqs::TermDict;
+pub use term_freqs::{DictStats, RejectedTerms, TermDict, TermDictReader, MAX_EDIT_DISTANCE};
 pub use trainer::FirstTrainer;
 pub use trainer::FirstTrainerResult;
 pub use trainer::SecondTrainer;
@@ -52,8 +54,23 @@ pub enum Error {
     #[error("Bincode error: {0}")]
     Bincode(#[from] bincode::Error),
 
+    #[error("Levenshtein error: {0}")]
+    Levenshtein(#[from] fst::automaton::LevenshteinError),
+
+    #[error("Edit distance {requested} is larger than the maximum supported distance {max}")]
+    EditDistanceTooLarge { requested: u32, max: u32 },
+
+    #[error("Executor error: {0}")]
+    Executor(String),
+
     #[error("Checker not found")]
     CheckerNotFound,
+
+    #[error("File is not an exported term dictionary")]
+    InvalidDictFile,
+
+    #[error("Unsupported term dictionary version: {0}")]
+    UnsupportedDictVersion(u32),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -36,7 +36,12 @@ struct LangSpellChecker {
 
 impl LangSpellChecker {
     fn open<P: AsRef<Path>>(path: P, config: CorrectionConfig) -> Result<Self> {
-        let term_dict = TermDict::open(path.as_ref().join("term_dict"))?;
+        let term_dict_path = path.as_ref().join("term_dict");
+        let mut term_dict = match config.compact_term_dict_above {
+            Some(max_dicts) => TermDict::open_and_compact(term_dict_path, max_dicts)?,
+            None => TermDict::open(term_dict_path)?,
+        };
+        term_dict.set_freq_cache_size(config.freq_cache_size);
         let language_model = StupidBackoff::open(path.as_ref().join("stupid_backoff"))?;
         let error_model = ErrorModel::open(path.as_ref().join("error_model.json"))?;
 
@@ -60,7 +65,13 @@ impl LangSpellChecker {
             3
         };
 
-        self.term_dict.search(term, max_edit_distance)
+        match self.term_dict.search(term, max_edit_distance) {
+            Ok(candidates) => candidates,
+            Err(err) => {
+                tracing::debug!("failed to find candidates for {}: {}", term, err);
+                Vec::new()
+            }
+        }
     }
 
     fn lm_logprob(&self, term_idx: usize, context: &[String]) -> f64 {
@@ -14,17 +14,83 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use super::{MergePointer, Result};
-use fst::{IntoStreamer, Streamer};
+use super::{
+    edit_distance::{self, EditCost},
+    Error, MergePointer, Result,
+};
+use crate::executor::Executor;
+use fst::{Automaton, IntoStreamer, Streamer};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::{BTreeMap, BinaryHeap},
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, HashMap},
     fs::{File, OpenOptions},
-    io::BufWriter,
+    io::{BufWriter, Write},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
 };
 use uuid::Uuid;
 
+/// The distance is clamped to the number of characters in `term`. See [`TermDict::search`].
+fn levenshtein_automaton(
+    term: &str,
+    max_edit_distance: u32,
+) -> Result<fst::automaton::Levenshtein> {
+    let term_len = u32::try_from(term.chars().count()).unwrap_or(u32::MAX);
+    let max_edit_distance = max_edit_distance.min(term_len);
+
+    if max_edit_distance > MAX_EDIT_DISTANCE {
+        return Err(Error::EditDistanceTooLarge {
+            requested: max_edit_distance,
+            max: MAX_EDIT_DISTANCE,
+        });
+    }
+
+    Ok(fst::automaton::Levenshtein::new(term, max_edit_distance)?)
+}
+
+/// Matches the keys accepted by any of the levenshtein automata.
+struct MultiLevenshtein {
+    automata: Vec<fst::automaton::Levenshtein>,
+}
+
+impl MultiLevenshtein {
+    /// Which of the automata matches the key that led to `state`.
+    fn matches<'a>(&'a self, state: &'a [Option<usize>]) -> impl Iterator<Item = bool> + 'a {
+        self.automata
+            .iter()
+            .zip(state)
+            .map(|(automaton, state)| automaton.is_match(state))
+    }
+}
+
+impl fst::Automaton for MultiLevenshtein {
+    type State = Vec<Option<usize>>;
+
+    fn start(&self) -> Self::State {
+        self.automata.iter().map(|a| a.start()).collect()
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        self.matches(state).any(|matched| matched)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        self.automata
+            .iter()
+            .zip(state)
+            .any(|(automaton, state)| automaton.can_match(state))
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        self.automata
+            .iter()
+            .zip(state)
+            .map(|(automaton, state)| automaton.accept(state, byte))
+            .collect()
+    }
+}
+
 struct DictBuilder {
     map: BTreeMap<String, u64>,
 }
@@ -49,7 +115,39 @@ impl DictBuilder {
             .or_insert(1);
     }
 
-    fn build<P: AsRef<Path>>(self, path: P) -> Result<StoredDict> {
+    fn build(self, mut writer: DictWriter) -> Result<StoredDict> {
+        for (term, freq) in self.map {
+            writer.insert(term, freq)?;
+        }
+
+        writer.finish()
+    }
+}
+
+enum DictData {
+    Mmap(memmap::Mmap),
+    Memory(Vec<u8>),
+}
+
+impl AsRef<[u8]> for DictData {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            DictData::Mmap(mmap) => &mmap[..],
+            DictData::Memory(bytes) => bytes.as_slice(),
+        }
+    }
+}
+
+enum DictWriter {
+    File {
+        builder: fst::MapBuilder<BufWriter<File>>,
+        path: PathBuf,
+    },
+    Memory(fst::MapBuilder<Vec<u8>>),
+}
+
+impl DictWriter {
+    fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
         let file = OpenOptions::new()
             .create(true)
             .write(true)
@@ -57,21 +155,46 @@ impl DictBuilder {
 
         let wtr = BufWriter::new(file);
 
-        let mut builder = fst::MapBuilder::new(wtr)?;
+        Ok(DictWriter::File {
+            builder: fst::MapBuilder::new(wtr)?,
+            path: path.as_ref().to_path_buf(),
+        })
+    }
 
-        for (term, freq) in self.map {
-            builder.insert(term, freq)?;
+    fn memory() -> Self {
+        DictWriter::Memory(fst::MapBuilder::memory())
+    }
+
+    fn insert<K: AsRef<[u8]>>(&mut self, term: K, freq: u64) -> Result<()> {
+        match self {
+            DictWriter::File { builder, .. } => builder.insert(term, freq)?,
+            DictWriter::Memory(builder) => builder.insert(term, freq)?,
         }
 
-        builder.finish()?;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<StoredDict> {
+        match self {
+            DictWriter::File { builder, path } => {
+                builder.finish()?;
+                StoredDict::open(path)
+            }
+            DictWriter::Memory(builder) => {
+                let bytes = builder.into_inner()?;
 
-        StoredDict::open(path)
+                Ok(StoredDict {
+                    map: fst::Map::new(DictData::Memory(bytes))?,
+                    path: None,
+                })
+            }
+        }
     }
 }
 
 struct StoredDict {
-    map: fst::Map<memmap::Mmap>,
-    path: PathBuf,
+    map: fst::Map<DictData>,
+    path: Option<PathBuf>,
 }
 
 impl StoredDict {
@@ -79,20 +202,23 @@ impl StoredDict {
         let mmap = unsafe { memmap::Mmap::map(&File::open(path.as_ref())?)? };
 
         Ok(Self {
-            map: fst::Map::new(mmap)?,
-            path: path.as_ref().to_path_buf(),
+            map: fst::Map::new(DictData::Mmap(mmap))?,
+            path: Some(path.as_ref().to_path_buf()),
         })
     }
 
-    fn merge<P: AsRef<Path>>(dicts: Vec<Self>, path: P) -> Result<Self> {
-        let file = OpenOptions::new()
-            .create(true)
-            .write(true)
-            .open(path.as_ref())?;
+    fn merge(dicts: Vec<Arc<Self>>, mut builder: DictWriter) -> Result<Self> {
+        Self::merge_into(&dicts, |term, freq| builder.insert(term, freq))?;
 
-        let wtr = BufWriter::new(file);
-        let mut builder = fst::MapBuilder::new(wtr)?;
+        builder.finish()
+    }
 
+    /// Stream the union of `dicts` in sorted order, summing the frequencies
+    /// of terms that are present in more than one dictionary.
+    fn merge_into<F>(dicts: &[Arc<Self>], mut insert: F) -> Result<()>
+    where
+        F: FnMut(String, u64) -> Result<()>,
+    {
         let mut pointers: Vec<_> = dicts
             .iter()
             .map(|d| MergePointer {
@@ -139,31 +265,205 @@ impl StoredDict {
                     }
                 }
 
-                builder.insert(term, freq)?;
+                insert(term, freq)?;
             }
         }
 
-        builder.finish()?;
-
-        let mmap = unsafe { memmap::Mmap::map(&File::open(path.as_ref())?)? };
-
-        Ok(StoredDict {
-            map: fst::Map::new(mmap)?,
-            path: path.as_ref().to_path_buf(),
-        })
+        Ok(())
     }
 }
 
+/// Largest edit distance supported by [`TermDict::search`]. The levenshtein
+/// automaton grows exponentially with the distance.
+pub const MAX_EDIT_DISTANCE: u32 = 3;
+
+const EXPORT_MAGIC: &[u8; 8] = b"STRCTDCT";
+const EXPORT_VERSION: u32 = 1;
+const EXPORT_HEADER_LEN: usize = EXPORT_MAGIC.len() + std::mem::size_of::<u32>();
+
 #[derive(Default, Serialize, Deserialize)]
 struct Metadata {
     dicts: Vec<Uuid>,
 }
 
+enum Storage {
+    Disk(PathBuf),
+    Memory,
+}
+
+impl Storage {
+    fn writer(&self, uuid: Uuid) -> Result<DictWriter> {
+        match self {
+            Storage::Disk(path) => DictWriter::file(path.join(format!("{}.dict", uuid))),
+            Storage::Memory => Ok(DictWriter::memory()),
+        }
+    }
+
+    fn store_bytes(&self, uuid: Uuid, bytes: Vec<u8>) -> Result<StoredDict> {
+        match self {
+            Storage::Disk(path) => {
+                let path = path.join(format!("{}.dict", uuid));
+                std::fs::write(&path, bytes)?;
+                StoredDict::open(path)
+            }
+            Storage::Memory => Ok(StoredDict {
+                map: fst::Map::new(DictData::Memory(bytes))?,
+                path: None,
+            }),
+        }
+    }
+}
+
+/// Least recently used cache of summed term frequencies. Terms that are
+/// not in the dictionary are cached as `None`.
+struct FreqCache {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<String, (Option<u64>, u64)>,
+    recency: BTreeMap<u64, String>,
+}
+
+impl FreqCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn get(&mut self, term: &str) -> Option<Option<u64>> {
+        let tick = self.next_tick();
+        let (freq, last_used) = self.entries.get_mut(term)?;
+
+        let key = self
+            .recency
+            .remove(last_used)
+            .expect("all cached terms should have a recency entry");
+        *last_used = tick;
+        self.recency.insert(tick, key);
+
+        Some(*freq)
+    }
+
+    fn insert(&mut self, term: &str, freq: Option<u64>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(term);
+
+        if self.entries.len() >= self.capacity {
+            if let Some((_, lru)) = self.recency.pop_first() {
+                self.entries.remove(&lru);
+            }
+        }
+
+        let tick = self.next_tick();
+        self.entries.insert(term.to_string(), (freq, tick));
+        self.recency.insert(tick, term.to_string());
+    }
+
+    fn remove(&mut self, term: &str) {
+        if let Some((_, last_used)) = self.entries.remove(term) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DictStats {
+    /// Number of distinct committed terms.
+    pub num_terms: u64,
+    pub num_shards: usize,
+    pub total_freq: u64,
+    /// Lowest summed frequency of any term. Zero if the dictionary is empty.
+    pub min_freq: u64,
+    /// Highest summed frequency of any term. Zero if the dictionary is empty.
+    pub max_freq: u64,
+}
+
+/// Number of terms [`TermDict::insert`] dropped for each of its filters.
+/// Only counted when enabled with [`TermDict::set_track_rejected`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RejectedTerms {
+    pub too_short: u64,
+    pub too_long: u64,
+    pub has_space: u64,
+    pub punctuation: u64,
+    pub non_alphabetic: u64,
+}
+
+enum Rejection {
+    TooShort,
+    TooLong,
+    HasSpace,
+    Punctuation,
+    NonAlphabetic,
+}
+
+impl Rejection {
+    fn of(term: &str) -> Option<Self> {
+        if term.len() <= 1 {
+            return Some(Rejection::TooShort);
+        }
+
+        if term.len() > 100 {
+            return Some(Rejection::TooLong);
+        }
+
+        if term.contains(' ') {
+            return Some(Rejection::HasSpace);
+        }
+
+        let punctuation_percentage =
+            term.chars().filter(|c| c.is_ascii_punctuation()).count() as f64 / term.len() as f64;
+
+        if punctuation_percentage > 0.5 {
+            return Some(Rejection::Punctuation);
+        }
+
+        let non_alphabetic_percentage =
+            term.chars().filter(|c| !c.is_alphabetic()).count() as f64 / term.len() as f64;
+
+        if non_alphabetic_percentage > 0.25 {
+            return Some(Rejection::NonAlphabetic);
+        }
+
+        None
+    }
+}
+
+impl RejectedTerms {
+    fn record(&mut self, rejection: Rejection) {
+        match rejection {
+            Rejection::TooShort => self.too_short += 1,
+            Rejection::TooLong => self.too_long += 1,
+            Rejection::HasSpace => self.has_space += 1,
+            Rejection::Punctuation => self.punctuation += 1,
+            Rejection::NonAlphabetic => self.non_alphabetic += 1,
+        }
+    }
+}
+
 pub struct TermDict {
     builder: DictBuilder,
-    stored: Vec<StoredDict>,
-    path: PathBuf,
+    stored: Vec<Arc<StoredDict>>,
+    storage: Storage,
     metadata: Metadata,
+    freq_cache: Option<Mutex<FreqCache>>,
+    rejected: Option<RejectedTerms>,
 }
 
 impl TermDict {
@@ -175,16 +475,18 @@ impl TermDict {
             let mut stored = Vec::new();
 
             for uuid in metadata.dicts.iter() {
-                stored.push(StoredDict::open(
+                stored.push(Arc::new(StoredDict::open(
                     path.as_ref().join(format!("{}.dict", uuid)),
-                )?);
+                )?));
             }
 
             Ok(Self {
                 builder: DictBuilder::new(),
                 stored,
-                path: path.as_ref().to_path_buf(),
+                storage: Storage::Disk(path.as_ref().to_path_buf()),
                 metadata,
+                freq_cache: None,
+                rejected: None,
             })
         } else {
             std::fs::create_dir_all(path.as_ref())?;
@@ -192,8 +494,10 @@ impl TermDict {
             let s = Self {
                 builder: DictBuilder::new(),
                 stored: Vec::new(),
-                path: path.as_ref().to_path_buf(),
+                storage: Storage::Disk(path.as_ref().to_path_buf()),
                 metadata: Metadata::default(),
+                freq_cache: None,
+                rejected: None,
             };
             s.save_meta()?;
 
@@ -201,55 +505,108 @@ impl TermDict {
         }
     }
 
-    pub fn insert(&mut self, term: &str) {
-        if term.len() <= 1 {
-            return;
-        }
+    /// Same as [`TermDict::open`], but merges the committed dictionaries into one
+    /// if there are more than `max_dicts` of them. Every lookup visits all the
+    /// dictionaries, so this makes opening slower in exchange for faster lookups.
+    pub fn open_and_compact<P: AsRef<Path>>(path: P, max_dicts: usize) -> Result<Self> {
+        let mut dict = Self::open(path)?;
 
-        if term.len() > 100 {
-            return;
+        if dict.stored.len() > max_dicts {
+            dict.merge_dicts()?;
+            dict.gc()?;
         }
 
-        if term.contains(' ') {
-            return;
+        Ok(dict)
+    }
+
+    /// Create a dictionary that is never written to disk. All committed
+    /// dictionaries are kept as in-memory FSTs and are lost when dropped.
+    pub fn in_memory() -> Self {
+        Self {
+            builder: DictBuilder::new(),
+            stored: Vec::new(),
+            storage: Storage::Memory,
+            metadata: Metadata::default(),
+            freq_cache: None,
+            rejected: None,
         }
+    }
 
-        let punctuation_percentage =
-            term.chars().filter(|c| c.is_ascii_punctuation()).count() as f64 / term.len() as f64;
+    /// Cache the summed frequencies of up to `size` recently looked up terms
+    /// so [`TermDict::freq`] doesn't have to visit every shard for hot terms.
+    /// `None` disables the cache.
+    pub fn set_freq_cache_size(&mut self, size: Option<usize>) {
+        self.freq_cache = size.map(|size| Mutex::new(FreqCache::new(size)));
+    }
 
-        if punctuation_percentage > 0.5 {
-            return;
+    fn freq_cache_mut(&mut self) -> Option<&mut FreqCache> {
+        self.freq_cache
+            .as_mut()
+            .map(|cache| cache.get_mut().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn clear_freq_cache(&mut self) {
+        if let Some(cache) = self.freq_cache_mut() {
+            cache.clear();
         }
+    }
 
-        let non_alphabetic_percentage =
-            term.chars().filter(|c| !c.is_alphabetic()).count() as f64 / term.len() as f64;
+    /// Count the terms dropped by [`TermDict::insert`] for each filter. Useful
+    /// to find out why a term is never suggested as a correction.
+    pub fn set_track_rejected(&mut self, track: bool) {
+        self.rejected = if track {
+            Some(RejectedTerms::default())
+        } else {
+            None
+        };
+    }
 
-        if non_alphabetic_percentage > 0.25 {
-            return;
-        }
+    /// Terms dropped since tracking was enabled with [`TermDict::set_track_rejected`].
+    pub fn rejected(&self) -> Option<&RejectedTerms> {
+        self.rejected.as_ref()
+    }
 
-        self.builder.insert(term);
+    pub fn insert(&mut self, term: &str) {
+        match Rejection::of(term) {
+            Some(rejection) => {
+                if let Some(rejected) = &mut self.rejected {
+                    rejected.record(rejection);
+                }
+            }
+            None => self.builder.insert(term),
+        }
     }
 
     pub fn commit(&mut self) -> Result<()> {
         let builder = std::mem::take(&mut self.builder);
 
+        // only the frequencies of the newly committed terms change
+        if let Some(cache) = self.freq_cache_mut() {
+            for term in builder.map.keys() {
+                cache.remove(term);
+            }
+        }
+
         let uuid = uuid::Uuid::new_v4();
 
-        let stored = builder.build(self.path.join(format!("{}.dict", uuid)))?;
+        let stored = builder.build(self.storage.writer(uuid)?)?;
 
         self.metadata.dicts.push(uuid);
         self.save_meta()?;
 
-        self.stored.push(stored);
+        self.stored.push(Arc::new(stored));
         self.gc()?;
 
         Ok(())
     }
 
     fn gc(&self) -> Result<()> {
-        let all_dicts = self
-            .path
+        let path = match &self.storage {
+            Storage::Disk(path) => path,
+            Storage::Memory => return Ok(()),
+        };
+
+        let all_dicts = path
             .read_dir()?
             .filter_map(|e| e.ok())
             .filter(|e| e.path().extension().unwrap_or_default() == "dict")
@@ -274,11 +631,16 @@ impl TermDict {
     }
 
     fn save_meta(&self) -> Result<()> {
+        let path = match &self.storage {
+            Storage::Disk(path) => path,
+            Storage::Memory => return Ok(()),
+        };
+
         let file = OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
-            .open(self.path.join("meta.json"))?;
+            .open(path.join("meta.json"))?;
 
         serde_json::to_writer_pretty(file, &self.metadata)?;
 
@@ -290,91 +652,193 @@ impl TermDict {
             return Ok(());
         }
 
+        self.clear_freq_cache();
+
         let uuid = uuid::Uuid::new_v4();
 
-        let merged = StoredDict::merge(
-            std::mem::take(&mut self.stored),
-            self.path.join(format!("{}.dict", uuid)),
-        )?;
+        let merged =
+            StoredDict::merge(std::mem::take(&mut self.stored), self.storage.writer(uuid)?)?;
         self.metadata.dicts.clear();
 
         self.metadata.dicts.push(uuid);
         self.save_meta()?;
 
-        self.stored.push(merged);
+        self.stored.push(Arc::new(merged));
+
+        Ok(())
+    }
+
+    /// Merge all committed dictionaries into a single file at `path`.
+    /// The file starts with a version tagged header followed by the raw FST,
+    /// and can be loaded into another dictionary using [`TermDict::import`].
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(path.as_ref())?;
+
+        let mut wtr = BufWriter::new(file);
+        wtr.write_all(EXPORT_MAGIC)?;
+        wtr.write_all(&EXPORT_VERSION.to_le_bytes())?;
+
+        let mut builder = fst::MapBuilder::new(wtr)?;
+        StoredDict::merge_into(&self.stored, |term, freq| Ok(builder.insert(term, freq)?))?;
+        builder.finish()?;
+
+        Ok(())
+    }
+
+    /// Load a dictionary previously written by [`TermDict::export`] and
+    /// add it as a new committed dictionary.
+    pub fn import<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let mut bytes = std::fs::read(path.as_ref())?;
+
+        if bytes.len() < EXPORT_HEADER_LEN || !bytes.starts_with(EXPORT_MAGIC) {
+            return Err(Error::InvalidDictFile);
+        }
+
+        let mut version = [0; std::mem::size_of::<u32>()];
+        version.copy_from_slice(&bytes[EXPORT_MAGIC.len()..EXPORT_HEADER_LEN]);
+        let version = u32::from_le_bytes(version);
+
+        if version != EXPORT_VERSION {
+            return Err(Error::UnsupportedDictVersion(version));
+        }
+
+        let bytes = bytes.split_off(EXPORT_HEADER_LEN);
+
+        let uuid = uuid::Uuid::new_v4();
+        let stored = self.storage.store_bytes(uuid, bytes)?;
+
+        self.metadata.dicts.push(uuid);
+        self.save_meta()?;
+
+        self.stored.push(Arc::new(stored));
+        self.clear_freq_cache();
 
         Ok(())
     }
 
     pub fn freq(&self, term: &str) -> Option<u64> {
-        let mut freqs = None;
+        if let Some(cache) = &self.freq_cache {
+            let mut cache = cache.lock().unwrap_or_else(|e| e.into_inner());
 
-        for stored in self.stored.iter() {
-            if let Some(freq) = stored.map.get(term) {
-                match freqs {
-                    None => freqs = Some(freq),
-                    Some(f) => freqs = Some(f + freq),
-                }
+            if let Some(freq) = cache.get(term) {
+                return freq;
             }
+
+            let freq = self.shard_freq(term);
+            cache.insert(term, freq);
+
+            return freq;
         }
 
-        freqs
+        self.shard_freq(term)
     }
 
-    pub fn prune(&mut self, top_n_terms: usize) -> Result<()> {
-        let mut top_term_freqs: BinaryHeap<u64> = BinaryHeap::new();
+    fn shard_freq(&self, term: &str) -> Option<u64> {
+        shard_freq(&self.stored, term)
+    }
 
-        for stored in self.stored.iter() {
-            let mut stream = stored.map.stream();
+    /// Keep only the terms whose frequency is at least the frequency of the
+    /// `top_n_terms`'th most frequent term. Frequencies are summed across all
+    /// shards, after which each shard is rewritten independently on `executor`.
+    pub fn prune(&mut self, top_n_terms: usize, executor: &Executor) -> Result<()> {
+        if top_n_terms == 0 {
+            return Ok(());
+        }
 
-            while let Some((_, freq)) = stream.next() {
-                if top_term_freqs.len() < top_n_terms {
-                    top_term_freqs.push(freq);
-                } else if let Some(mut min) = top_term_freqs.peek_mut() {
-                    if freq > *min {
-                        *min = freq;
-                    }
+        // min-heap of the `top_n_terms` largest summed frequencies
+        let mut top_term_freqs: BinaryHeap<Reverse<u64>> = BinaryHeap::new();
+
+        StoredDict::merge_into(&self.stored, |_, freq| {
+            if top_term_freqs.len() < top_n_terms {
+                top_term_freqs.push(Reverse(freq));
+            } else if let Some(mut min) = top_term_freqs.peek_mut() {
+                if freq > min.0 {
+                    *min = Reverse(freq);
                 }
             }
-        }
+
+            Ok(())
+        })?;
 
         if top_term_freqs.len() < top_n_terms {
             return Ok(());
         }
 
-        let lowest = top_term_freqs.into_sorted_vec().pop().unwrap();
-
-        self.metadata.dicts.clear();
-        for stored in self.stored.iter_mut() {
-            let uuid = uuid::Uuid::new_v4();
-            let file = OpenOptions::new()
-                .create(true)
-                .write(true)
-                .open(self.path.join(format!("{}.dict", uuid)))?;
+        let lowest = top_term_freqs.peek().unwrap().0;
 
-            let wtr = BufWriter::new(file);
+        self.clear_freq_cache();
 
-            let mut builder = fst::MapBuilder::new(wtr)?;
-
-            let mut stream = stored.map.stream();
-            while let Some((term, freq)) = stream.next() {
-                if freq >= lowest {
-                    builder.insert(term, freq)?;
-                }
-            }
+        let uuids: Vec<_> = self.stored.iter().map(|_| uuid::Uuid::new_v4()).collect();
+        let storage = &self.storage;
+        let all_stored = &self.stored;
 
-            self.metadata.dicts.push(uuid);
+        let pruned = executor
+            .map(
+                |(stored, uuid): (&StoredDict, Uuid)| -> Result<StoredDict> {
+                    let mut builder = storage.writer(uuid)?;
 
-            builder.finish()?;
+                    let mut stream = stored.map.stream();
+                    while let Some((term, freq)) = stream.next() {
+                        // a term is kept in every shard if its summed frequency is high enough
+                        let total = std::str::from_utf8(term)
+                            .ok()
+                            .and_then(|term| shard_freq(all_stored, term))
+                            .unwrap_or(freq);
 
-            *stored = StoredDict::open(self.path.join(format!("{}.dict", uuid)))?;
-        }
+                        if total >= lowest {
+                            builder.insert(term, freq)?;
+                        }
+                    }
 
+                    builder.finish()
+                },
+                self.stored
+                    .iter()
+                    .map(|stored| stored.as_ref())
+                    .zip(uuids.iter().copied()),
+            )
+            .map_err(|err| Error::Executor(err.to_string()))?
+            .into_iter()
+            .map(|stored| stored.map(Arc::new))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.stored = pruned;
+        self.metadata.dicts = uuids;
         self.save_meta()?;
+        self.gc()?;
 
         Ok(())
     }
 
+    /// Compute statistics over all committed terms. Frequencies of terms
+    /// present in multiple shards are summed before computing min/max.
+    pub fn stats(&self) -> Result<DictStats> {
+        let mut stats = DictStats {
+            num_shards: self.stored.len(),
+            min_freq: u64::MAX,
+            ..Default::default()
+        };
+
+        StoredDict::merge_into(&self.stored, |_, freq| {
+            stats.num_terms += 1;
+            stats.total_freq += freq;
+            stats.min_freq = stats.min_freq.min(freq);
+            stats.max_freq = stats.max_freq.max(freq);
+
+            Ok(())
+        })?;
+
+        if stats.num_terms == 0 {
+            stats.min_freq = 0;
+        }
+
+        Ok(stats)
+    }
+
     pub fn terms(&self) -> Vec<String> {
         let mut terms = Vec::new();
 
@@ -389,42 +853,252 @@ impl TermDict {
         terms
     }
 
-    pub fn search(&self, term: &str, max_edit_distance: u32) -> Vec<String> {
-        let mut res = Vec::new();
+    /// Find all terms within `max_edit_distance` of `term`.
+    ///
+    /// The distance is clamped to the number of characters in `term` as larger distances
+    /// would match every short term in the dictionary. Requesting a (clamped) distance
+    /// above [`MAX_EDIT_DISTANCE`] returns an error instead of silently yielding nothing.
+    pub fn search(&self, term: &str, max_edit_distance: u32) -> Result<Vec<String>> {
+        search_shards(&self.stored, term, max_edit_distance)
+    }
 
-        for stored in self.stored.iter() {
-            if let Ok(automaton) = fst::automaton::Levenshtein::new(term, max_edit_distance) {
-                if let Ok(mut s) = stored.map.search(automaton).into_stream().into_str_keys() {
-                    res.append(&mut s);
-                }
-            }
-        }
+    /// Find terms within `max_edit_distance` of `term` and rank them by their
+    /// weighted edit distance under `cost`. Candidates with equal cost are ordered
+    /// by descending frequency. Plain levenshtein distance is used if no cost model is given.
+    pub fn suggest(
+        &self,
+        term: &str,
+        max_edit_distance: u32,
+        cost: Option<&dyn EditCost>,
+    ) -> Result<Vec<String>> {
+        let candidates = self.search(term, max_edit_distance)?;
+
+        Ok(rank_suggestions(term, candidates, cost, |candidate| {
+            self.freq(candidate)
+        }))
+    }
 
-        res
+    /// Same as calling [`TermDict::suggest`] for each term, but all the terms
+    /// are searched in a single pass over each stored dictionary.
+    pub fn suggest_many(
+        &self,
+        terms: &[&str],
+        max_edit_distance: u32,
+        cost: Option<&dyn EditCost>,
+    ) -> Result<Vec<Vec<String>>> {
+        let candidates = search_many_shards(&self.stored, terms, max_edit_distance)?;
+
+        Ok(terms
+            .iter()
+            .zip(candidates)
+            .map(|(term, candidates)| {
+                rank_suggestions(term, candidates, cost, |candidate| self.freq(candidate))
+            })
+            .collect())
+    }
+
+    /// Read-only view of the currently committed terms. The view is not affected by later
+    /// commits, so a new reader should be created after each commit to see the new terms.
+    pub fn reader(&self) -> TermDictReader {
+        TermDictReader {
+            stored: self.stored.clone(),
+        }
     }
 
     pub fn merge(&mut self, other: Self) -> Result<()> {
         for stored in other.stored {
             let uuid = uuid::Uuid::new_v4();
-            let new_path = self.path.join(format!("{}.dict", uuid));
-            std::fs::rename(stored.path, &new_path)?;
+
+            let new_dict = match (&self.storage, &stored.path) {
+                (Storage::Disk(path), Some(old_path)) => {
+                    let new_path = path.join(format!("{}.dict", uuid));
+                    std::fs::rename(old_path, &new_path)?;
+                    StoredDict::open(new_path)?
+                }
+                _ => {
+                    let mut builder = self.storage.writer(uuid)?;
+                    let mut stream = stored.map.stream();
+
+                    while let Some((term, freq)) = stream.next() {
+                        builder.insert(term, freq)?;
+                    }
+
+                    builder.finish()?
+                }
+            };
 
             self.metadata.dicts.push(uuid);
             self.save_meta()?;
 
-            self.stored.push(StoredDict::open(new_path)?);
+            self.stored.push(Arc::new(new_dict));
         }
 
+        self.clear_freq_cache();
+
         Ok(())
     }
 
-    pub(crate) fn path(&self) -> &Path {
-        &self.path
+    pub(crate) fn path(&self) -> Option<&Path> {
+        match &self.storage {
+            Storage::Disk(path) => Some(path),
+            Storage::Memory => None,
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
+/// Cheap to clone read-only snapshot of a [`TermDict`] that can be shared between
+/// threads while the dictionary itself keeps being written to. Created by [`TermDict::reader`].
+#[derive(Clone)]
+pub struct TermDictReader {
+    stored: Vec<Arc<StoredDict>>,
+}
+
+impl TermDictReader {
+    /// Same as [`TermDict::freq`] but without the frequency cache.
+    pub fn freq(&self, term: &str) -> Option<u64> {
+        shard_freq(&self.stored, term)
+    }
+
+    /// See [`TermDict::search`].
+    pub fn search(&self, term: &str, max_edit_distance: u32) -> Result<Vec<String>> {
+        search_shards(&self.stored, term, max_edit_distance)
+    }
+
+    /// See [`TermDict::suggest`].
+    pub fn suggest(
+        &self,
+        term: &str,
+        max_edit_distance: u32,
+        cost: Option<&dyn EditCost>,
+    ) -> Result<Vec<String>> {
+        let candidates = self.search(term, max_edit_distance)?;
+
+        Ok(rank_suggestions(term, candidates, cost, |candidate| {
+            self.freq(candidate)
+        }))
+    }
+
+    /// See [`TermDict::suggest_many`].
+    pub fn suggest_many(
+        &self,
+        terms: &[&str],
+        max_edit_distance: u32,
+        cost: Option<&dyn EditCost>,
+    ) -> Result<Vec<Vec<String>>> {
+        let candidates = search_many_shards(&self.stored, terms, max_edit_distance)?;
+
+        Ok(terms
+            .iter()
+            .zip(candidates)
+            .map(|(term, candidates)| {
+                rank_suggestions(term, candidates, cost, |candidate| self.freq(candidate))
+            })
+            .collect())
+    }
+}
+
+fn shard_freq(stored: &[Arc<StoredDict>], term: &str) -> Option<u64> {
+    let mut freqs = None;
+
+    for stored in stored.iter() {
+        if let Some(freq) = stored.map.get(term) {
+            match freqs {
+                None => freqs = Some(freq),
+                Some(f) => freqs = Some(f + freq),
+            }
+        }
+    }
+
+    freqs
+}
+
+fn search_shards(
+    stored: &[Arc<StoredDict>],
+    term: &str,
+    max_edit_distance: u32,
+) -> Result<Vec<String>> {
+    let automaton = levenshtein_automaton(term, max_edit_distance)?;
+    let mut res = Vec::new();
+
+    for stored in stored.iter() {
+        let mut s = stored
+            .map
+            .search(&automaton)
+            .into_stream()
+            .into_str_keys()?;
+        res.append(&mut s);
+    }
+
+    Ok(res)
+}
+
+/// Candidates for each of the `terms`, found in a single pass over each stored dictionary.
+fn search_many_shards(
+    stored: &[Arc<StoredDict>],
+    terms: &[&str],
+    max_edit_distance: u32,
+) -> Result<Vec<Vec<String>>> {
+    let automaton = MultiLevenshtein {
+        automata: terms
+            .iter()
+            .map(|term| levenshtein_automaton(term, max_edit_distance))
+            .collect::<Result<_>>()?,
+    };
+
+    let mut candidates = vec![Vec::new(); terms.len()];
+
+    for stored in stored.iter() {
+        let mut stream = stored.map.search_with_state(&automaton).into_stream();
+
+        while let Some((key, _, state)) = stream.next() {
+            let key = std::str::from_utf8(key).unwrap();
+
+            for (candidates, matched) in candidates.iter_mut().zip(automaton.matches(&state)) {
+                if matched {
+                    candidates.push(key.to_string());
+                }
+            }
+        }
+    }
+
+    Ok(candidates)
+}
+
+fn rank_suggestions<F>(
+    term: &str,
+    mut candidates: Vec<String>,
+    cost: Option<&dyn EditCost>,
+    freq: F,
+) -> Vec<String>
+where
+    F: Fn(&str) -> Option<u64>,
+{
+    let cost = cost.unwrap_or(&edit_distance::Levenshtein);
+
+    candidates.sort();
+    candidates.dedup();
+
+    let mut scored: Vec<_> = candidates
+        .into_iter()
+        .map(|candidate| {
+            let dist = edit_distance::weighted_distance(term, &candidate, cost);
+            let freq = freq(&candidate).unwrap_or_default();
+            (candidate, dist, freq)
+        })
+        .collect();
+
+    scored.sort_by(|(a, a_dist, a_freq), (b, b_dist, b_freq)| {
+        a_dist
+            .total_cmp(b_dist)
+            .then_with(|| b_freq.cmp(a_freq))
+            .then_with(|| a.cmp(b))
+    });
+
+    scored.into_iter().map(|(term, _, _)| term).collect()
+}
+
+#[cfg(test)]
+mod tests {
     use super::*;
     use crate::gen_temp_path;
     use anyhow::Result;
@@ -502,4 +1176,538 @@ mod tests {
             assert_eq!(dict.freq("baz"), Some(2));
         }
     }
+
+    #[test]
+    fn open_and_compact() -> Result<()> {
+        let path = gen_temp_path();
+
+        {
+            let mut dict = TermDict::open(&path)?;
+
+            for i in 0..20_u8 {
+                dict.insert("foo");
+                if i % 2 == 0 {
+                    dict.insert("bar");
+                }
+                dict.insert(&format!("term{}", char::from(b'a' + i)));
+
+                dict.commit()?;
+            }
+        }
+
+        let num_dict_files = || {
+            path.read_dir()
+                .unwrap()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().unwrap_or_default() == "dict")
+                .count()
+        };
+
+        {
+            let dict = TermDict::open_and_compact(&path, 20)?;
+            assert_eq!(dict.stored.len(), 20);
+        }
+
+        {
+            let dict = TermDict::open_and_compact(&path, 10)?;
+            assert_eq!(dict.stored.len(), 1);
+            assert_eq!(num_dict_files(), 1);
+
+            assert_eq!(dict.freq("foo"), Some(20));
+            assert_eq!(dict.freq("bar"), Some(10));
+            for i in 0..20_u8 {
+                assert_eq!(dict.freq(&format!("term{}", char::from(b'a' + i))), Some(1));
+            }
+        }
+
+        let dict = TermDict::open(&path)?;
+        assert_eq!(dict.stored.len(), 1);
+        assert_eq!(dict.freq("foo"), Some(20));
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_memory_term_dict() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("baz");
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("foo");
+
+        dict.commit()?;
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("baz");
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("foo");
+
+        dict.commit()?;
+
+        assert_eq!(dict.stored.len(), 2);
+        assert!(dict.path().is_none());
+
+        dict.merge_dicts()?;
+
+        assert_eq!(dict.stored.len(), 1);
+        assert!(dict.stored.iter().all(|stored| stored.path.is_none()));
+
+        assert_eq!(dict.freq("foo"), Some(6));
+        assert_eq!(dict.freq("bar"), Some(4));
+        assert_eq!(dict.freq("baz"), Some(2));
+        assert_eq!(dict.freq("qux"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_memory_search() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        dict.insert("hello");
+        dict.insert("help");
+        dict.insert("world");
+
+        dict.commit()?;
+
+        let mut res = dict.search("helo", 1)?;
+        res.sort();
+
+        assert_eq!(res, vec!["hello".to_string(), "help".to_string()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn in_memory_prune_and_merge() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        for _ in 0..3 {
+            dict.insert("foo");
+        }
+        dict.insert("bar");
+        dict.commit()?;
+
+        let mut other = TermDict::in_memory();
+        other.insert("foo");
+        other.insert("baz");
+        other.commit()?;
+
+        dict.merge(other)?;
+        assert_eq!(dict.freq("foo"), Some(4));
+
+        dict.merge_dicts()?;
+        dict.prune(1, &Executor::single_thread())?;
+
+        assert_eq!(dict.freq("foo"), Some(4));
+        assert_eq!(dict.freq("bar"), None);
+        assert_eq!(dict.freq("baz"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parallel_prune() -> Result<()> {
+        let build = || -> Result<TermDict> {
+            let mut dict = TermDict::open(gen_temp_path())?;
+
+            for shard in 0..4 {
+                for i in 0..10 {
+                    for _ in 0..(i + shard) {
+                        dict.insert(&format!("term{}", char::from(b'a' + i as u8)));
+                    }
+                }
+                dict.insert(&format!("shard{}", char::from(b'a' + shard as u8)));
+                dict.commit()?;
+            }
+
+            Ok(dict)
+        };
+
+        let mut sequential = build()?;
+        sequential.prune(5, &Executor::single_thread())?;
+
+        let mut parallel = build()?;
+        parallel.prune(5, &Executor::with_threads(3, "prune-test").unwrap())?;
+
+        assert_eq!(parallel.stored.len(), 4);
+        assert_eq!(parallel.terms(), sequential.terms());
+
+        // the 5 terms with the highest frequencies summed over all shards survive,
+        // including their entries in shards where they are infrequent
+        let mut surviving = sequential.terms();
+        surviving.sort();
+        surviving.dedup();
+        assert_eq!(surviving, vec!["termf", "termg", "termh", "termi", "termj"]);
+        assert_eq!(sequential.freq("termf"), Some(26));
+        assert_eq!(sequential.freq("termj"), Some(42));
+        assert!(sequential.freq("terme").is_none());
+        for term in sequential.terms() {
+            assert_eq!(parallel.freq(&term), sequential.freq(&term));
+        }
+        assert!(parallel.freq("shardd").is_none());
+
+        // metadata lists the new shards in the same order as they are stored
+        let path = parallel.path().unwrap().to_path_buf();
+        let parallel_terms = parallel.terms();
+        drop(parallel);
+
+        let reopened = TermDict::open(path)?;
+        assert_eq!(reopened.terms(), parallel_terms);
+
+        Ok(())
+    }
+
+    #[test]
+    fn export_import() -> Result<()> {
+        let mut dict = TermDict::open(gen_temp_path())?;
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("foo");
+        dict.commit()?;
+
+        dict.insert("foo");
+        dict.insert("baz");
+        dict.commit()?;
+
+        let export_path = gen_temp_path();
+        dict.export(&export_path)?;
+
+        let mut imported = TermDict::open(gen_temp_path())?;
+        imported.import(&export_path)?;
+
+        assert_eq!(imported.stored.len(), 1);
+        assert_eq!(imported.freq("foo"), Some(3));
+        assert_eq!(imported.freq("bar"), Some(1));
+        assert_eq!(imported.freq("baz"), Some(1));
+
+        let mut imported = TermDict::in_memory();
+        imported.import(&export_path)?;
+
+        assert_eq!(imported.freq("foo"), Some(3));
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_wrong_version() -> Result<()> {
+        let dict = TermDict::in_memory();
+
+        let export_path = gen_temp_path();
+        dict.export(&export_path)?;
+
+        let mut bytes = std::fs::read(&export_path)?;
+        bytes[EXPORT_MAGIC.len()..EXPORT_HEADER_LEN].copy_from_slice(&42u32.to_le_bytes());
+        std::fs::write(&export_path, bytes)?;
+
+        let mut imported = TermDict::in_memory();
+        assert!(matches!(
+            imported.import(&export_path),
+            Err(Error::UnsupportedDictVersion(42))
+        ));
+
+        std::fs::write(&export_path, b"not a dictionary")?;
+        assert!(matches!(
+            imported.import(&export_path),
+            Err(Error::InvalidDictFile)
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        assert_eq!(
+            dict.stats()?,
+            DictStats {
+                num_terms: 0,
+                num_shards: 0,
+                total_freq: 0,
+                min_freq: 0,
+                max_freq: 0,
+            }
+        );
+
+        dict.insert("foo");
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.commit()?;
+
+        dict.insert("foo");
+        dict.insert("baz");
+        dict.insert("baz");
+        dict.commit()?;
+
+        assert_eq!(
+            dict.stats()?,
+            DictStats {
+                num_terms: 3,
+                num_shards: 2,
+                total_freq: 6,
+                min_freq: 1,
+                max_freq: 3,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejected_terms() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        dict.insert("a");
+        assert_eq!(dict.rejected(), None);
+
+        dict.set_track_rejected(true);
+
+        for term in [
+            "a",
+            "b",
+            &"x".repeat(101),
+            "foo bar",
+            "!?!",
+            "abc123",
+            "hello",
+            "world",
+        ] {
+            dict.insert(term);
+        }
+        dict.commit()?;
+
+        assert_eq!(
+            dict.rejected(),
+            Some(&RejectedTerms {
+                too_short: 2,
+                too_long: 1,
+                has_space: 1,
+                punctuation: 1,
+                non_alphabetic: 1,
+            })
+        );
+        assert_eq!(dict.stats()?.num_terms, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_snapshot() -> Result<()> {
+        let mut dict = TermDict::open(gen_temp_path())?;
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.commit()?;
+
+        let reader = dict.reader();
+
+        std::thread::scope(|s| -> Result<()> {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    let reader = reader.clone();
+
+                    s.spawn(move || {
+                        for _ in 0..100 {
+                            assert_eq!(reader.freq("foo"), Some(1));
+                            assert_eq!(reader.freq("baz"), None);
+                            assert_eq!(reader.search("bax", 1).unwrap(), vec!["bar"]);
+                        }
+                    })
+                })
+                .collect();
+
+            for _ in 0..10 {
+                dict.insert("foo");
+                dict.insert("baz");
+                dict.commit()?;
+            }
+
+            dict.merge_dicts()?;
+
+            for reader in readers {
+                reader.join().unwrap();
+            }
+
+            Ok(())
+        })?;
+
+        // the old snapshot is unaffected by the commits and merge
+        assert_eq!(reader.freq("foo"), Some(1));
+        assert_eq!(reader.suggest("bax", 1, None)?, vec!["bar".to_string()]);
+
+        let reader = dict.reader();
+        assert_eq!(reader.freq("foo"), dict.freq("foo"));
+        assert_eq!(reader.freq("foo"), Some(11));
+        assert_eq!(reader.freq("baz"), Some(10));
+        assert_eq!(
+            reader.suggest("bax", 1, None)?,
+            dict.suggest("bax", 1, None)?
+        );
+        assert_eq!(
+            reader.suggest_many(&["bax", "fo"], 1, None)?,
+            dict.suggest_many(&["bax", "fo"], 1, None)?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn suggest_with_cost_model() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        dict.insert("the");
+        dict.insert("tea");
+        dict.insert("tea");
+        dict.commit()?;
+
+        assert_eq!(
+            dict.suggest("teh", 2, None)?,
+            vec!["tea".to_string(), "the".to_string()]
+        );
+
+        let cost = edit_distance::KeyboardDamerau {
+            transposition: 0.5,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            dict.suggest("teh", 2, Some(&cost))?,
+            vec!["the".to_string(), "tea".to_string()]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn suggest_many() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        for term in ["the", "tea", "tea", "house", "horse", "mouse"] {
+            dict.insert(term);
+        }
+        dict.commit()?;
+
+        // terms in a second stored dictionary should also be found
+        for term in ["hose", "the", "apple"] {
+            dict.insert(term);
+        }
+        dict.commit()?;
+
+        let terms = ["teh", "hous", "aple"];
+        let cost = edit_distance::KeyboardDamerau::default();
+
+        for cost in [None, Some(&cost as &dyn EditCost)] {
+            let expected = terms
+                .iter()
+                .map(|term| dict.suggest(term, 2, cost))
+                .collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(dict.suggest_many(&terms, 2, cost)?, expected);
+        }
+
+        assert!(dict.suggest_many(&[], 2, None)?.is_empty());
+        assert!(matches!(
+            dict.suggest_many(&["terms"], MAX_EDIT_DISTANCE + 1, None),
+            Err(Error::EditDistanceTooLarge { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_clamps_edit_distance() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.insert("averyverylongword");
+        dict.commit()?;
+
+        // clamped to the length of the term
+        let mut res = dict.search("baz", 1_000)?;
+        res.sort();
+        assert_eq!(res, vec!["bar".to_string(), "foo".to_string()]);
+
+        assert!(matches!(
+            dict.search("averyverylongwort", 1_000),
+            Err(Error::EditDistanceTooLarge {
+                requested: 17,
+                max: MAX_EDIT_DISTANCE
+            })
+        ));
+
+        assert_eq!(
+            dict.search("averyverylongwort", MAX_EDIT_DISTANCE)?,
+            vec!["averyverylongword".to_string()]
+        );
+
+        Ok(())
+    }
+
+    fn cached_freq(dict: &TermDict, term: &str) -> Option<Option<u64>> {
+        dict.freq_cache
+            .as_ref()
+            .unwrap()
+            .lock()
+            .unwrap()
+            .entries
+            .get(term)
+            .map(|(freq, _)| *freq)
+    }
+
+    #[test]
+    fn freq_cache() -> Result<()> {
+        let mut dict = TermDict::in_memory();
+        dict.set_freq_cache_size(Some(2));
+
+        dict.insert("foo");
+        dict.insert("bar");
+        dict.commit()?;
+
+        dict.insert("foo");
+        dict.commit()?;
+
+        assert_eq!(dict.freq("foo"), Some(2));
+        assert_eq!(dict.freq("bar"), Some(1));
+        assert_eq!(dict.freq("foo"), Some(2));
+        assert_eq!(cached_freq(&dict, "foo"), Some(Some(2)));
+        assert_eq!(cached_freq(&dict, "bar"), Some(Some(1)));
+
+        // least recently used term is evicted
+        assert_eq!(dict.freq("baz"), None);
+        assert_eq!(cached_freq(&dict, "baz"), Some(None));
+        assert_eq!(cached_freq(&dict, "bar"), None);
+        assert_eq!(cached_freq(&dict, "foo"), Some(Some(2)));
+
+        // commit only invalidates the committed terms
+        dict.insert("baz");
+        dict.insert("baz");
+        dict.commit()?;
+
+        assert_eq!(cached_freq(&dict, "baz"), None);
+        assert_eq!(cached_freq(&dict, "foo"), Some(Some(2)));
+        assert_eq!(dict.freq("baz"), Some(2));
+
+        dict.merge_dicts()?;
+        assert_eq!(
+            dict.freq_cache
+                .as_ref()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .entries
+                .len(),
+            0
+        );
+        assert_eq!(dict.freq("foo"), Some(2));
+
+        Ok(())
+    }
 }
@@ -17,7 +17,7 @@
 use indicatif::ParallelProgressIterator;
 use rayon::prelude::*;
 
-use crate::web_spell::error_model::ErrorModel;
+use crate::{executor::Executor, web_spell::error_model::ErrorModel};
 
 use super::{stupid_backoff::StupidBackoffTrainer, tokenize, Result};
 use std::{
@@ -78,8 +78,12 @@ pub struct SecondTrainer {
 }
 
 impl SecondTrainer {
-    /// Panics if vec is empty.
-    pub fn new<P: AsRef<Path>>(first_steps: Vec<FirstTrainerResult>, path: P) -> Result<Self> {
+    /// Panics if vec is empty. The merged term dictionary is pruned on `executor`.
+    pub fn new<P: AsRef<Path>>(
+        first_steps: Vec<FirstTrainerResult>,
+        path: P,
+        executor: &Executor,
+    ) -> Result<Self> {
         if !path.as_ref().exists() {
             std::fs::create_dir_all(path.as_ref())?;
         }
@@ -98,12 +102,17 @@ impl SecondTrainer {
         }
         tracing::debug!("merged term dicts");
 
-        std::fs::rename(term_dict.path(), path.as_ref().join("term_dict"))?;
+        std::fs::rename(
+            term_dict
+                .path()
+                .expect("trainer term dicts are always stored on disk"),
+            path.as_ref().join("term_dict"),
+        )?;
         drop(term_dict);
 
         let mut term_dict = TermDict::open(path.as_ref().join("term_dict"))?;
         term_dict.merge_dicts()?;
-        term_dict.prune(10_000_000)?;
+        term_dict.prune(10_000_000, executor)?;
 
         Ok(Self {
             term_dict,
@@ -134,6 +143,7 @@ impl SecondTrainer {
                 let possible_corrections = self
                     .term_dict
                     .search(&term, max_edit_distance)
+                    .unwrap_or_default()
                     .into_iter()
                     .filter(|correction| {
                         correction != &term
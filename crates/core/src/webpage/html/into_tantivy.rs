@@ -16,6 +16,7 @@
 
 use crate::{
     ceil_char_boundary,
+    minhash::MinHash,
     prehashed::hash,
     schema::{FastField, TextField},
     simhash, split_u128, tokenizer,
@@ -33,7 +34,7 @@ use super::{find_recipe_first_ingredient_tag_id, schema_org, Html};
 use crate::schema::{Field, FLOAT_SCALING};
 
 impl Html {
-    fn pretokenize_title(&self) -> Result<PreTokenizedString> {
+    fn pretokenize_title(&self, max_token_len: usize) -> Result<PreTokenizedString> {
         let title = self.title();
 
         if title.is_none() {
@@ -41,10 +42,10 @@ impl Html {
         }
         let title = title.unwrap();
 
-        Ok(self.pretokenize_string(title))
+        Ok(self.pretokenize_string(title, max_token_len))
     }
 
-    fn pretokenize_all_text(&self) -> Result<PreTokenizedString> {
+    fn pretokenize_all_text(&self, max_token_len: usize) -> Result<PreTokenizedString> {
         let all_text = self.all_text();
 
         if all_text.is_none() {
@@ -52,38 +53,38 @@ impl Html {
         }
         let all_text = all_text.unwrap();
 
-        Ok(self.pretokenize_string(all_text))
+        Ok(self.pretokenize_string(all_text, max_token_len))
     }
 
-    fn pretokenize_clean_text(&self) -> PreTokenizedString {
+    fn pretokenize_clean_text(&self, max_token_len: usize) -> PreTokenizedString {
         let clean_text = self.clean_text().cloned().unwrap_or_default();
-        self.pretokenize_string(clean_text)
+        self.pretokenize_string(clean_text, max_token_len)
     }
 
-    fn pretokenize_url(&self) -> PreTokenizedString {
+    fn pretokenize_url(&self, max_token_len: usize) -> PreTokenizedString {
         let url = self.url().to_string();
-        self.pretokenize_string(url)
+        self.pretokenize_string(url, max_token_len)
     }
 
-    fn pretokenize_domain(&self) -> PreTokenizedString {
+    fn pretokenize_domain(&self, max_token_len: usize) -> PreTokenizedString {
         let domain = self.url().root_domain().unwrap_or_default().to_string();
 
-        self.pretokenize_string(domain)
+        self.pretokenize_string(domain, max_token_len)
     }
 
-    fn pretokenize_site(&self) -> PreTokenizedString {
+    fn pretokenize_site(&self, max_token_len: usize) -> PreTokenizedString {
         let site = self.url().normalized_host().unwrap_or_default().to_string();
 
-        self.pretokenize_string(site)
+        self.pretokenize_string(site, max_token_len)
     }
 
-    fn pretokenize_description(&self) -> PreTokenizedString {
+    fn pretokenize_description(&self, max_token_len: usize) -> PreTokenizedString {
         let text = self.description().unwrap_or_default();
 
-        self.pretokenize_string(text)
+        self.pretokenize_string(text, max_token_len)
     }
 
-    fn pretokenize_microformats(&self) -> PreTokenizedString {
+    fn pretokenize_microformats(&self, max_token_len: usize) -> PreTokenizedString {
         let mut text = String::new();
 
         for microformat in self.microformats().iter() {
@@ -91,11 +92,14 @@ impl Html {
             text.push(' ');
         }
 
-        self.pretokenize_string(text)
+        self.pretokenize_string(text, max_token_len)
     }
 
-    fn pretokenize_string(&self, text: String) -> PreTokenizedString {
-        self.pretokenize_string_with(text, tokenizer::Tokenizer::default())
+    fn pretokenize_string(&self, text: String, max_token_len: usize) -> PreTokenizedString {
+        self.pretokenize_string_with(
+            text,
+            tokenizer::Tokenizer::default().with_max_token_len(max_token_len),
+        )
     }
 
     fn pretokenize_string_with(
@@ -116,17 +120,22 @@ impl Html {
 
         PreTokenizedString { text, tokens }
     }
-    pub fn into_tantivy(self, schema: &tantivy::schema::Schema) -> Result<TantivyDocument> {
+    /// Words longer than `max_token_len` bytes are not indexed.
+    pub fn into_tantivy(
+        self,
+        schema: &tantivy::schema::Schema,
+        max_token_len: usize,
+    ) -> Result<TantivyDocument> {
         let mut doc = TantivyDocument::new();
 
-        let title = self.pretokenize_title()?;
-        let all_text = self.pretokenize_all_text()?;
-        let clean_text = self.pretokenize_clean_text();
-        let url = self.pretokenize_url();
-        let domain = self.pretokenize_domain();
-        let site = self.pretokenize_site();
-        let description = self.pretokenize_description();
-        let microformats = self.pretokenize_microformats();
+        let title = self.pretokenize_title(max_token_len)?;
+        let all_text = self.pretokenize_all_text(max_token_len)?;
+        let clean_text = self.pretokenize_clean_text(max_token_len);
+        let url = self.pretokenize_url(max_token_len);
+        let domain = self.pretokenize_domain(max_token_len);
+        let site = self.pretokenize_site(max_token_len);
+        let description = self.pretokenize_description(max_token_len);
+        let microformats = self.pretokenize_microformats(max_token_len);
         let url_for_site_operator = self.pretokenize_string_with(
             self.url().to_string(),
             tokenizer::Tokenizer::SiteOperator(tokenizer::SiteOperatorUrlTokenizer),
@@ -197,6 +206,16 @@ impl Html {
         let domain_hash = split_u128(hash(self.url().root_domain().unwrap_or_default()).0);
         let title_hash = split_u128(hash(self.title().unwrap_or_default()).0);
 
+        // documents without any text have a minhash of 0 and no bands
+        let min_hash = MinHash::new(&clean_text.text);
+        let min_hash_bands = min_hash
+            .as_ref()
+            .map(|min_hash| min_hash.bands().to_vec())
+            .unwrap_or_default();
+        let min_hash = min_hash
+            .map(|min_hash| min_hash.to_packed())
+            .unwrap_or_default();
+
         for field in schema
             .fields()
             .filter_map(|(field, _)| Field::get(field.field_id() as usize))
@@ -280,6 +299,45 @@ impl Html {
                         },
                     );
                 }
+                Field::Text(TextField::UrlPath) => {
+                    let path = self.url().path().to_string();
+
+                    doc.add_pre_tokenized_text(
+                        tantivy_field,
+                        PreTokenizedString {
+                            text: path.clone(),
+                            tokens: vec![tantivy::tokenizer::Token {
+                                offset_from: 0,
+                                offset_to: path.len(),
+                                position: 0,
+                                text: path,
+                                position_length: 1,
+                            }],
+                        },
+                    );
+                }
+                Field::Text(TextField::MinHashBands) => {
+                    let text = min_hash_bands.join(" ");
+                    let mut offset = 0;
+                    let tokens = min_hash_bands
+                        .iter()
+                        .enumerate()
+                        .map(|(position, band)| {
+                            let token = tantivy::tokenizer::Token {
+                                offset_from: offset,
+                                offset_to: offset + band.len(),
+                                position,
+                                text: band.clone(),
+                                position_length: 1,
+                            };
+                            offset += band.len() + 1;
+
+                            token
+                        })
+                        .collect();
+
+                    doc.add_pre_tokenized_text(tantivy_field, PreTokenizedString { text, tokens });
+                }
                 Field::Text(TextField::SiteWithout) => {
                     doc.add_pre_tokenized_text(tantivy_field, site.clone())
                 }
@@ -480,6 +538,18 @@ impl Html {
                     };
                     doc.add_u64(tantivy_field, hash);
                 }
+                Field::Fast(FastField::MinHash1) => {
+                    doc.add_u64(tantivy_field, min_hash[0]);
+                }
+                Field::Fast(FastField::MinHash2) => {
+                    doc.add_u64(tantivy_field, min_hash[1]);
+                }
+                Field::Fast(FastField::MinHash3) => {
+                    doc.add_u64(tantivy_field, min_hash[2]);
+                }
+                Field::Fast(FastField::MinHash4) => {
+                    doc.add_u64(tantivy_field, min_hash[3]);
+                }
                 Field::Fast(FastField::NumPathAndQuerySlashes) => {
                     let num_slashes = self
                         .url()
@@ -506,6 +576,12 @@ impl Html {
 
                     doc.add_u64(tantivy_field, num_digits as u64);
                 }
+                Field::Fast(FastField::NumPathAndQueryChars) => {
+                    let num_chars = self.url().path().chars().count()
+                        + self.url().query().unwrap_or_default().chars().count();
+
+                    doc.add_u64(tantivy_field, num_chars as u64);
+                }
                 Field::Fast(FastField::LikelyHasAds) => {
                     doc.add_u64(tantivy_field, self.likely_has_ads() as u64);
                 }
@@ -520,6 +596,8 @@ impl Html {
                 }
                 Field::Text(TextField::BacklinkText)
                 | Field::Text(TextField::SafetyClassification)
+                | Field::Text(TextField::UrlCharNgrams)
+                | Field::Text(TextField::CleanBodyCased)
                 | Field::Text(TextField::InsertionTimestamp)
                 | Field::Fast(FastField::HostCentrality)
                 | Field::Fast(FastField::HostCentralityRank)
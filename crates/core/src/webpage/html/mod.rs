@@ -14,7 +14,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::{enum_map::EnumSet, Result};
+use crate::{enum_map::EnumSet, Error, Result};
 use chrono::{DateTime, FixedOffset, Utc};
 use itertools::Itertools;
 use kuchiki::{traits::TendrilSink, NodeRef};
@@ -38,6 +38,10 @@ pub static URL_REGEX: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(
     Regex::new(r"(((http|ftp|https):/{2})+(([0-9a-z_-]+\.)+(aero|asia|biz|cat|com|coop|edu|gov|info|int|jobs|mil|mobi|museum|name|net|org|pro|tel|travel|ac|ad|ae|af|ag|ai|al|am|an|ao|aq|ar|as|at|au|aw|ax|az|ba|bb|bd|be|bf|bg|bh|bi|bj|bm|bn|bo|br|bs|bt|bv|bw|by|bz|ca|cc|cd|cf|cg|ch|ci|ck|cl|cm|cn|co|cr|cu|cv|cx|cy|cz|cz|de|dj|dk|dm|do|dz|ec|ee|eg|er|es|et|eu|fi|fj|fk|fm|fo|fr|ga|gb|gd|ge|gf|gg|gh|gi|gl|gm|gn|gp|gq|gr|gs|gt|gu|gw|gy|hk|hm|hn|hr|ht|hu|id|ie|il|im|in|io|iq|ir|is|it|je|jm|jo|jp|ke|kg|kh|ki|km|kn|kp|kr|kw|ky|kz|la|lb|lc|li|lk|lr|ls|lt|lu|lv|ly|ma|mc|md|me|mg|mh|mk|ml|mn|mn|mo|mp|mr|ms|mt|mu|mv|mw|mx|my|mz|na|nc|ne|nf|ng|ni|nl|no|np|nr|nu|nz|nom|pa|pe|pf|pg|ph|pk|pl|pm|pn|pr|ps|pt|pw|py|qa|re|ra|rs|ru|rw|sa|sb|sc|sd|se|sg|sh|si|sj|sj|sk|sl|sm|sn|so|sr|st|su|sv|sy|sz|tc|td|tf|tg|th|tj|tk|tl|tm|tn|to|tp|tr|tt|tv|tw|tz|ua|ug|uk|us|uy|uz|va|vc|ve|vg|vi|vn|vu|wf|ws|ye|yt|yu|za|zm|zw|arpa)(:[0-9]+)?((/([~0-9a-zA-Z\#\+%@\./_-]+))?(\?[0-9a-zA-Z\+%@/&\[\];=_-]+)?)?))\b").unwrap()
 });
 
+/// Inputs larger than this are rejected by [`Html::parse`] to bound the memory
+/// used while building the DOM.
+pub const DEFAULT_MAX_HTML_SIZE: usize = 8 * 1024 * 1024; // 8 mb
+
 #[derive(Debug)]
 pub struct Html {
     url: Url,
@@ -46,6 +50,9 @@ pub struct Html {
     clean_text: Option<String>,
     lang: Option<Lang>,
     robots: Option<EnumSet<RobotsMeta>>,
+    raw_title: Option<String>,
+    clean_title: Option<String>,
+    use_clean_title: bool,
 }
 
 impl Html {
@@ -63,6 +70,22 @@ impl Html {
     }
 
     pub fn parse_without_text(html: &str, url: &str) -> Result<Self> {
+        Self::parse_without_text_with_max_size(html, url, DEFAULT_MAX_HTML_SIZE)
+    }
+
+    pub fn parse_without_text_with_max_size(
+        html: &str,
+        url: &str,
+        max_size: usize,
+    ) -> Result<Self> {
+        if html.len() > max_size {
+            return Err(Error::HtmlTooLarge {
+                size: html.len(),
+                max: max_size,
+            }
+            .into());
+        }
+
         let root = kuchiki::parse_html().one(html);
 
         let mut url = Url::parse(url)?;
@@ -75,6 +98,9 @@ impl Html {
             lang: None,
             url,
             robots: None,
+            raw_title: None,
+            clean_title: None,
+            use_clean_title: false,
         };
 
         let queries: Vec<_> = res
@@ -104,6 +130,12 @@ impl Html {
 
         res.robots = res.parse_robots_meta();
 
+        res.raw_title = res.parse_title();
+        res.clean_title = res
+            .raw_title
+            .as_ref()
+            .map(|title| strip_site_suffix(title, &res.url).unwrap_or_else(|| title.clone()));
+
         Ok(res)
     }
 
@@ -151,7 +183,7 @@ impl Html {
         }
     }
 
-    pub fn title(&self) -> Option<String> {
+    fn parse_title(&self) -> Option<String> {
         if let Some(title) = self.root.select_first("title") {
             let title = title.text_contents().trim().to_string();
             if title.is_empty() {
@@ -164,6 +196,32 @@ impl Html {
         }
     }
 
+    /// The title that is displayed and matched against. This is the
+    /// [clean title](Html::clean_title) if [`Html::set_use_clean_title`] is enabled.
+    pub fn title(&self) -> Option<String> {
+        if self.use_clean_title {
+            self.clean_title()
+        } else {
+            self.raw_title()
+        }
+    }
+
+    /// The title as written in the page.
+    pub fn raw_title(&self) -> Option<String> {
+        self.raw_title.clone()
+    }
+
+    /// The title with a trailing site name (e.g. "Result | Example.com") removed.
+    /// The suffix is only removed if it matches the domain of the page.
+    pub fn clean_title(&self) -> Option<String> {
+        self.clean_title.clone()
+    }
+
+    /// Use the [clean title](Html::clean_title) as the title of the page.
+    pub fn set_use_clean_title(&mut self, use_clean_title: bool) {
+        self.use_clean_title = use_clean_title;
+    }
+
     pub fn url(&self) -> &Url {
         &self.url
     }
@@ -447,6 +505,45 @@ impl Html {
     }
 }
 
+const TITLE_SEPARATORS: [&str; 5] = [" | ", " - ", " — ", " – ", " · "];
+
+fn normalize_site_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn strip_site_suffix(title: &str, url: &Url) -> Option<String> {
+    let (idx, sep) = TITLE_SEPARATORS
+        .iter()
+        .filter_map(|sep| title.rfind(sep).map(|idx| (idx, sep)))
+        .max_by_key(|(idx, _)| *idx)?;
+
+    let prefix = title[..idx].trim();
+    let suffix = normalize_site_name(&title[idx + sep.len()..]);
+
+    if prefix.is_empty() || suffix.is_empty() {
+        return None;
+    }
+
+    let root_domain = url.root_domain()?;
+    let domain_name = root_domain
+        .strip_suffix(url.tld().unwrap_or_default())
+        .unwrap_or(root_domain)
+        .trim_end_matches('.');
+
+    let matches_site = [root_domain, domain_name, url.normalized_host()?]
+        .into_iter()
+        .any(|name| normalize_site_name(name) == suffix);
+
+    if matches_site {
+        Some(prefix.to_string())
+    } else {
+        None
+    }
+}
+
 fn find_recipe_first_ingredient_tag_id(
     schemas: &[schema_org::Item],
     root: &NodeRef,
@@ -494,6 +591,7 @@ mod tests {
 
     use crate::{
         schema::create_schema,
+        tokenizer::DEFAULT_MAX_TOKEN_LEN,
         webpage::{url_ext::UrlExt, Link},
     };
 
@@ -740,7 +838,9 @@ mod tests {
         assert!(!webpage.all_text().unwrap().is_empty());
 
         let schema = create_schema();
-        webpage.into_tantivy(&schema).unwrap();
+        webpage
+            .into_tantivy(&schema, DEFAULT_MAX_TOKEN_LEN)
+            .unwrap();
     }
 
     #[test]
@@ -1136,4 +1236,67 @@ mod tests {
 
         assert!(html.likely_has_paywall());
     }
+
+    #[test]
+    fn max_html_size() {
+        let html = format!(
+            "<html><head><title>Test</title></head><body>{}</body></html>",
+            CONTENT.repeat(100)
+        );
+
+        assert!(
+            Html::parse_without_text_with_max_size(&html, "https://example.com/", html.len())
+                .is_ok()
+        );
+
+        let err = Html::parse_without_text_with_max_size(&html, "https://example.com/", 1024)
+            .unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::HtmlTooLarge { size, max: 1024 }) if *size == html.len()
+        ));
+    }
+
+    #[test]
+    fn clean_title() {
+        let clean = |title: &str, url: &str| {
+            Html::parse(
+                &format!("<html><head><title>{title}</title></head></html>"),
+                url,
+            )
+            .unwrap()
+            .clean_title()
+            .unwrap()
+        };
+
+        assert_eq!(
+            clean("Result — Example.com", "https://www.example.com/a"),
+            "Result"
+        );
+        assert_eq!(clean("Result | Example", "https://example.com/a"), "Result");
+        assert_eq!(
+            clean("Result - BBC News - BBC", "https://www.bbc.co.uk/news"),
+            "Result - BBC News"
+        );
+        assert_eq!(
+            clean("Rust - The Book", "https://example.com/a"),
+            "Rust - The Book"
+        );
+        assert_eq!(clean("| Example", "https://example.com/"), "| Example");
+        assert_eq!(clean("Example", "https://example.com/"), "Example");
+
+        let mut html = Html::parse(
+            "<html><head><title>Result | Example.com</title></head></html>",
+            "https://www.example.com/a",
+        )
+        .unwrap();
+
+        assert_eq!(html.raw_title().unwrap(), "Result | Example.com");
+        assert_eq!(html.title().unwrap(), "Result | Example.com");
+
+        html.set_use_clean_title(true);
+        assert_eq!(html.title().unwrap(), "Result");
+        assert_eq!(html.raw_title().unwrap(), "Result | Example.com");
+    }
 }
@@ -36,7 +36,7 @@ pub mod region;
 pub mod safety_classifier;
 pub mod schema_org;
 pub mod url_ext;
-pub use self::html::Html;
+pub use self::html::{Html, DEFAULT_MAX_HTML_SIZE};
 
 #[derive(Debug)]
 pub struct Webpage {
@@ -101,12 +101,16 @@ impl Webpage {
         })
     }
 
-    pub fn into_tantivy(self, schema: &tantivy::schema::Schema) -> Result<TantivyDocument> {
+    pub fn into_tantivy(
+        self,
+        schema: &tantivy::schema::Schema,
+        max_token_len: usize,
+    ) -> Result<TantivyDocument> {
         let region = Region::guess_from(&self);
 
         let dmoz_description = self.dmoz_description();
 
-        let mut doc = self.html.into_tantivy(schema)?;
+        let mut doc = self.html.into_tantivy(schema, max_token_len)?;
 
         if let Ok(region) = region {
             doc.add_u64(